@@ -2,32 +2,41 @@ extern crate base64;
 extern crate byteorder;
 extern crate rand;
 
+pub mod poly1305_key_reuse;
 pub mod query_string;
+pub mod spn_cryptanalysis;
+pub mod sweet32;
 pub mod tools;
+pub mod wep;
 
 // some of the below tests are quite slow.. skip after the solution is found
-// pub mod problem3;
+pub mod problem3;
 // pub mod problem4;
 // pub mod problem5;
-// pub mod problem6;
+pub mod problem6;
 // pub mod problem7;
-// pub mod problem8;
+pub mod problem8;
 // pub mod problem9;
 // pub mod problem10;
-// pub mod problem11;
-// pub mod problem12;
+pub mod problem11;
+pub mod problem12;
 // pub mod problem13;
 // pub mod problem14;
 // pub mod problem16;
-// pub mod problem17;
-// pub mod problem19;
-// pub mod problem20;
+pub mod problem17;
+pub mod problem19;
+pub mod problem20;
 // pub mod problem22;
 // pub mod problem23;
 // pub mod problem24;
 // pub mod problem25;
-// pub mod problem26;
+pub mod problem26;
 // pub mod problem27;
-// pub mod problem29;
-// pub mod problem30;
+pub mod problem29;
+pub mod problem30;
 pub mod problem31;
+pub mod problem52;
+pub mod problem53;
+pub mod problem54;
+pub mod problem55;
+pub mod problem56;
@@ -0,0 +1,460 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::aes;
+use crustopals::tools::chacha20_poly1305;
+use crustopals::tools::poly1305;
+use crustopals::tools::poly1305::Fe1305;
+use crustopals::tools::CryptoError;
+
+// Poly1305 one time key reuse. A tag is t = (h(r) mod p + s) mod 2^128 with
+// h(r) = c_1 r^q + ... + c_q r over the message blocks and p = 2^130 - 5.
+// Two messages tagged under the same (r, s) give
+//   h_1(r) - h_2(r) == t_1 - t_2 + k 2^128 (mod p)
+// for some small k: s cancels out and r is a root of a known polynomial.
+// ChaCha20-Poly1305 derives (r, s) from the key and nonce, so a repeated
+// nonce hands over the one time key and, with it, tags for any message;
+// the integrity counterpart of problem19/20 breaking CTR confidentiality.
+
+// p and (p - 1) / 2 as little endian exponents
+const P: [u8; 17] = [
+  0xfb, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+  0xff, 0xff, 0xff, 0x03,
+];
+const HALF_P_MINUS_1: [u8; 17] = [
+  0xfd, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+  0xff, 0xff, 0xff, 0x01,
+];
+
+// A polynomial over GF(2^130 - 5), lowest degree coefficient first and
+// without trailing zeros.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+  coefficients: Vec<Fe1305>,
+}
+
+impl Polynomial {
+  pub fn new(mut coefficients: Vec<Fe1305>) -> Polynomial {
+    while coefficients.last().is_some_and(|c| c.is_zero()) {
+      coefficients.pop();
+    }
+    Polynomial { coefficients }
+  }
+
+  pub fn constant(c: Fe1305) -> Polynomial {
+    Polynomial::new(vec![c])
+  }
+
+  pub fn x() -> Polynomial {
+    Polynomial::new(vec![Fe1305::zero(), Fe1305::one()])
+  }
+
+  pub fn coefficients(&self) -> &[Fe1305] {
+    &self.coefficients
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.coefficients.is_empty()
+  }
+
+  // None for the zero polynomial.
+  pub fn degree(&self) -> Option<usize> {
+    self.coefficients.len().checked_sub(1)
+  }
+
+  pub fn evaluate(&self, x: &Fe1305) -> Fe1305 {
+    self
+      .coefficients
+      .iter()
+      .rev()
+      .fold(Fe1305::zero(), |acc, c| acc.mul(x).add(c))
+  }
+
+  pub fn add(&self, other: &Polynomial) -> Polynomial {
+    self.combine(other, |a, b| a.add(b))
+  }
+
+  pub fn sub(&self, other: &Polynomial) -> Polynomial {
+    self.combine(other, |a, b| a.sub(b))
+  }
+
+  pub fn mul(&self, other: &Polynomial) -> Polynomial {
+    if self.is_zero() || other.is_zero() {
+      return Polynomial::new(vec![]);
+    }
+    let mut product =
+      vec![Fe1305::zero(); self.coefficients.len() + other.coefficients.len()];
+    for (i, a) in self.coefficients.iter().enumerate() {
+      for (j, b) in other.coefficients.iter().enumerate() {
+        product[i + j] = product[i + j].add(&a.mul(b));
+      }
+    }
+    Polynomial::new(product)
+  }
+
+  // Quotient and remainder of long division.
+  pub fn div_rem(&self, divisor: &Polynomial) -> (Polynomial, Polynomial) {
+    let divisor_degree =
+      divisor.degree().expect("division by the zero polynomial");
+    if self.coefficients.len() <= divisor_degree {
+      return (Polynomial::new(vec![]), self.clone());
+    }
+    let lead_inverse = divisor.coefficients[divisor_degree]
+      .inverse()
+      .expect("leading coefficients are non zero");
+    let mut remainder = self.coefficients.clone();
+    let mut quotient =
+      vec![Fe1305::zero(); self.coefficients.len() - divisor_degree];
+    for shift in (0..quotient.len()).rev() {
+      let factor = remainder[shift + divisor_degree].mul(&lead_inverse);
+      quotient[shift] = factor;
+      for (i, c) in divisor.coefficients.iter().enumerate() {
+        remainder[shift + i] = remainder[shift + i].sub(&c.mul(&factor));
+      }
+    }
+    (Polynomial::new(quotient), Polynomial::new(remainder))
+  }
+
+  pub fn monic(&self) -> Polynomial {
+    match self.coefficients.last().and_then(|lead| lead.inverse()) {
+      Some(lead_inverse) => Polynomial::new(
+        self
+          .coefficients
+          .iter()
+          .map(|c| c.mul(&lead_inverse))
+          .collect(),
+      ),
+      None => self.clone(),
+    }
+  }
+
+  // The monic greatest common divisor.
+  pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+    let mut a = self.clone();
+    let mut b = other.clone();
+    while !b.is_zero() {
+      let remainder = a.div_rem(&b).1;
+      a = b;
+      b = remainder;
+    }
+    a.monic()
+  }
+
+  // self^exponent mod modulus, for a little endian exponent.
+  pub fn pow_mod(&self, exponent: &[u8], modulus: &Polynomial) -> Polynomial {
+    let base = self.div_rem(modulus).1;
+    let mut result = Polynomial::constant(Fe1305::one()).div_rem(modulus).1;
+    for byte in exponent.iter().rev() {
+      for bit in (0..8).rev() {
+        result = result.mul(&result).div_rem(modulus).1;
+        if (byte >> bit) & 1 == 1 {
+          result = result.mul(&base).div_rem(modulus).1;
+        }
+      }
+    }
+    result
+  }
+
+  // Every distinct root in GF(p). x^p - x vanishes on the whole field, so
+  // its gcd with self is the product of self's linear factors, which
+  // Cantor-Zassenhaus then splits apart.
+  pub fn roots(&self) -> Vec<Fe1305> {
+    if self.degree().is_none_or(|degree| degree == 0) {
+      return vec![];
+    }
+    let x = Polynomial::x();
+    let linear_factors = self.gcd(&x.pow_mod(&P, self).sub(&x));
+    let mut roots = vec![];
+    split_linear_factors(&linear_factors, &mut roots);
+    roots
+  }
+
+  fn combine<F: Fn(&Fe1305, &Fe1305) -> Fe1305>(
+    &self,
+    other: &Polynomial,
+    f: F,
+  ) -> Polynomial {
+    let len = self.coefficients.len().max(other.coefficients.len());
+    let zero = Fe1305::zero();
+    Polynomial::new(
+      (0..len)
+        .map(|i| {
+          f(
+            self.coefficients.get(i).unwrap_or(&zero),
+            other.coefficients.get(i).unwrap_or(&zero),
+          )
+        })
+        .collect(),
+    )
+  }
+}
+
+// Splits a monic product of distinct linear factors: (x + a)^((p - 1) / 2)
+// is 1 at the roots where x + a is a square and -1 at the others, so for a
+// random a the gcd with (x + a)^((p - 1) / 2) - 1 takes about half of them.
+fn split_linear_factors(factors: &Polynomial, roots: &mut Vec<Fe1305>) {
+  let degree = match factors.degree() {
+    Some(degree) if degree > 0 => degree,
+    _ => return,
+  };
+  if degree == 1 {
+    roots.push(Fe1305::zero().sub(&factors.coefficients[0]));
+    return;
+  }
+  loop {
+    let a = Fe1305::from_bytes(&aes::generate_rand_bytes(16));
+    let shifted = Polynomial::new(vec![a, Fe1305::one()]);
+    let half = shifted
+      .pow_mod(&HALF_P_MINUS_1, factors)
+      .sub(&Polynomial::constant(Fe1305::one()));
+    let divisor = factors.gcd(&half);
+    if divisor.degree().is_some_and(|d| d > 0 && d < degree) {
+      split_linear_factors(&divisor, roots);
+      split_linear_factors(&factors.div_rem(&divisor).0, roots);
+      return;
+    }
+  }
+}
+
+// h(x) = c_1 x^q + ... + c_q x, the polynomial Poly1305 evaluates at r.
+pub fn message_polynomial(message: &[u8]) -> Polynomial {
+  let mut coefficients = poly1305::message_blocks(message);
+  coefficients.push(Fe1305::zero());
+  coefficients.reverse();
+  Polynomial::new(coefficients)
+}
+
+// Every one time key r || s consistent with both (message, tag) pairs;
+// normally just the one that was used.
+pub fn recover_keys(
+  first: (&[u8], &[u8]),
+  second: (&[u8], &[u8]),
+) -> Result<Vec<Vec<u8>>, CryptoError> {
+  let (first_message, first_tag) = first;
+  let (second_message, second_tag) = second;
+  for tag in [first_tag, second_tag].iter() {
+    if tag.len() != 16 {
+      return Err(CryptoError::LengthMismatch {
+        left: 16,
+        right: tag.len(),
+      });
+    }
+  }
+  let first_polynomial = message_polynomial(first_message);
+  let difference = first_polynomial.sub(&message_polynomial(second_message));
+  let first_tag = LittleEndian::read_u128(first_tag);
+  let tag_difference =
+    first_tag.wrapping_sub(LittleEndian::read_u128(second_tag));
+  let mut tag_difference_bytes = [0u8; 16];
+  LittleEndian::write_u128(&mut tag_difference_bytes, tag_difference);
+
+  // both h(r) mod p lie in [0, p) and p < 2^130, so the true difference is
+  // the tag difference plus k 2^128 for some k in -4..4
+  let mut wrap_bytes = [0u8; 17];
+  wrap_bytes[16] = 1;
+  let wrap = Fe1305::from_bytes(&wrap_bytes);
+  let mut target = Fe1305::from_bytes(&tag_difference_bytes)
+    .sub(&wrap.mul(&Fe1305::from_bytes(&[4])));
+  let mut keys: Vec<Vec<u8>> = vec![];
+  for _ in 0..8 {
+    let candidates = difference.sub(&Polynomial::constant(target)).roots();
+    for r in candidates {
+      let r_bytes = r.to_bytes();
+      if r_bytes[16] != 0 || poly1305::clamp(&r_bytes[..16])? != r {
+        continue;
+      }
+      let h = LittleEndian::read_u128(
+        &first_polynomial.evaluate(&r).to_bytes()[..16],
+      );
+      let mut key = r_bytes[..16].to_vec();
+      key.extend(vec![0u8; 16]);
+      LittleEndian::write_u128(&mut key[16..], first_tag.wrapping_sub(h));
+      let second_matches =
+        poly1305::poly1305(&key, second_message)?[..] == second_tag[..];
+      if second_matches && !keys.contains(&key) {
+        keys.push(key);
+      }
+    }
+    target = target.add(&wrap);
+  }
+  Ok(keys)
+}
+
+// A tag for any message under the key shared by the two tagged messages,
+// or None if no key fits both.
+pub fn forge_tag(
+  first: (&[u8], &[u8]),
+  second: (&[u8], &[u8]),
+  message: &[u8],
+) -> Result<Option<Vec<u8>>, CryptoError> {
+  match recover_keys(first, second)?.first() {
+    Some(key) => Ok(Some(poly1305::poly1305(key, message)?)),
+    None => Ok(None),
+  }
+}
+
+// Given two (aad, ciphertext || tag) messages sealed with ChaCha20-Poly1305
+// under one key and nonce, seals a ciphertext and aad of our choosing.
+pub fn forge_chacha20_poly1305(
+  first: (&[u8], &[u8]),
+  second: (&[u8], &[u8]),
+  aad: &[u8],
+  ciphertext: &[u8],
+) -> Result<Option<Vec<u8>>, CryptoError> {
+  let (first_data, first_tag) = authenticated_data(first)?;
+  let (second_data, second_tag) = authenticated_data(second)?;
+  let tag = forge_tag(
+    (&first_data, &first_tag),
+    (&second_data, &second_tag),
+    &chacha20_poly1305::mac_data(aad, ciphertext),
+  )?;
+  Ok(tag.map(|tag| [ciphertext, &tag[..]].concat()))
+}
+
+// The Poly1305 input and tag behind an (aad, ciphertext || tag) message.
+// Too short to hold a tag fails as `chacha20_poly1305::decrypt` would.
+fn authenticated_data(
+  message: (&[u8], &[u8]),
+) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+  let (aad, sealed) = message;
+  if sealed.len() < chacha20_poly1305::TAG_SIZE {
+    return Err(CryptoError::AuthenticationFailed);
+  }
+  let (ciphertext, tag) =
+    sealed.split_at(sealed.len() - chacha20_poly1305::TAG_SIZE);
+  Ok((chacha20_poly1305::mac_data(aad, ciphertext), tag.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn random_element() -> Fe1305 {
+    Fe1305::from_bytes(&aes::generate_rand_bytes(16))
+  }
+
+  #[test]
+  fn it_finds_the_distinct_roots() {
+    let a = random_element();
+    let b = random_element();
+    let x = Polynomial::x();
+    let linear = |root: &Fe1305| x.sub(&Polynomial::constant(*root));
+    // 7 (x - a)^2 (x - b) (x^2 + x + 2)
+    let poly = Polynomial::constant(Fe1305::from_bytes(&[7]))
+      .mul(&linear(&a))
+      .mul(&linear(&a))
+      .mul(&linear(&b))
+      .mul(&Polynomial::new(vec![
+        Fe1305::from_bytes(&[2]),
+        Fe1305::one(),
+        Fe1305::one(),
+      ]));
+    let roots = poly.roots();
+
+    assert!(roots.contains(&a) && roots.contains(&b));
+    for root in roots.iter() {
+      assert!(poly.evaluate(root).is_zero());
+    }
+    assert_eq!(Polynomial::constant(Fe1305::one()).roots(), vec![]);
+  }
+
+  #[test]
+  fn it_divides_polynomials() {
+    let a = Polynomial::new((1..6).map(|i| Fe1305::from_bytes(&[i])).collect());
+    let b = Polynomial::new(vec![random_element(), random_element()]);
+    let (quotient, remainder) = a.div_rem(&b);
+
+    assert_eq!(quotient.mul(&b).add(&remainder), a);
+    assert_eq!(remainder.degree(), Some(0));
+    assert_eq!(a.mul(&b).gcd(&b), b.monic());
+  }
+
+  #[test]
+  fn it_forges_poly1305_tags_after_key_reuse() {
+    let key = aes::generate_rand_bytes(32);
+    let first = "attack at dawn, bring the usual amount of coffee".as_bytes();
+    let second = "retreat at dusk, the coffee ran out".as_bytes();
+    let target = "send all of the coffee to the attacker".as_bytes();
+    let first_tag = poly1305::poly1305(&key, first).unwrap();
+    let second_tag = poly1305::poly1305(&key, second).unwrap();
+
+    let keys =
+      recover_keys((first, &first_tag), (second, &second_tag)).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(&keys[0][16..], &key[16..]);
+    assert_eq!(
+      forge_tag((first, &first_tag), (second, &second_tag), target),
+      Ok(Some(poly1305::poly1305(&key, target).unwrap()))
+    );
+  }
+
+  #[test]
+  fn it_rejects_tags_of_the_wrong_size() {
+    let message = "any message".as_bytes();
+
+    assert_eq!(
+      recover_keys((message, &[0u8; 16]), (message, &[0u8; 15])),
+      Err(CryptoError::LengthMismatch {
+        left: 16,
+        right: 15
+      })
+    );
+    assert_eq!(
+      forge_chacha20_poly1305((b"", &[0u8; 15]), (b"", &[0u8; 16]), b"", b""),
+      Err(CryptoError::AuthenticationFailed)
+    );
+  }
+
+  #[test]
+  fn it_finds_nothing_under_different_keys() {
+    let message = "the same message".as_bytes();
+    let other = "under two different one time keys".as_bytes();
+    let first_tag =
+      poly1305::poly1305(&aes::generate_rand_bytes(32), message).unwrap();
+    let second_tag =
+      poly1305::poly1305(&aes::generate_rand_bytes(32), other).unwrap();
+
+    assert_eq!(
+      recover_keys((message, &first_tag), (other, &second_tag)),
+      Ok(vec![])
+    );
+  }
+
+  #[test]
+  fn it_forges_chacha20_poly1305_messages_after_nonce_reuse() {
+    let key = aes::generate_rand_bytes(32);
+    let nonce = aes::generate_rand_bytes(12);
+    let known_plaintext = "user=alice;role=guest;motd=hello world".as_bytes();
+    let first_aad = "session 1".as_bytes();
+    let first =
+      chacha20_poly1305::encrypt(&key, &nonce, first_aad, known_plaintext)
+        .unwrap();
+    let second_aad = "session 2".as_bytes();
+    let second =
+      chacha20_poly1305::encrypt(&key, &nonce, second_aad, b"ping").unwrap();
+
+    // the reused keystream turns the known plaintext into chosen ciphertext
+    let wanted = "user=alice;role=admin".as_bytes();
+    let ciphertext: Vec<u8> = wanted
+      .iter()
+      .zip(known_plaintext.iter().zip(first.iter()))
+      .map(|(w, (p, c))| w ^ p ^ c)
+      .collect();
+    let aad = "session 3".as_bytes();
+    let forged = forge_chacha20_poly1305(
+      (first_aad, &first),
+      (second_aad, &second),
+      aad,
+      &ciphertext,
+    )
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(
+      chacha20_poly1305::decrypt(&key, &nonce, aad, &forged).unwrap(),
+      wanted
+    );
+    assert_eq!(
+      chacha20_poly1305::decrypt(&key, &nonce, b"session 4", &forged),
+      Err(CryptoError::AuthenticationFailed)
+    );
+  }
+}
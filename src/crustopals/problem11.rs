@@ -20,13 +20,13 @@ pub fn random_aes_encryption(message: String) -> (String, Vec<u8>) {
     println!("doing ecb");
     (
       String::from("ecb"),
-      aes::encrypt_message_ecb(&msg_bytes, &random_key),
+      aes::encrypt_message_ecb(&msg_bytes, &random_key).unwrap(),
     )
   } else {
     let random_iv = aes::generate_iv();
     (
       String::from("cbc"),
-      aes::encrypt_message_cbc(&msg_bytes, &random_key, &random_iv),
+      aes::encrypt_message_cbc(&msg_bytes, &random_key, &random_iv).unwrap(),
     )
   }
 }
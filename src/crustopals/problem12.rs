@@ -12,20 +12,24 @@ qdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK".to_string();
 
 pub fn aes_128_ecb_rand_key_oracle(mut message: Vec<u8>) -> Vec<u8> {
   message.extend(base64::decode(&APPEND_STR.to_string()).unwrap());
-  aes::encrypt_message_ecb(&message, &RANDOM_KEY.to_vec())
+  aes::encrypt_message_ecb(&message, &RANDOM_KEY.to_vec()).unwrap()
 }
 
 pub fn discover_blocksize() -> usize {
+  discover_oracle_blocksize(&aes_128_ecb_rand_key_oracle)
+}
+
+pub fn discover_oracle_blocksize<F: Fn(Vec<u8>) -> Vec<u8>>(
+  oracle: &F,
+) -> usize {
   let test_byte: u8 = 65;
-  let ciphertext_len1 =
-    aes_128_ecb_rand_key_oracle(build_byte_vec(test_byte, 1)).len();
-  let mut ciphertext_len2 =
-    aes_128_ecb_rand_key_oracle(build_byte_vec(test_byte, 2)).len();
+  let ciphertext_len1 = oracle(build_byte_vec(test_byte, 1)).len();
+  let mut ciphertext_len2 = oracle(build_byte_vec(test_byte, 2)).len();
   let mut repeats = 2;
   while ciphertext_len2 <= ciphertext_len1 {
     repeats = repeats + 1;
     let pt = build_byte_vec(test_byte, repeats);
-    ciphertext_len2 = aes_128_ecb_rand_key_oracle(pt).len();
+    ciphertext_len2 = oracle(pt).len();
   }
   ciphertext_len2 - ciphertext_len1
 }
@@ -86,6 +90,7 @@ fn find_next_pt_byte(oracle_msg: Vec<u8>, block: &[u8]) -> Result<u8, &str> {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crustopals::tools::des::Des;
 
   #[test]
   fn cracks_the_oracle() {
@@ -100,6 +105,15 @@ mod tests {
     assert_eq!(discover_blocksize(), 16);
   }
 
+  #[test]
+  fn can_determine_the_blocksize_of_a_des_oracle() {
+    let des = Des::new(&des::generate_key()).unwrap();
+    let des_oracle =
+      |message: Vec<u8>| block_cipher::encrypt_ecb(&des, &message);
+
+    assert_eq!(discover_oracle_blocksize(&des_oracle), 8);
+  }
+
   #[test]
   fn confirms_ecb_mode() {
     assert_eq!(test_ecb(), true);
@@ -31,25 +31,20 @@ impl Profile {
     filtered_email
   }
 
-  pub fn from_query_string(query_str: String) -> Result<Profile, String> {
+  pub fn from_query_string(query_str: String) -> Result<Profile, CryptoError> {
     let parsed_query_string = parse_kv_string(query_str);
-    if parsed_query_string.contains_key("email")
-      && parsed_query_string.contains_key("uid")
-    {
-      Ok(Profile {
-        email: parsed_query_string.get("email").unwrap().to_string(),
-        uid: parsed_query_string
-          .get("uid")
-          .unwrap()
-          .parse::<i32>()
-          .unwrap(),
+    match (parsed_query_string.get("email"), parsed_query_string.get("uid")) {
+      (Some(email), Some(uid)) => Ok(Profile {
+        email: email.to_string(),
+        uid: uid.parse::<i32>()?,
         role: parsed_query_string
           .get("role")
           .unwrap_or(&"user".to_string())
           .to_string(),
-      })
-    } else {
-      Err("Doesn't have the correct params".to_string())
+      }),
+      _ => Err(CryptoError::Parse(
+        "Doesn't have the correct params".to_string(),
+      )),
     }
   }
 
@@ -57,17 +52,19 @@ impl Profile {
     format!("email={}&uid={}&role={}", self.email, self.uid, self.role)
   }
 
-  pub fn encrypt(&self) -> Vec<u8> {
+  pub fn encrypt(&self) -> Result<Vec<u8>, CryptoError> {
     aes::encrypt_message_ecb(
       &self.to_query_string().as_bytes(),
       &RANDOM_KEY.to_vec(),
     )
   }
 
-  pub fn from_encrypted_blob(encrypted_qs: Vec<u8>) -> Result<Profile, String> {
+  pub fn from_encrypted_blob(
+    encrypted_qs: Vec<u8>,
+  ) -> Result<Profile, CryptoError> {
     let decrypted_qs =
-      aes::decrypt_message_ecb(&encrypted_qs, &RANDOM_KEY.to_vec());
-    Profile::from_query_string(bytes_to_string(decrypted_qs.unwrap()))
+      aes::decrypt_message_ecb(&encrypted_qs, &RANDOM_KEY.to_vec())?;
+    Profile::from_query_string(bytes_to_string(decrypted_qs))
   }
 }
 
@@ -87,13 +84,14 @@ pub fn upgrade_user_to_admin_ciphertext() -> Vec<u8> {
   let padding = bytes_to_string(padding_bytes(11));
   let mut admin_string = "jack@gmailadmin".to_string();
   admin_string.push_str(&padding);
-  let admin_ciphertext = Profile::profile_for(admin_string).encrypt();
+  let admin_ciphertext = Profile::profile_for(admin_string).encrypt().unwrap();
   let admin_ct = admin_ciphertext.chunks(16).nth(1).unwrap();
   // normal format "email=jack@gmail.com&uid=10&role="
   // need to ensure this section is an even number of blocks, then can append
   // the admin block to it and pwn.
-  let ct =
-    Profile::profile_for("jack+extrapaddinggg@gmail.com".to_string()).encrypt();
+  let ct = Profile::profile_for("jack+extrapaddinggg@gmail.com".to_string())
+    .encrypt()
+    .unwrap();
   let ct_blocks = ct.chunks(16);
   let ct_blocks_len = ct_blocks.len();
   let mut crafted_ct: Vec<u8> = vec![];
@@ -117,7 +115,11 @@ pub fn parse_kv_string(kv_string: String) -> HashMap<String, String> {
   let mut i = 0;
   while i < kv_vec.len() {
     let kv_set = kv_vec[i].clone();
-    if kv_set.len() == 1 {
+    if kv_set.len() == 1 && i == kv_vec.len() - 1 {
+      // trailing term without a value
+      kv_vec[i] = vec![kv_set[0].to_string(), String::new()];
+      i += 1;
+    } else if kv_set.len() == 1 {
       // term has a literal '&'
       let removed = kv_vec.remove(i);
       let mut next_kv_set = kv_vec[i].clone();
@@ -182,6 +184,18 @@ mod tests {
     assert_eq!(result, kvs);
   }
 
+  #[test]
+  fn it_handles_a_trailing_term_without_a_value() {
+    let kv_string: String = "foo=bar&baz".to_string();
+    let mut kvs: HashMap<String, String> = HashMap::new();
+    kvs.insert("foo".to_string(), "bar".to_string());
+    kvs.insert("baz".to_string(), "".to_string());
+
+    let result = parse_kv_string(kv_string);
+
+    assert_eq!(result, kvs);
+  }
+
   #[test]
   fn formats_a_profile_as_a_query_string() {
     let profile = Profile {
@@ -210,6 +224,26 @@ mod tests {
     assert_eq!(result.unwrap(), profile);
   }
 
+  #[test]
+  fn rejects_query_strings_with_a_non_numeric_uid() {
+    let query_str = "email=jack@email.email&uid=ten&role=user".to_string();
+
+    match Profile::from_query_string(query_str) {
+      Err(CryptoError::Parse(_)) => (),
+      result => panic!("expected a parse error, got {:?}", result),
+    }
+  }
+
+  #[test]
+  fn rejects_query_strings_missing_params() {
+    let query_str = "email=jack@email.email&role=user".to_string();
+
+    match Profile::from_query_string(query_str) {
+      Err(CryptoError::Parse(_)) => (),
+      result => panic!("expected a parse error, got {:?}", result),
+    }
+  }
+
   #[test]
   fn generates_a_user_profile_from_an_email() {
     let email = "jack@gmail.com".to_string();
@@ -258,7 +292,7 @@ mod tests {
       uid: 10,
     };
 
-    let encrypted_blob = profile.encrypt();
+    let encrypted_blob = profile.encrypt().unwrap();
     let decrypted_profile = Profile::from_encrypted_blob(encrypted_blob);
 
     assert_eq!(decrypted_profile.unwrap(), profile);
@@ -15,7 +15,7 @@ pub fn aes_128_ecb_rand_prepend_oracle(message: Vec<u8>) -> Vec<u8> {
   let mut plaintext = random_bytes();
   plaintext.extend(message);
   plaintext.extend(base64::decode(&APPEND_STR.to_string()).unwrap());
-  aes::encrypt_message_ecb(&plaintext, &RANDOM_KEY.to_vec())
+  aes::encrypt_message_ecb(&plaintext, &RANDOM_KEY.to_vec()).unwrap()
 }
 
 pub fn crack_the_oracle() -> Vec<u8> {
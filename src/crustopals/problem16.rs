@@ -9,17 +9,16 @@ lazy_static! {
 pub fn attack_cbc_oracle() -> Vec<u8> {
   // need to manipulate the third block of ct to adjust the \x00's to ; and =
   let attack_string = "superspecialdata\x00admin\x00true";
-  let ct = query_string::cbc_encrypt(
-    &RANDOM_KEY,
-    &IV,
-    attack_string.as_bytes().to_vec(),
-  );
+  let ct =
+    query_string::cbc_encrypt(&RANDOM_KEY, &IV, attack_string.as_bytes())
+      .unwrap();
   let mut bitflipped_ct: Vec<u8> = vec![];
   for (i, block) in ct.chunks(16).enumerate() {
     if i == 2 {
       let with_flipped_bits =
         ";\x00\x00\x00\x00\x00=\x00\x00\x00\x00\x00\x00\x00\x00\x00";
-      let flipped_block = xor_bytes(block, with_flipped_bits.as_bytes());
+      let flipped_block =
+        xor_bytes(block, with_flipped_bits.as_bytes()).unwrap();
       bitflipped_ct.extend(flipped_block);
     } else {
       bitflipped_ct.extend(block);
@@ -39,7 +38,7 @@ mod tests {
     let admin_rights = cbc_decrypts_with_admin_rights(
       &RANDOM_KEY,
       &IV,
-      crack_cbc_to_upgrade_to_admin,
+      &crack_cbc_to_upgrade_to_admin,
     );
 
     assert!(admin_rights);
@@ -24,19 +24,27 @@ pub fn cbc_encrypt() -> Vec<u8> {
   let mut rng = thread_rng();
   let b64_msg = rng.choose(&messages).unwrap();
   let msg = base64::decode(b64_msg.clone()).unwrap();
-  aes::encrypt_message_cbc(&msg, &RANDOM_KEY.to_vec(), &IV.to_vec())
+  aes::encrypt_message_cbc(&msg, &RANDOM_KEY.to_vec(), &IV.to_vec()).unwrap()
 }
 
-pub fn prob17_cbc_decrypt(iv_and_ct: &[u8]) -> bool {
-  let iv = &iv_and_ct[0..16];
-  let ct = &iv_and_ct[16..];
+// Whether the padding is valid; anything else wrong with the ciphertext is
+// an error rather than a bad guess.
+pub fn prob17_cbc_decrypt(iv_and_ct: &[u8]) -> Result<bool, CryptoError> {
+  if iv_and_ct.len() < 16 {
+    return Err(CryptoError::InvalidIvLength {
+      expected: 16,
+      actual: iv_and_ct.len(),
+    });
+  }
+  let (iv, ct) = iv_and_ct.split_at(16);
   match aes::decrypt_message_cbc(ct, &RANDOM_KEY.to_vec(), iv) {
-    Ok(_) => true,
-    Err(_) => false,
+    Ok(_) => Ok(true),
+    Err(CryptoError::InvalidPadding) => Ok(false),
+    Err(err) => Err(err),
   }
 }
 
-pub fn crack_cbc_using_padding_oracle() -> Vec<u8> {
+pub fn crack_cbc_using_padding_oracle() -> Result<Vec<u8>, CryptoError> {
   // You can xor the last byte in the second to last ct block with a random
   // character.. if it comes out valid.. you know that the actual plaintext xor
   // the character you used is \x01.
@@ -46,28 +54,55 @@ pub fn crack_cbc_using_padding_oracle() -> Vec<u8> {
   // ciphertext, otherwise we'd everything but the first block
   let ct = cbc_encrypt();
   let iv = IV.to_vec();
-  let mut plaintext: Vec<u8> = vec![];
   let iv_with_ct = [&iv[..], &ct[..]].concat();
-  let num_blocks = ct.len() / 16;
+  let plaintext = padding_oracle_attack(&iv_with_ct, 16, &prob17_cbc_decrypt)?;
+  strip_pkcs7_padding(plaintext)
+}
+
+// Recovers the (still padded) plaintext of `iv_with_ct` for any block size,
+// given an oracle that reports whether a ciphertext (IV first) decrypts with
+// valid padding.
+pub fn padding_oracle_attack<F: Fn(&[u8]) -> Result<bool, CryptoError>>(
+  iv_with_ct: &[u8],
+  block_size: usize,
+  oracle: &F,
+) -> Result<Vec<u8>, CryptoError> {
+  // the IV and at least one block of ciphertext
+  if block_size == 0
+    || iv_with_ct.len() < 2 * block_size
+    || !iv_with_ct.len().is_multiple_of(block_size)
+  {
+    return Err(CryptoError::InvalidBlockLength {
+      block_size,
+      actual: iv_with_ct.len(),
+    });
+  }
+  let mut plaintext: Vec<u8> = vec![];
+  let num_blocks = iv_with_ct.len() / block_size - 1;
   for ct_block_idx in 0..num_blocks {
-    for byte in crack_block(ct_block_idx, &iv_with_ct) {
+    for byte in crack_block(ct_block_idx, iv_with_ct, block_size, oracle)? {
       plaintext.push(byte);
     }
   }
-  strip_pkcs7_padding(plaintext).unwrap()
+  Ok(plaintext)
 }
 
-fn crack_block(block_idx: usize, iv_with_ct: &[u8]) -> Vec<u8> {
+fn crack_block<F: Fn(&[u8]) -> Result<bool, CryptoError>>(
+  block_idx: usize,
+  iv_with_ct: &[u8],
+  block_size: usize,
+  oracle: &F,
+) -> Result<Vec<u8>, CryptoError> {
   let mut recovered_block: Vec<u8> = vec![];
   // here 0 yeilds IV..which is manipulated to recover block 0 of the CT
-  let blk_start = block_idx * 16;
-  let blk_end = blk_start + 16;
+  let blk_start = block_idx * block_size;
+  let blk_end = blk_start + block_size;
   let pre_target_ct: Vec<u8> = iv_with_ct[0..blk_start].to_vec();
   let manipulated_blk = iv_with_ct[blk_start..blk_end].to_vec();
-  let target_blk = iv_with_ct[blk_end..(blk_end + 16)].to_vec();
+  let target_blk = iv_with_ct[blk_end..(blk_end + block_size)].to_vec();
 
-  while recovered_block.len() < 16 {
-    let target_byte_idx = 16 - recovered_block.len() - 1;
+  while recovered_block.len() < block_size {
+    let target_byte_idx = block_size - recovered_block.len() - 1;
     let target_byte = manipulated_blk[target_byte_idx];
     let pre_target_ct =
       [&pre_target_ct[..], &manipulated_blk[0..target_byte_idx]].concat();
@@ -77,30 +112,28 @@ fn crack_block(block_idx: usize, iv_with_ct: &[u8]) -> Vec<u8> {
       &recovered_block,
       pad_byte,
     );
-    match recover_byte(
+    let byte_that_xors_pt_to_correct_padding = recover_byte(
       &pre_target_ct,
       &target_byte,
       &post_target_padding_bytes,
       &target_blk,
-    ) {
-      Ok(byte_that_xors_pt_to_correct_padding) => {
-        let recovered_byte = byte_that_xors_pt_to_correct_padding ^ pad_byte;
-        // need to shift this byte onto the front of the array.. :(
-        recovered_block =
-          [&vec![recovered_byte][..], &recovered_block[..]].concat();
-      }
-      Err(error) => panic!(error),
-    };
+      oracle,
+    )?;
+    let recovered_byte = byte_that_xors_pt_to_correct_padding ^ pad_byte;
+    // need to shift this byte onto the front of the array.. :(
+    recovered_block =
+      [&vec![recovered_byte][..], &recovered_block[..]].concat();
   }
-  recovered_block
+  Ok(recovered_block)
 }
 
-fn recover_byte(
+fn recover_byte<F: Fn(&[u8]) -> Result<bool, CryptoError>>(
   start_ct: &[u8],
   target_byte: &u8,
   padding_ct: &[u8],
   target_blk: &[u8],
-) -> Result<u8, String> {
+  oracle: &F,
+) -> Result<u8, CryptoError> {
   for byte in (0u8..=255).rev() {
     let test_byte = target_byte ^ byte;
     let modified_iv_and_ct = [
@@ -109,12 +142,14 @@ fn recover_byte(
       &padding_ct[..],
       &target_blk,
     ]
-      .concat();
-    if prob17_cbc_decrypt(&modified_iv_and_ct) {
+    .concat();
+    if oracle(&modified_iv_and_ct)? {
       return Ok(byte);
     }
   }
-  Err("Could not find a valid byte :(".to_string())
+  // the oracle rejected every guess, so it isn't a padding oracle for this
+  // ciphertext
+  Err(CryptoError::InvalidPadding)
 }
 
 fn gen_valid_padding(
@@ -126,13 +161,14 @@ fn gen_valid_padding(
   // you'd get the decryption oracle to decrypt to the padding string that
   // padding string will be the outcome. (since you've xored )
   let padding_vec = build_byte_vec(desired_pad, (desired_pad - 1) as usize);
-  let tmp_xor = xor_bytes(prev_blk_ct_bytes, pt_bytes);
-  xor_bytes(&tmp_xor, &padding_vec)
+  let tmp_xor = xor_bytes(prev_blk_ct_bytes, pt_bytes).unwrap();
+  xor_bytes(&tmp_xor, &padding_vec).unwrap()
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crustopals::tools::des::Des;
 
   #[test]
   fn it_can_decrypt_using_the_padding_oracle() {
@@ -148,10 +184,63 @@ mod tests {
       "000008ollin' in my five point oh".to_string(),
       "000009ith my rag-top down so my hair can blow".to_string(),
     ];
-    let result = crack_cbc_using_padding_oracle();
+    let result = crack_cbc_using_padding_oracle().unwrap();
     let result_str = bytes_to_string(result);
     println!("result: {:?}", result_str);
 
     assert!(possible_results.contains(&result_str));
   }
+
+  #[test]
+  fn it_can_decrypt_des_cbc_using_the_padding_oracle() {
+    let des = Des::new(&des::generate_key()).unwrap();
+    let iv = des::generate_iv();
+    let message = "eight byte blocks need padding too".as_bytes();
+    let ct = block_cipher::encrypt_cbc(&des, message, &iv).unwrap();
+    let oracle = |iv_and_ct: &[u8]| {
+      let (iv, ct) = iv_and_ct.split_at(8);
+      Ok(block_cipher::decrypt_cbc(&des, ct, iv).is_ok())
+    };
+
+    let iv_with_ct = [&iv[..], &ct[..]].concat();
+    let plaintext = padding_oracle_attack(&iv_with_ct, 8, &oracle).unwrap();
+
+    assert_eq!(unpad_bytes(plaintext, 8).unwrap(), message);
+  }
+
+  #[test]
+  fn the_oracle_rejects_input_shorter_than_the_iv() {
+    assert_eq!(
+      prob17_cbc_decrypt(&[0u8; 15]),
+      Err(CryptoError::InvalidIvLength {
+        expected: 16,
+        actual: 15
+      })
+    );
+  }
+
+  #[test]
+  fn it_needs_an_iv_and_whole_blocks() {
+    let oracle = |_: &[u8]| Ok(true);
+
+    for (len, block_size) in &[(32, 0), (16, 16), (40, 16), (0, 8)] {
+      assert_eq!(
+        padding_oracle_attack(&vec![0u8; *len], *block_size, &oracle),
+        Err(CryptoError::InvalidBlockLength {
+          block_size: *block_size,
+          actual: *len
+        })
+      );
+    }
+  }
+
+  #[test]
+  fn it_errors_when_the_oracle_accepts_nothing() {
+    let iv_with_ct = [0u8; 32];
+
+    assert_eq!(
+      padding_oracle_attack(&iv_with_ct, 16, &|_: &[u8]| Ok(false)),
+      Err(CryptoError::InvalidPadding)
+    );
+  }
 }
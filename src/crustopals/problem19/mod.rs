@@ -1,5 +1,7 @@
 use crustopals::base64;
 use crustopals::tools;
+use crustopals::tools::aes::Aes128;
+use crustopals::tools::stream_cipher::{Ctr, StreamCipher};
 use std::cmp;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -11,13 +13,23 @@ pub fn ciphertexts(filepath: String) -> Vec<Vec<u8>> {
     197, 248, 107, 98, 164, 254, 30, 153, 250, 41, 99, 32, 59, 15, 76, 220,
   ]; // tools::aes::generate_key();
   let nonce: Vec<u8> = vec![144, 226, 242, 95, 221, 181, 68, 198]; // tools::aes::generate_rand_bytes(8);
+  let ctr = Ctr::new(Aes128::new(&key).unwrap(), &nonce).unwrap();
+  encrypt_lines(filepath, &ctr)
+}
+
+// Encrypts every base64 line of the file under the same stream cipher,
+// i.e. with a fixed nonce.
+pub fn encrypt_lines<S: StreamCipher>(
+  filepath: String,
+  cipher: &S,
+) -> Vec<Vec<u8>> {
   let mut ciphertexts: Vec<Vec<u8>> = vec![];
   let file = File::open(filepath).unwrap();
   let reader = BufReader::new(file);
   for l in reader.lines() {
     let line = l.unwrap();
     let bytes = base64::decode(&line).unwrap();
-    ciphertexts.push(tools::aes::encrypt_ctr(&bytes, &key, &nonce));
+    ciphertexts.push(cipher.apply_keystream(&bytes));
   }
   ciphertexts
 }
@@ -29,7 +41,8 @@ pub fn targeted_guess(cts: Vec<Vec<u8>>) {
   let keystream_fragment = tools::xor_bytes(
     &relevant_ct[start_idx..(start_idx + guess.len())],
     &guess,
-  );
+  )
+  .unwrap();
   let mut test_decrypts: Vec<u8> = vec![];
   println!("keystream is {:?}", keystream_fragment);
   for ct2 in cts.iter() {
@@ -38,7 +51,8 @@ pub fn targeted_guess(cts: Vec<Vec<u8>>) {
       let bytes = tools::xor_bytes(
         &keystream_fragment[0..num_bytes],
         &ct2[start_idx..(start_idx + num_bytes)],
-      );
+      )
+      .unwrap();
       test_decrypts.extend(&bytes);
     }
   }
@@ -53,14 +67,15 @@ pub fn try_guess(cts: Vec<Vec<u8>>) {
     for i in 0..(ct.len() - guess.len() + 1) {
       let mut test_decrypts: Vec<u8> = vec![];
       let keystream_fragment =
-        tools::xor_bytes(&ct[i..(i + guess.len())], &guess);
+        tools::xor_bytes(&ct[i..(i + guess.len())], guess).unwrap();
       for ct2 in cts.iter() {
         if ct2.len() > i {
           let num_bytes = cmp::min(ct2.len() - i, guess.len());
           let bytes = tools::xor_bytes(
             &keystream_fragment[0..num_bytes],
             &ct2[i..(i + num_bytes)],
-          );
+          )
+          .unwrap();
           test_decrypts.extend(&bytes);
         }
       }
@@ -100,7 +115,9 @@ mod tests {
   use super::*;
   use std::collections::HashMap;
 
+  // scratch tests that print their working and fail on purpose to show it
   #[test]
+  #[ignore]
   fn scratch_test_for_piecemeal_attack() {
     let ciphertexts =
       ciphertexts("src/crustopals/problem19/19.txt".to_string());
@@ -110,6 +127,7 @@ mod tests {
   }
 
   #[test]
+  #[ignore]
   fn print_substitutions() {
     let mut subs: HashMap<usize, Vec<u8>> = HashMap::new();
     subs.insert(0, vec![187, 167, 47, 25, 189]);
@@ -139,7 +157,8 @@ mod tests {
           let changed_segment = tools::xor_bytes(
             &ciphertexts[i][start_idx..(start_idx + num_bytes)],
             &stream_segment[0..num_bytes],
-          );
+          )
+          .unwrap();
           for i in 0..num_bytes {
             renderable_ct[i + start_idx] = changed_segment[i];
           }
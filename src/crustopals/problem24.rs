@@ -12,7 +12,7 @@ pub fn mt19937_decrypt(key: u16, ciphertext: &[u8]) -> Vec<u8> {
 
 fn xor_with_keystream(key: u16, bytes: &[u8]) -> Vec<u8> {
   let key_stream = mt19937_stream(key as u32, bytes.len());
-  xor_bytes(&bytes, &key_stream)
+  xor_bytes(&bytes, &key_stream).unwrap()
 }
 
 pub fn generate_password_reset_token(use_prng: bool) -> Vec<u8> {
@@ -40,7 +40,7 @@ pub fn crack_prng_stream_key(ciphertext: &[u8], known_plaintext: &[u8]) -> u16 {
   let length = ciphertext.len();
   for test_seed in 0u16..=65535 {
     let stream = mt19937_stream(test_seed as u32, length);
-    let proposed_pt = xor_bytes(&stream, ciphertext);
+    let proposed_pt = xor_bytes(&stream, ciphertext).unwrap();
     if &proposed_pt[(length - 14)..] == known_plaintext {
       return test_seed;
     }
@@ -7,7 +7,7 @@ lazy_static! {
 }
 
 pub fn ctr_encrypt_with_unknown_key(pt: &[u8]) -> Vec<u8> {
-  aes::encrypt_ctr(&pt, &RANDOM_KEY, &NONCE)
+  aes::encrypt_ctr(&pt, &RANDOM_KEY, &NONCE).unwrap()
 }
 
 pub fn recover_pt_from_ct(ct: &[u8]) -> Vec<u8> {
@@ -15,13 +15,14 @@ pub fn recover_pt_from_ct(ct: &[u8]) -> Vec<u8> {
   let known_pt_bytes = known_pt.as_bytes();
   let mut new_ct: Vec<u8> = ct.clone().to_vec();
   edit(&mut new_ct, 0, known_pt_bytes);
-  let ctr_stream = tools::xor_bytes(&known_pt_bytes, &new_ct);
-  tools::xor_bytes(&ctr_stream, ct)
+  let ctr_stream = tools::xor_bytes(&known_pt_bytes, &new_ct).unwrap();
+  tools::xor_bytes(&ctr_stream, ct).unwrap()
 }
 
 pub fn edit(ciphertext: &mut Vec<u8>, offset: usize, newtext: &[u8]) {
   let stream_length = offset + newtext.len();
-  let ctr_stream = aes::generate_ctr_stream(&RANDOM_KEY, &NONCE, stream_length);
+  let ctr_stream =
+    aes::generate_ctr_stream(&RANDOM_KEY, &NONCE, stream_length).unwrap();
   for i in 0..newtext.len() {
     ciphertext[i + offset] = newtext[i] ^ ctr_stream[i + offset];
   }
@@ -1,4 +1,6 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
 use crustopals::query_string;
+use crustopals::tools::crc32::IEEE_CRC;
 use crustopals::tools::*;
 
 lazy_static! {
@@ -9,7 +11,8 @@ lazy_static! {
 pub fn attack_ctr_oracle() -> Vec<u8> {
   let attack_string = "\x00admin\x00true";
   let attack_bytes = attack_string.as_bytes().to_vec();
-  let ct = query_string::ctr_encrypt(&RANDOM_KEY, &NONCE, attack_bytes);
+  let ct =
+    query_string::ctr_encrypt(&RANDOM_KEY, &NONCE, &attack_bytes).unwrap();
   let mut bitflipped_ct = ct.clone();
 
   // 32 is the length of the prefix string
@@ -18,10 +21,38 @@ pub fn attack_ctr_oracle() -> Vec<u8> {
   bitflipped_ct
 }
 
+// The same flips against a receiver that checks a CRC-32 of the message.
+// The CRC's change only depends on the flipped bits, so it can be flipped
+// to match through the encryption too.
+pub fn attack_ctr_crc_oracle() -> Vec<u8> {
+  let ct = encrypt_attack_with_crc();
+  let mut delta = admin_delta(ct.len() - 4);
+  let mut crc_delta = [0u8; 4];
+  LittleEndian::write_u32(&mut crc_delta, IEEE_CRC.flip_delta(&delta));
+  delta.extend(crc_delta.iter());
+  xor_bytes(&ct, &delta).unwrap()
+}
+
+fn encrypt_attack_with_crc() -> Vec<u8> {
+  let attack_bytes = "\x00admin\x00true".as_bytes();
+  query_string::ctr_encrypt_with_crc(&RANDOM_KEY, &NONCE, attack_bytes).unwrap()
+}
+
+// The bits to flip in a message of `msg_len` bytes to turn the attack
+// string into ";admin=true".
+fn admin_delta(msg_len: usize) -> Vec<u8> {
+  let mut delta = vec![0; msg_len];
+  delta[32] = b';';
+  delta[32 + 6] = b'=';
+  delta
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crustopals::query_string::ctr_decrypts_with_admin_rights;
+  use crustopals::query_string::{
+    ctr_crc_decrypts_with_admin_rights, ctr_decrypts_with_admin_rights,
+  };
 
   #[test]
   fn cracks_ctr_with_padding_attack() {
@@ -29,7 +60,33 @@ mod tests {
     let admin_rights = ctr_decrypts_with_admin_rights(
       &RANDOM_KEY,
       &NONCE,
-      crack_ctr_to_upgrade_to_admin,
+      &crack_ctr_to_upgrade_to_admin,
+    );
+
+    assert!(admin_rights);
+  }
+
+  #[test]
+  fn a_crc_does_not_stop_the_bitflips() {
+    let ct = encrypt_attack_with_crc();
+    let mut delta = admin_delta(ct.len() - 4);
+    delta.extend([0u8; 4].iter());
+    let flipped_without_the_crc = xor_bytes(&ct, &delta).unwrap();
+    // the flips work, only the CRC gives them away
+    let decrypted =
+      aes::decrypt_ctr(&flipped_without_the_crc, &RANDOM_KEY, &NONCE).unwrap();
+    assert!(query_string::has_admin_rights(&decrypted));
+    assert!(!ctr_crc_decrypts_with_admin_rights(
+      &RANDOM_KEY,
+      &NONCE,
+      &flipped_without_the_crc,
+    ));
+
+    let crack_ctr_through_the_crc = attack_ctr_crc_oracle();
+    let admin_rights = ctr_crc_decrypts_with_admin_rights(
+      &RANDOM_KEY,
+      &NONCE,
+      &crack_ctr_through_the_crc,
     );
 
     assert!(admin_rights);
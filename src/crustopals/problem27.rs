@@ -5,51 +5,88 @@ lazy_static! {
   pub static ref RANDOM_KEY: Vec<u8> = aes::generate_key();
 }
 
-pub fn broken_cbc_encrypt(msg_bytes: &[u8]) -> Vec<u8> {
+pub fn broken_cbc_encrypt(msg_bytes: &[u8]) -> Result<Vec<u8>, CryptoError> {
   query_string::cbc_encrypt(&RANDOM_KEY, &RANDOM_KEY, msg_bytes)
 }
 
-fn ascii_compliant_decrypt(ct: Vec<u8>) -> Result<Vec<u8>, (String, Vec<u8>)> {
-  let decrypted = query_string::cbc_decrypt(&RANDOM_KEY, &RANDOM_KEY, &ct);
+#[derive(Debug, Clone, PartialEq)]
+enum AsciiError {
+  Crypto(CryptoError),
+  // the receiver complains, quoting the offending plaintext
+  NonCompliant(Vec<u8>),
+}
+
+impl From<CryptoError> for AsciiError {
+  fn from(err: CryptoError) -> AsciiError {
+    AsciiError::Crypto(err)
+  }
+}
+
+fn ascii_compliant_decrypt(ct: &[u8]) -> Result<Vec<u8>, AsciiError> {
+  let decrypted = query_string::cbc_decrypt(&RANDOM_KEY, &RANDOM_KEY, ct)?;
 
   if decrypted[..].is_ascii() {
     Ok(decrypted)
   } else {
-    Err(("Invalid ascii".to_string(), decrypted))
+    Err(AsciiError::NonCompliant(decrypted))
   }
 }
 
-pub fn attack_broken_cbc(mut ct: Vec<u8>) -> Vec<u8> {
-  for i in 0..32 {
-    ct[i] = 0u8;
+// The leaked plaintext, or None if it came back ASCII after all.
+fn leaked_plaintext(ct: &[u8]) -> Result<Option<Vec<u8>>, CryptoError> {
+  match ascii_compliant_decrypt(ct) {
+    Ok(_) => Ok(None),
+    Err(AsciiError::NonCompliant(plaintext)) => Ok(Some(plaintext)),
+    Err(AsciiError::Crypto(err)) => Err(err),
   }
+}
 
-  match ascii_compliant_decrypt(ct) {
-    Ok(_) => panic!("Something went wrong.."),
-    Err((_e, plaintext)) => {
-      return xor_bytes(&plaintext[0..16], &plaintext[16..32]);
+// Both attacks need at least three blocks of ciphertext.
+fn check_length(ct: &[u8]) -> Result<(), CryptoError> {
+  if ct.len() < 48 || !ct.len().is_multiple_of(16) {
+    return Err(CryptoError::InvalidBlockLength {
+      block_size: 16,
+      actual: ct.len(),
+    });
+  }
+  Ok(())
+}
+
+pub fn attack_broken_cbc(
+  mut ct: Vec<u8>,
+) -> Result<Option<Vec<u8>>, CryptoError> {
+  check_length(&ct)?;
+  for byte in ct[..32].iter_mut() {
+    *byte = 0u8;
+  }
+
+  match leaked_plaintext(&ct)? {
+    Some(plaintext) => {
+      Ok(Some(xor_bytes(&plaintext[0..16], &plaintext[16..32])?))
     }
-  };
+    None => Ok(None),
+  }
 }
 
-pub fn attack_broken_cbc_method2(mut ct: Vec<u8>) -> Vec<u8> {
+pub fn attack_broken_cbc_method2(
+  mut ct: Vec<u8>,
+) -> Result<Option<Vec<u8>>, CryptoError> {
+  check_length(&ct)?;
   // set block 2 to all zeros
-  for i in 16..32 {
-    ct[i] = 0u8;
+  for byte in ct[16..32].iter_mut() {
+    *byte = 0u8;
   }
 
   // set block 3 to block 1
-  for i in 32..48 {
-    ct[i] = ct[i - 32];
-  }
+  ct.copy_within(0..16, 32);
 
-  match ascii_compliant_decrypt(ct) {
-    Ok(_) => panic!("Something went wrong.."),
-    Err((_e, plaintext)) => {
-      // xor the first and third pt blocks
-      return xor_bytes(&plaintext[0..16], &plaintext[32..48]);
+  match leaked_plaintext(&ct)? {
+    // xor the first and third pt blocks
+    Some(plaintext) => {
+      Ok(Some(xor_bytes(&plaintext[0..16], &plaintext[32..48])?))
     }
-  };
+    None => Ok(None),
+  }
 }
 
 #[cfg(test)]
@@ -61,10 +98,10 @@ mod tests {
     let message = "OK".to_string();
     let msg_bytes = message.as_bytes();
 
-    let ct = broken_cbc_encrypt(msg_bytes);
-    let key: Vec<u8> = attack_broken_cbc(ct);
+    let ct = broken_cbc_encrypt(msg_bytes).unwrap();
+    let key = attack_broken_cbc(ct).unwrap();
 
-    assert_eq!(key, *RANDOM_KEY);
+    assert_eq!(key, Some(RANDOM_KEY.clone()));
   }
 
   #[test]
@@ -72,9 +109,29 @@ mod tests {
     let message = "OK".to_string();
     let msg_bytes = message.as_bytes();
 
-    let ct = broken_cbc_encrypt(msg_bytes);
-    let key: Vec<u8> = attack_broken_cbc_method2(ct);
+    let ct = broken_cbc_encrypt(msg_bytes).unwrap();
+    let key = attack_broken_cbc_method2(ct).unwrap();
 
-    assert_eq!(key, *RANDOM_KEY);
+    assert_eq!(key, Some(RANDOM_KEY.clone()));
+  }
+
+  #[test]
+  fn it_reports_bad_ciphertext_instead_of_panicking() {
+    let ct = broken_cbc_encrypt(b"OK").unwrap();
+
+    assert_eq!(
+      attack_broken_cbc(ct[..40].to_vec()),
+      Err(CryptoError::InvalidBlockLength {
+        block_size: 16,
+        actual: 40
+      })
+    );
+    assert_eq!(
+      ascii_compliant_decrypt(&ct[..20]),
+      Err(AsciiError::Crypto(CryptoError::InvalidBlockLength {
+        block_size: 16,
+        actual: 20
+      }))
+    );
   }
 }
@@ -1,79 +1,50 @@
-extern crate sha1;
+use crustopals::tools::merkle_damgard::{
+  extend_secret_prefix_mac, forge_with_oracle, Forgery,
+};
+use crustopals::tools::sha1::{self, Sha1};
+use crustopals::tools::sha2::Sha256;
+use crustopals::tools::CryptoError;
 
 pub fn generate_sha1_padding(bytes: &[u8]) -> Vec<u8> {
-  let num_bytes = bytes.len();
-  let extra_bytes = num_bytes % 64;
-  let mut last_blocks = [0u8; 128];
-  last_blocks[0..extra_bytes]
-    .clone_from_slice(&bytes[(num_bytes - extra_bytes)..]);
-  last_blocks[extra_bytes] = 0x80u8; // 10000000 in binary
-  let num_bits: u64 = num_bytes as u64 * 8;
-  let extra = [
-    (num_bits >> 56) as u8,
-    (num_bits >> 48) as u8,
-    (num_bits >> 40) as u8,
-    (num_bits >> 32) as u8,
-    (num_bits >> 24) as u8,
-    (num_bits >> 16) as u8,
-    (num_bits >> 8) as u8,
-    num_bits as u8,
-  ];
-  if extra_bytes < 56 {
-    last_blocks[56..64].clone_from_slice(&extra);
-    last_blocks[..64].to_vec()
-  } else {
-    last_blocks[120..128].clone_from_slice(&extra);
-    last_blocks.to_vec()
-  }
+  sha1::padding(bytes.len() as u64)
 }
 
 pub fn forge_mac(
   mac: &[u8],
   msg: &[u8],
   forged_bytes: &[u8],
-) -> (Vec<u8>, Vec<u8>) {
-  let fake_secret = [0u8; 16]; // "guess" of 16 bytes
-  let mut full_msg: Vec<u8> = vec![];
-  full_msg.extend(fake_secret.to_vec());
-  full_msg.extend(msg.to_vec());
-  // The below returns the last block that was run through the hashing algorithm
-  // inclusive of padding. This should always be a round 64 bytes but it may be
-  // 64 and it may be 128 depending if there was enough room to add the 1 bit
-  // and the 8 byte length at the end (i.e. if the original message ran up
-  // past 56 bytes but below 64 it would need almost an entire block of null
-  // byte padding)
-  let padding = generate_sha1_padding(&full_msg);
-  let blks_len = (full_msg.len() as u64 / 64) * 64;
-  let padding_len = padding.len() as u64;
-
-  let mut forged_msg: Vec<u8> = vec![];
-  if blks_len > 0 {
-    forged_msg.extend(&full_msg[fake_secret.len()..blks_len as usize].to_vec());
-    forged_msg.extend(padding);
-  } else {
-    forged_msg.extend(&padding[fake_secret.len()..].to_vec());
-  }
-
-  let sha1_state = sha1_state_from_digest(mac);
-  let length = blks_len + padding_len;
-  let mut sha1 = sha1::Sha1::new_with_state_and_len(sha1_state, length);
-  sha1.update(&forged_bytes);
-  let forged_sha = hex::decode(sha1.hexdigest()).unwrap();
+) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+  let secret_len = 16; // "guess" of 16 bytes
+  extend_secret_prefix_mac::<Sha1>(mac, secret_len, msg, forged_bytes)
+}
 
-  forged_msg.extend(forged_bytes.to_vec());
-  (forged_sha, forged_msg)
+// SHA-256 pads like SHA-1 and its digest is its whole state too, so
+// sha256(secret || msg) falls the same way.
+pub fn forge_sha256_mac(
+  mac: &[u8],
+  msg: &[u8],
+  forged_bytes: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+  let secret_len = 16; // "guess" of 16 bytes
+  extend_secret_prefix_mac::<Sha256>(mac, secret_len, msg, forged_bytes)
 }
 
-fn sha1_state_from_digest(digest_bytes: &[u8]) -> sha1::Sha1State {
-  let mut state = [0u32; 5];
-  for i in 0..5 {
-    let off = i * 4;
-    state[i] = (digest_bytes[off + 3] as u32)
-      | ((digest_bytes[off + 2] as u32) << 8)
-      | ((digest_bytes[off + 1] as u32) << 16)
-      | ((digest_bytes[off] as u32) << 24);
-  }
-  sha1::Sha1State { state }
+// `forge_mac` for a key of unknown length, found by trying every length up
+// to `max_secret_len` against a MAC checking oracle.
+pub fn forge_mac_with_oracle<F: FnMut(&[u8], &[u8]) -> bool>(
+  mac: &[u8],
+  msg: &[u8],
+  forged_bytes: &[u8],
+  max_secret_len: usize,
+  oracle: F,
+) -> Result<Option<Forgery>, CryptoError> {
+  forge_with_oracle::<Sha1, _, _>(
+    mac,
+    msg,
+    forged_bytes,
+    0..=max_secret_len,
+    oracle,
+  )
 }
 
 #[cfg(test)]
@@ -109,9 +80,10 @@ mod tests {
       0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 40,
     ];
 
-    assert_eq!(generate_sha1_padding(quote1.as_bytes()), padding1);
-    assert_eq!(generate_sha1_padding(quote2.as_bytes()), padding2);
-    assert_eq!(generate_sha1_padding(quote3.as_bytes()), padding3);
+    // the vectors above are the whole last block(s), message tail included
+    assert_eq!(generate_sha1_padding(quote1.as_bytes()), &padding1[29..]);
+    assert_eq!(generate_sha1_padding(quote2.as_bytes()), &padding2[56..]);
+    assert_eq!(generate_sha1_padding(quote3.as_bytes()), &padding3[37..]);
   }
 
   #[test]
@@ -122,7 +94,7 @@ mod tests {
     let desired_append_bytes = ";admin=true;".as_bytes();
 
     let (forged_mac, forged_msg) =
-      forge_mac(&legit_mac, &msg_bytes, &desired_append_bytes);
+      forge_mac(&legit_mac, msg_bytes, desired_append_bytes).unwrap();
 
     assert!(query_string::has_admin_rights(&forged_msg));
     assert!(authentication::valid_sha1_mac(
@@ -131,4 +103,67 @@ mod tests {
       forged_mac
     ));
   }
+
+  #[test]
+  fn it_can_forge_a_valid_sha256_mac() {
+    let secret_key: Vec<u8> = aes::generate_key(); //random 16 byte key
+    let msg_bytes = "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon".as_bytes();
+    let legit_mac = authentication::sha256_mac(&secret_key, msg_bytes);
+    let desired_append_bytes = ";admin=true;".as_bytes();
+
+    let (forged_mac, forged_msg) =
+      forge_sha256_mac(&legit_mac, msg_bytes, desired_append_bytes).unwrap();
+
+    assert!(query_string::has_admin_rights(&forged_msg));
+    assert!(authentication::valid_sha256_mac(
+      &secret_key,
+      &forged_msg,
+      forged_mac
+    ));
+  }
+
+  #[test]
+  fn it_rejects_a_mac_of_the_wrong_length() {
+    let short_mac = [0u8; 19];
+
+    assert_eq!(
+      forge_mac(&short_mac, b"msg", b";admin=true;"),
+      Err(CryptoError::LengthMismatch {
+        left: 20,
+        right: 19
+      })
+    );
+    assert_eq!(
+      forge_sha256_mac(&short_mac, b"msg", b";admin=true;"),
+      Err(CryptoError::LengthMismatch {
+        left: 32,
+        right: 19
+      })
+    );
+  }
+
+  #[test]
+  fn it_forges_a_mac_for_a_key_of_unknown_length() {
+    let key_len = 1 + aes::generate_rand_bytes(1)[0] as usize % 64;
+    let secret_key: Vec<u8> = aes::generate_rand_bytes(key_len);
+    let msg_bytes = "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon".as_bytes();
+    let legit_mac = authentication::sha1_mac(&secret_key, msg_bytes);
+    let oracle = |msg: &[u8], mac: &[u8]| {
+      authentication::valid_sha1_mac(&secret_key, msg, mac.to_vec())
+    };
+
+    let forgery =
+      forge_mac_with_oracle(&legit_mac, msg_bytes, b";admin=true;", 64, oracle)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(forgery.secret_len, key_len);
+    assert_eq!(forgery.queries, key_len + 1);
+    assert!(query_string::has_admin_rights(&forgery.message));
+    assert!(authentication::valid_sha1_mac(
+      &secret_key,
+      &forgery.message,
+      forgery.mac
+    ));
+  }
 }
@@ -28,7 +28,7 @@ fn generate_single_byte_hex_xors(hex_str: &str) -> Vec<(String, String)> {
   (0..256)
     .into_iter()
     .map(|byte| format!("{:02x}", byte))
-    .map(|hex_key| (tools::xor_hex(hex_str, &hex_key), hex_key))
+    .map(|hex_key| (tools::xor_hex(hex_str, &hex_key).unwrap(), hex_key))
     .collect()
 }
 
@@ -39,7 +39,8 @@ mod tests {
   #[test]
   fn solves_simple_xor() {
     let plaintext = "Hello World";
-    let ciphertext_hex = tools::xor_hex(&hex::encode(plaintext), "0a");
+    let ciphertext_hex =
+      tools::xor_hex(&hex::encode(plaintext), "0a").unwrap();
     let deciphered_string = problem3::solve_single_byte_xor(&ciphertext_hex);
     assert_eq!(deciphered_string.1, plaintext);
   }
@@ -18,7 +18,8 @@ pub fn run() {
       }
       let file = query.get("file").unwrap();
       let signature = query.get("signature").unwrap();
-      let valid_sig = problem31::insecure_compare(file, signature);
+      let valid_sig =
+        problem31::insecure_compare(file, signature).unwrap_or(false);
       if !valid_sig {
         response.status(StatusCode::NOT_FOUND);
       }
@@ -1,8 +1,9 @@
 extern crate reqwest;
-extern crate sha1;
 
 use crustopals::tools;
-use std::iter;
+use crustopals::tools::authentication::hmac;
+use crustopals::tools::sha1::Sha1;
+use crustopals::tools::CryptoError;
 use std::{thread, time};
 
 lazy_static! {
@@ -11,36 +12,22 @@ lazy_static! {
 
 pub mod hmac_server;
 
-pub fn hmac_sha1(key_bytes: &[u8], msg: &str) -> Vec<u8> {
-  let key: Vec<u8>;
-  if key_bytes.len() > 64 {
-    key = sha1_hash(&key_bytes);
-  } else {
-    key = key_bytes.to_vec();
+pub fn insecure_compare(
+  filename: &str,
+  signature: &str,
+) -> Result<bool, CryptoError> {
+  let hmac_filename = hmac::<Sha1>(&RANDOM_KEY, filename.as_bytes());
+  let sig_bytes = hex::decode(signature)?;
+  if sig_bytes.len() != hmac_filename.len() {
+    return Ok(false);
   }
-  let mut block_sized_key = [0; 64];
-  block_sized_key[0..key.len()].clone_from_slice(&key[..]);
-
-  let outer_xor: Vec<u8> = iter::repeat(0x5c as u8).take(64).collect();
-  let inner_xor: Vec<u8> = iter::repeat(0x36 as u8).take(64).collect();
-  let o_key_pad = tools::xor_bytes(&block_sized_key, &outer_xor);
-  let i_key_pad = tools::xor_bytes(&block_sized_key, &inner_xor);
-
-  // HMAC hashing
-  let inner_hash = sha1_hash(&[&i_key_pad[..], msg.as_bytes()].concat());
-  sha1_hash(&[&o_key_pad[..], &inner_hash[..]].concat())
-}
-
-pub fn insecure_compare(filename: &str, signature: &str) -> bool {
-  let hmac_filename = hmac_sha1(&RANDOM_KEY, filename);
-  let sig_bytes = hex::decode(signature).unwrap();
   for i in 0..sig_bytes.len() {
     if hmac_filename[i] != sig_bytes[i] {
-      return false;
+      return Ok(false);
     }
     thread::sleep(time::Duration::from_millis(6));
   }
-  true
+  Ok(true)
 }
 
 fn exploit_early_exit(filename: &str) -> Result<[u8; 20], reqwest::Error> {
@@ -73,33 +60,10 @@ fn exploit_early_exit(filename: &str) -> Result<[u8; 20], reqwest::Error> {
   Ok(signature)
 }
 
-fn sha1_hash(bytes: &[u8]) -> Vec<u8> {
-  let mut sha1 = sha1::Sha1::new();
-  sha1.update(&bytes[..]);
-  sha1.digest().bytes().to_vec()
-}
-
 #[cfg(test)]
 mod tests {
   use super::*;
-
-  #[test]
-  fn it_generates_an_hmac() {
-    let empty_ex = hmac_sha1("".as_bytes(), "");
-    let quick_brown_fox_ex = hmac_sha1(
-      "key".as_bytes(),
-      "The quick brown fox jumps over the lazy dog",
-    );
-
-    assert_eq!(
-      empty_ex,
-      hex::decode("fbdb1d1b18aa6c08324b7d64b71fb76370690e1d").unwrap()
-    );
-    assert_eq!(
-      quick_brown_fox_ex,
-      hex::decode("de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9").unwrap()
-    );
-  }
+  use crustopals::tools::constant_time;
 
   #[test]
   fn it_can_exploit_an_early_exit_compare() {
@@ -109,8 +73,27 @@ mod tests {
     let desired_file = "/etc/passwd";
     let exploited_signature =
       exploit_early_exit(desired_file).unwrap().to_vec();
-    let valid_signature = hmac_sha1(&RANDOM_KEY, desired_file);
+    let valid_signature = hmac::<Sha1>(&RANDOM_KEY, desired_file.as_bytes());
 
     assert_eq!(exploited_signature, valid_signature);
   }
+
+  #[test]
+  fn insecure_compare_leaks_through_its_timing() {
+    let filename = "/etc/passwd";
+    let mut signature = hmac::<Sha1>(&RANDOM_KEY, filename.as_bytes());
+    signature[1] ^= 1;
+    let right_first_byte = hex::encode(&signature);
+    signature[0] ^= 1;
+    let wrong_first_byte = hex::encode(&signature);
+
+    assert!(constant_time::leaks_timing(60, |class| {
+      let signature = if class {
+        &right_first_byte
+      } else {
+        &wrong_first_byte
+      };
+      insecure_compare(filename, signature).unwrap();
+    }));
+  }
 }
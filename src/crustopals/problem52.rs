@@ -0,0 +1,220 @@
+use crustopals::tools::aes;
+use crustopals::tools::toy_hash::{ToyHash, BLOCK_SIZE};
+use crustopals::tools::CryptoError;
+use std::collections::HashMap;
+
+// Joux multicollisions. A collision in one block from some chaining state
+// lands both messages on the same next state, so collisions found one
+// after another from each resulting state can be mixed and matched: n of
+// them, each a birthday search, give 2^n messages with the same hash.
+//
+// That sinks the cascade f(m) || g(m). Make 2^(b/2) messages colliding in
+// the cheap f, for b the bits in g, and by the birthday bound two of them
+// probably collide in g as well, for about b/2 collisions in f plus 2^(b/2)
+// evaluations of g, rather than the 2^((bits of f + b)/2) the combined
+// digest suggests.
+
+// Two one block messages taking `state` to the same next state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCollision {
+  pub blocks: (Vec<u8>, Vec<u8>),
+  pub next_state: Vec<u8>,
+  pub compressions: usize,
+}
+
+pub fn find_block_collision(
+  hash: &ToyHash,
+  state: &[u8],
+) -> Result<BlockCollision, CryptoError> {
+  let mut seen: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+  let mut compressions = 0;
+  loop {
+    let block = aes::generate_rand_bytes(BLOCK_SIZE);
+    let next_state = hash.compress(state, &block)?;
+    compressions += 1;
+    match seen.get(&next_state) {
+      Some(other) if *other != block => {
+        return Ok(BlockCollision {
+          blocks: (other.clone(), block),
+          next_state,
+          compressions,
+        })
+      }
+      _ => {
+        seen.insert(next_state, block);
+      }
+    }
+  }
+}
+
+// 2^n messages of n blocks, all hashing to the same value: message i takes
+// the second block of pair k where bit k of i is set, the first otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multicollision {
+  pub pairs: Vec<(Vec<u8>, Vec<u8>)>,
+  // the state after any of the messages, before padding
+  pub state: Vec<u8>,
+  pub compressions: usize,
+}
+
+impl Multicollision {
+  pub fn new(hash: &ToyHash, n: usize) -> Result<Multicollision, CryptoError> {
+    let mut multicollision = Multicollision {
+      pairs: vec![],
+      state: hash.iv().to_vec(),
+      compressions: 0,
+    };
+    multicollision.extend(hash, n)?;
+    Ok(multicollision)
+  }
+
+  // Doubles the number of messages `n` more times.
+  pub fn extend(
+    &mut self,
+    hash: &ToyHash,
+    n: usize,
+  ) -> Result<(), CryptoError> {
+    for _ in 0..n {
+      let collision = find_block_collision(hash, &self.state)?;
+      self.pairs.push(collision.blocks);
+      self.state = collision.next_state;
+      self.compressions += collision.compressions;
+    }
+    Ok(())
+  }
+
+  pub fn message(&self, index: u64) -> Vec<u8> {
+    self
+      .pairs
+      .iter()
+      .enumerate()
+      .flat_map(|(k, pair)| {
+        if index >> k & 1 == 1 {
+          pair.1.clone()
+        } else {
+          pair.0.clone()
+        }
+      })
+      .collect()
+  }
+
+  pub fn messages(&self) -> Vec<Vec<u8>> {
+    (0..1u64 << self.pairs.len())
+      .map(|i| self.message(i))
+      .collect()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CascadeCollision {
+  pub messages: (Vec<u8>, Vec<u8>),
+  // compressions spent on each of the two hashes
+  pub f_compressions: usize,
+  pub g_compressions: usize,
+}
+
+// A collision in f(m) || g(m), for f the cheaper hash, from multicollisions
+// in f alone.
+pub fn cascade_collision(
+  f: &ToyHash,
+  g: &ToyHash,
+) -> Result<CascadeCollision, CryptoError> {
+  // 2^(b/2) messages, b being g's state size in bits
+  let mut multicollision = Multicollision::new(f, g.state_size() * 4)?;
+  let mut g_compressions = 0;
+  loop {
+    let mut seen: HashMap<Vec<u8>, u64> = HashMap::new();
+    let mut visit = |index, state| match seen.get(&state) {
+      Some(other) => Some((*other, index)),
+      None => {
+        seen.insert(state, index);
+        None
+      }
+    };
+    let found = g_states(
+      g,
+      &multicollision.pairs,
+      0,
+      g.iv(),
+      0,
+      &mut visit,
+      &mut g_compressions,
+    )?;
+    if let Some((a, b)) = found {
+      return Ok(CascadeCollision {
+        messages: (multicollision.message(a), multicollision.message(b)),
+        f_compressions: multicollision.compressions,
+        g_compressions,
+      });
+    }
+    // unlucky, so twice as many messages to try
+    multicollision.extend(f, 1)?;
+  }
+}
+
+// Walks the tree of messages depth first so g is run once per prefix
+// rather than once per message, passing each message's index and final
+// state to `visit` until it returns something.
+fn g_states<F>(
+  g: &ToyHash,
+  pairs: &[(Vec<u8>, Vec<u8>)],
+  depth: usize,
+  state: &[u8],
+  index: u64,
+  visit: &mut F,
+  compressions: &mut usize,
+) -> Result<Option<(u64, u64)>, CryptoError>
+where
+  F: FnMut(u64, Vec<u8>) -> Option<(u64, u64)>,
+{
+  if depth == pairs.len() {
+    return Ok(visit(index, state.to_vec()));
+  }
+  let (first, second) = &pairs[depth];
+  *compressions += 1;
+  let next = g.compress(state, first)?;
+  if let Some(found) =
+    g_states(g, pairs, depth + 1, &next, index, visit, compressions)?
+  {
+    return Ok(Some(found));
+  }
+  *compressions += 1;
+  let next = g.compress(state, second)?;
+  let index = index | 1 << depth;
+  g_states(g, pairs, depth + 1, &next, index, visit, compressions)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  #[test]
+  fn it_finds_2_to_the_n_colliding_messages() {
+    let hash = ToyHash::new(2).unwrap();
+    let multicollision = Multicollision::new(&hash, 8).unwrap();
+    let messages = multicollision.messages();
+    let digest = hash.hash(&messages[0]);
+
+    assert_eq!(messages.iter().collect::<HashSet<_>>().len(), 256);
+    for message in messages.iter() {
+      assert_eq!(hash.hash(message), digest);
+    }
+    // n birthday searches of ~2^8 each, not 2^16 or more for brute force
+    assert!(multicollision.compressions < 4 * 8 * (1 << 8));
+  }
+
+  #[test]
+  fn cascading_a_strong_hash_after_a_weak_one_adds_little() {
+    let f = ToyHash::new(2).unwrap();
+    let g = ToyHash::with_iv(&[0xca, 0xfe, 0xba]).unwrap();
+    let collision = cascade_collision(&f, &g).unwrap();
+    let (m1, m2) = &collision.messages;
+
+    assert_ne!(m1, m2);
+    assert_eq!(f.hash(m1), f.hash(m2));
+    assert_eq!(g.hash(m1), g.hash(m2));
+    // a generic birthday attack on the 40 bit cascade would be ~2^20
+    assert!(collision.f_compressions + collision.g_compressions < 1 << 18);
+  }
+}
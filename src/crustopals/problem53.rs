@@ -0,0 +1,240 @@
+use crustopals::tools::aes;
+use crustopals::tools::toy_hash::{ToyHash, BLOCK_SIZE};
+use crustopals::tools::CryptoError;
+use std::collections::HashMap;
+
+// Kelsey and Schneier's second preimages for long messages. A 2^k block
+// message passes through 2^k intermediate states, so a random block from
+// a state of our choosing lands on one of them after about 2^(b-k) tries
+// rather than 2^b. What stops that working outright is the length in the
+// padding: the forgery has to reach that state in exactly as many blocks
+// as the original did. An expandable message fixes the length up. Built
+// from k collisions between a one block message and a 2^i + 1 block one,
+// it can be made any length from k to k + 2^k - 1 blocks and still end on
+// the same state.
+
+// One block from `state_a` and one from `state_b` that reach the same next
+// state, and that state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossCollision {
+  pub blocks: (Vec<u8>, Vec<u8>),
+  pub next_state: Vec<u8>,
+  pub compressions: usize,
+}
+
+pub fn find_cross_collision(
+  hash: &ToyHash,
+  state_a: &[u8],
+  state_b: &[u8],
+) -> Result<CrossCollision, CryptoError> {
+  let mut from_a: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+  let mut from_b: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+  let mut compressions = 0;
+  loop {
+    let block_a = aes::generate_rand_bytes(BLOCK_SIZE);
+    let block_b = aes::generate_rand_bytes(BLOCK_SIZE);
+    let next_a = hash.compress(state_a, &block_a)?;
+    let next_b = hash.compress(state_b, &block_b)?;
+    compressions += 2;
+    if next_a == next_b {
+      return Ok(CrossCollision {
+        blocks: (block_a, block_b),
+        next_state: next_a,
+        compressions,
+      });
+    }
+    if let Some(other) = from_b.get(&next_a) {
+      return Ok(CrossCollision {
+        blocks: (block_a, other.clone()),
+        next_state: next_a,
+        compressions,
+      });
+    }
+    if let Some(other) = from_a.get(&next_b) {
+      return Ok(CrossCollision {
+        blocks: (other.clone(), block_b),
+        next_state: next_b,
+        compressions,
+      });
+    }
+    from_a.insert(next_a, block_a);
+    from_b.insert(next_b, block_b);
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandableMessage {
+  // piece j is a single block or 2^(k - 1 - j) + 1 blocks, to the same
+  // state either way
+  pieces: Vec<(Vec<u8>, Vec<u8>)>,
+  // the state after any of the messages, before padding
+  pub state: Vec<u8>,
+  pub compressions: usize,
+}
+
+impl ExpandableMessage {
+  pub fn new(
+    hash: &ToyHash,
+    k: usize,
+  ) -> Result<ExpandableMessage, CryptoError> {
+    let mut pieces = vec![];
+    let mut state = hash.iv().to_vec();
+    let mut compressions = 0;
+    for j in 0..k {
+      let dummy = vec![0u8; BLOCK_SIZE << (k - 1 - j)];
+      let dummy_state = hash.iterate(&state, &dummy)?;
+      compressions += dummy.len() / BLOCK_SIZE;
+      let collision = find_cross_collision(hash, &state, &dummy_state)?;
+      let (short, long_end) = collision.blocks;
+      pieces.push((short, [&dummy[..], &long_end[..]].concat()));
+      state = collision.next_state;
+      compressions += collision.compressions;
+    }
+    Ok(ExpandableMessage {
+      pieces,
+      state,
+      compressions,
+    })
+  }
+
+  pub fn min_blocks(&self) -> usize {
+    self.pieces.len()
+  }
+
+  pub fn max_blocks(&self) -> usize {
+    self.pieces.len() + (1 << self.pieces.len()) - 1
+  }
+
+  // The message `blocks` blocks long, if it's in range: the extra blocks
+  // over k, in binary, say which pieces to take long.
+  pub fn message(&self, blocks: usize) -> Option<Vec<u8>> {
+    if blocks < self.min_blocks() || blocks > self.max_blocks() {
+      return None;
+    }
+    let k = self.pieces.len();
+    let extra = blocks - k;
+    Some(
+      self
+        .pieces
+        .iter()
+        .enumerate()
+        .flat_map(|(j, piece)| {
+          if extra >> (k - 1 - j) & 1 == 1 {
+            piece.1.clone()
+          } else {
+            piece.0.clone()
+          }
+        })
+        .collect(),
+    )
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecondPreimage {
+  pub message: Vec<u8>,
+  pub compressions: usize,
+}
+
+// A message other than `target` with the same length and hash, through an
+// expandable message of 2^k + k - 1 blocks at most. `target` needs more
+// than k + 1 whole blocks for there to be a state to link into.
+pub fn second_preimage(
+  hash: &ToyHash,
+  target: &[u8],
+  k: usize,
+) -> Result<Option<SecondPreimage>, CryptoError> {
+  let blocks = target.len() / BLOCK_SIZE;
+  if blocks <= k + 1 {
+    return Ok(None);
+  }
+  let expandable = ExpandableMessage::new(hash, k)?;
+  let mut compressions = expandable.compressions;
+
+  // the state after each block, as long as the prefix before that block
+  // is a length the expandable message comes in
+  let mut state = hash.iv().to_vec();
+  let mut intermediate: HashMap<Vec<u8>, usize> = HashMap::new();
+  for (i, block) in target[..blocks * BLOCK_SIZE].chunks(BLOCK_SIZE).enumerate()
+  {
+    state = hash.compress(&state, block)?;
+    compressions += 1;
+    if i >= expandable.min_blocks() && i <= expandable.max_blocks() {
+      intermediate.entry(state.clone()).or_insert(i);
+    }
+  }
+
+  loop {
+    let bridge = aes::generate_rand_bytes(BLOCK_SIZE);
+    compressions += 1;
+    let prefix_blocks =
+      match intermediate.get(&hash.compress(&expandable.state, &bridge)?) {
+        Some(i) => *i,
+        None => continue,
+      };
+    let expanded = match expandable.message(prefix_blocks) {
+      Some(expanded) => expanded,
+      None => return Ok(None),
+    };
+    let message = [
+      &expanded[..],
+      &bridge[..],
+      &target[(prefix_blocks + 1) * BLOCK_SIZE..],
+    ]
+    .concat();
+    // only the target itself if the expandable message happened to be
+    // its prefix
+    if message != target {
+      return Ok(Some(SecondPreimage {
+        message,
+        compressions,
+      }));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_builds_messages_of_every_length_to_one_state() {
+    let hash = ToyHash::new(2).unwrap();
+    let expandable = ExpandableMessage::new(&hash, 4).unwrap();
+
+    assert_eq!((expandable.min_blocks(), expandable.max_blocks()), (4, 19));
+    for blocks in 4..20 {
+      let message = expandable.message(blocks).unwrap();
+
+      assert_eq!(message.len(), blocks * BLOCK_SIZE);
+      assert_eq!(
+        hash.iterate(hash.iv(), &message),
+        Ok(expandable.state.clone())
+      );
+    }
+    assert_eq!(expandable.message(3), None);
+    assert_eq!(expandable.message(20), None);
+  }
+
+  #[test]
+  fn it_finds_a_second_preimage_of_a_long_message() {
+    let hash = ToyHash::new(2).unwrap();
+    // 2^8 blocks and a bit
+    let target = aes::generate_rand_bytes((1 << 8) * BLOCK_SIZE + 7);
+    let forgery = second_preimage(&hash, &target, 8).unwrap().unwrap();
+
+    assert_ne!(forgery.message, target);
+    assert_eq!(forgery.message.len(), target.len());
+    assert_eq!(hash.hash(&forgery.message), hash.hash(&target));
+    // k birthday searches and 2^(16 - k) bridge blocks, where a generic
+    // second preimage on 16 bits is 2^16
+    assert!(forgery.compressions < 1 << 14);
+  }
+
+  #[test]
+  fn short_targets_have_nothing_to_link_into() {
+    let hash = ToyHash::new(2).unwrap();
+
+    assert_eq!(second_preimage(&hash, &[0u8; 5 * BLOCK_SIZE], 4), Ok(None));
+  }
+}
@@ -0,0 +1,197 @@
+use crustopals::tools::aes;
+use crustopals::tools::toy_hash::{self, ToyHash, BLOCK_SIZE};
+use crustopals::tools::CryptoError;
+use std::collections::{HashMap, HashSet};
+
+// Kelsey and Kohno's herding ("Nostradamus") attack. Publish a hash, then
+// once the results are in produce a message starting with them that has
+// that hash. The work goes into a diamond structure built beforehand: 2^k
+// starting states, collided in pairs into 2^(k - 1) states, those in pairs
+// again and so on down to a single state, which is what the published hash
+// is computed from. Any message can then be linked to one of the 2^k
+// starting states with one block after about 2^(b - k) tries, and walked
+// down the diamond from there.
+
+// each state on a level to its block and the state that block leads to on
+// the next level
+type Level = HashMap<Vec<u8>, (Vec<u8>, Vec<u8>)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiamondStructure {
+  levels: Vec<Level>,
+  pub root: Vec<u8>,
+  pub compressions: usize,
+}
+
+impl DiamondStructure {
+  pub fn new(
+    hash: &ToyHash,
+    k: usize,
+  ) -> Result<DiamondStructure, CryptoError> {
+    let mut states: HashSet<Vec<u8>> = HashSet::new();
+    while states.len() < 1 << k {
+      states.insert(aes::generate_rand_bytes(hash.state_size()));
+    }
+    let mut states: Vec<Vec<u8>> = states.into_iter().collect();
+    let mut levels = vec![];
+    let mut compressions = 0;
+    while states.len() > 1 {
+      let (level, next_states, level_compressions) =
+        collide_in_pairs(hash, &states)?;
+      levels.push(level);
+      states = next_states;
+      compressions += level_compressions;
+    }
+    Ok(DiamondStructure {
+      levels,
+      root: states[0].clone(),
+      compressions,
+    })
+  }
+
+  pub fn is_leaf(&self, state: &[u8]) -> bool {
+    match self.levels.first() {
+      Some(level) => level.contains_key(state),
+      None => state == &self.root[..],
+    }
+  }
+
+  // The k blocks from a starting state down to the root.
+  pub fn path(&self, leaf: &[u8]) -> Option<Vec<u8>> {
+    let mut state = leaf.to_vec();
+    let mut blocks = vec![];
+    for level in self.levels.iter() {
+      let (block, next) = level.get(&state)?;
+      blocks.extend_from_slice(block);
+      state = next.clone();
+    }
+    Some(blocks)
+  }
+}
+
+// Pairs states off by colliding them: every unpaired state tries a random
+// block in turn, and any two unpaired states that land on the same next
+// state become a pair. Matching whichever states collide rather than
+// fixing the pairs up front makes each collision much cheaper while there
+// are many states left.
+fn collide_in_pairs(
+  hash: &ToyHash,
+  states: &[Vec<u8>],
+) -> Result<(Level, Vec<Vec<u8>>, usize), CryptoError> {
+  let mut level = HashMap::new();
+  let mut next_states = vec![];
+  let mut compressions = 0;
+  // next state -> the unpaired state and block that reached it
+  let mut seen: HashMap<Vec<u8>, (usize, Vec<u8>)> = HashMap::new();
+  let mut paired = vec![false; states.len()];
+  while next_states.len() < states.len() / 2 {
+    for (i, state) in states.iter().enumerate() {
+      if paired[i] {
+        continue;
+      }
+      let block = aes::generate_rand_bytes(BLOCK_SIZE);
+      let next = hash.compress(state, &block)?;
+      compressions += 1;
+      match seen.get(&next) {
+        // two pairs meeting in one state would leave fewer than half as
+        // many states on the next level
+        Some(_) if next_states.contains(&next) => (),
+        Some((j, other_block)) if *j != i && !paired[*j] => {
+          level.insert(states[*j].clone(), (other_block.clone(), next.clone()));
+          level.insert(state.clone(), (block, next.clone()));
+          paired[i] = true;
+          paired[*j] = true;
+          next_states.push(next);
+        }
+        _ => {
+          seen.insert(next, (i, block));
+        }
+      }
+    }
+  }
+  Ok((level, next_states, compressions))
+}
+
+// A published hash, of a message made of a `prefix_blocks` block prefix,
+// a linking block and a path through the diamond.
+pub struct Prediction {
+  hash: ToyHash,
+  diamond: DiamondStructure,
+  prefix_blocks: usize,
+  pub commitment: Vec<u8>,
+}
+
+impl Prediction {
+  pub fn commit(
+    hash: &ToyHash,
+    k: usize,
+    prefix_blocks: usize,
+  ) -> Result<Prediction, CryptoError> {
+    let diamond = DiamondStructure::new(hash, k)?;
+    let message_len = (prefix_blocks + 1 + k) * BLOCK_SIZE;
+    let commitment =
+      hash.iterate(&diamond.root, &toy_hash::padding(message_len as u64))?;
+    Ok(Prediction {
+      hash: hash.clone(),
+      diamond,
+      prefix_blocks,
+      commitment,
+    })
+  }
+
+  // A message starting with `prefix`, padded out with spaces, that hashes
+  // to the commitment. None if the prefix is longer than was committed to.
+  pub fn herd(&self, prefix: &[u8]) -> Result<Option<Vec<u8>>, CryptoError> {
+    if prefix.len() > self.prefix_blocks * BLOCK_SIZE {
+      return Ok(None);
+    }
+    let mut prefix = prefix.to_vec();
+    prefix.resize(self.prefix_blocks * BLOCK_SIZE, b' ');
+    let state = self.hash.iterate(self.hash.iv(), &prefix)?;
+    loop {
+      let link = aes::generate_rand_bytes(BLOCK_SIZE);
+      let leaf = self.hash.compress(&state, &link)?;
+      if self.diamond.is_leaf(&leaf) {
+        return Ok(
+          self
+            .diamond
+            .path(&leaf)
+            .map(|path| [&prefix[..], &link[..], &path[..]].concat()),
+        );
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_funnels_every_leaf_into_the_root() {
+    let hash = ToyHash::new(2).unwrap();
+    let diamond = DiamondStructure::new(&hash, 4).unwrap();
+    let leaves: Vec<Vec<u8>> = diamond.levels[0].keys().cloned().collect();
+
+    assert_eq!(leaves.len(), 16);
+    for leaf in leaves.iter() {
+      let path = diamond.path(leaf).unwrap();
+
+      assert_eq!(path.len(), 4 * BLOCK_SIZE);
+      assert_eq!(hash.iterate(leaf, &path), Ok(diamond.root.clone()));
+    }
+    assert_eq!(diamond.path(&[0xff, 0xff, 0xff]), None);
+  }
+
+  #[test]
+  fn it_predicts_the_results_after_the_fact() {
+    let hash = ToyHash::new(2).unwrap();
+    let prediction = Prediction::commit(&hash, 6, 4).unwrap();
+    let results = b"Brazil 2, Germany 1; Spain 0, Italy 0; Japan 3, Chile 1";
+    let message = prediction.herd(results).unwrap().unwrap();
+
+    assert!(message.starts_with(results));
+    assert_eq!(hash.hash(&message), prediction.commitment);
+    assert_eq!(prediction.herd(&[b'x'; 65]), Ok(None));
+  }
+}
@@ -0,0 +1,308 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::rand;
+use crustopals::tools::md4::{
+  self, f, g, INITIAL_WORDS, ROUND_CONSTANTS, SHIFTS,
+};
+
+// Wang et al.'s MD4 collisions ("Cryptanalysis of the Hash Functions MD4
+// and RIPEMD", 2005). Two one block messages differing by
+//
+//   m1' = m1 + 2^31, m2' = m2 + 2^31 - 2^28, m12' = m12 - 2^16
+//
+// collide whenever the differences they cause in the state cancel out,
+// which the paper reduces to a list of conditions on bits of the
+// intermediate states. The first round's conditions can all be met
+// directly: compute each state word, fix up its bits, and solve for the
+// message word that gives the fixed word instead. Some of the second
+// round's (a5 and d5 here) can be met by reaching back into the first
+// round, changing a state word there where nothing else depends on the bit
+// and patching the next message words to absorb it. The rest are left to
+// chance, about 2^-16 of the time.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Condition {
+  Zero(u32),
+  One(u32),
+  // the same bit of the state word computed just before
+  Equal(u32),
+}
+
+use self::Condition::{Equal, One, Zero};
+
+// the first round's conditions, step by step (a1, d1, c1, b1, a2, ...),
+// with bits numbered from 0 rather than the paper's 1
+const ROUND_1_CONDITIONS: [&[Condition]; 16] = [
+  &[Equal(6)],
+  &[Zero(6), Equal(7), Equal(10)],
+  &[One(6), One(7), Zero(10), Equal(25)],
+  &[One(6), Zero(7), Zero(10), Zero(25)],
+  &[One(7), One(10), Zero(25), Equal(13)],
+  &[
+    Zero(13),
+    Equal(18),
+    Equal(19),
+    Equal(20),
+    Equal(21),
+    One(25),
+  ],
+  &[
+    Equal(12),
+    Zero(13),
+    Equal(14),
+    Zero(18),
+    Zero(19),
+    One(20),
+    Zero(21),
+  ],
+  &[
+    One(12),
+    One(13),
+    Zero(14),
+    Equal(16),
+    Zero(18),
+    Zero(19),
+    Zero(20),
+    Zero(21),
+  ],
+  &[
+    One(12),
+    One(13),
+    One(14),
+    Zero(16),
+    Zero(18),
+    Zero(19),
+    Zero(20),
+    One(21),
+    Equal(22),
+    Equal(25),
+  ],
+  &[
+    One(12),
+    One(13),
+    One(14),
+    Zero(16),
+    Zero(19),
+    One(20),
+    One(21),
+    Zero(22),
+    One(25),
+    Equal(29),
+  ],
+  &[
+    One(16),
+    Zero(19),
+    Zero(20),
+    Zero(21),
+    Zero(22),
+    Zero(25),
+    One(29),
+    Equal(31),
+  ],
+  &[
+    Zero(19),
+    One(20),
+    One(21),
+    Equal(22),
+    One(25),
+    Zero(29),
+    Zero(31),
+  ],
+  &[Zero(22), Zero(25), Equal(26), Equal(28), One(29), Zero(31)],
+  &[Zero(22), Zero(25), One(26), One(28), Zero(29), One(31)],
+  &[Equal(18), One(22), One(25), Zero(26), Zero(28), Zero(29)],
+  &[Zero(18), One(25), One(26), One(28), Zero(29)],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Md4Collision {
+  pub messages: (Vec<u8>, Vec<u8>),
+  // candidate message pairs tried
+  pub attempts: usize,
+}
+
+pub fn find_collision() -> Md4Collision {
+  let mut attempts = 0;
+  loop {
+    attempts += 1;
+    let mut m = [0u32; 16];
+    for word in m.iter_mut() {
+      *word = rand::random();
+    }
+    let states = meet_round_1_conditions(&mut m);
+    meet_round_2_conditions(&mut m, states);
+    let m_prime = differential(&m);
+    if compress_words(&m) == compress_words(&m_prime) {
+      return Md4Collision {
+        messages: (words_to_bytes(&m), words_to_bytes(&m_prime)),
+        attempts,
+      };
+    }
+  }
+}
+
+// The partner message under Wang's differential.
+pub fn differential(m: &[u32; 16]) -> [u32; 16] {
+  let mut m_prime = *m;
+  m_prime[1] = m[1].wrapping_add(1 << 31);
+  m_prime[2] = m[2].wrapping_add(1 << 31).wrapping_sub(1 << 28);
+  m_prime[12] = m[12].wrapping_sub(1 << 16);
+  m_prime
+}
+
+// The states in the order they're computed: a0, d0, c0, b0, a1, d1, ...,
+// b4, so the step computing states[i + 4] replaces states[i] and reads
+// states[i + 1..i + 4].
+type Round1States = [u32; 20];
+
+fn meet_round_1_conditions(m: &mut [u32; 16]) -> Round1States {
+  let mut states = [0u32; 20];
+  states[..4].copy_from_slice(&[
+    INITIAL_WORDS[0],
+    INITIAL_WORDS[3],
+    INITIAL_WORDS[2],
+    INITIAL_WORDS[1],
+  ]);
+  for i in 0..16 {
+    let value = round_1_step(&states, i, m[i]);
+    let previous = states[i + 3];
+    states[i + 4] = ROUND_1_CONDITIONS[i]
+      .iter()
+      .fold(value, |v, c| apply(v, previous, *c));
+    m[i] = round_1_message_word(&states, i);
+  }
+  states
+}
+
+fn round_1_step(states: &Round1States, i: usize, word: u32) -> u32 {
+  states[i]
+    .wrapping_add(f(states[i + 3], states[i + 2], states[i + 1]))
+    .wrapping_add(word)
+    .rotate_left(SHIFTS[0][i % 4])
+}
+
+// the message word that makes step i produce states[i + 4]
+fn round_1_message_word(states: &Round1States, i: usize) -> u32 {
+  states[i + 4]
+    .rotate_right(SHIFTS[0][i % 4])
+    .wrapping_sub(states[i])
+    .wrapping_sub(f(states[i + 3], states[i + 2], states[i + 1]))
+}
+
+fn apply(value: u32, previous: u32, condition: Condition) -> u32 {
+  match condition {
+    Zero(bit) => value & !(1 << bit),
+    One(bit) => value | 1 << bit,
+    Equal(bit) => value & !(1 << bit) | previous & 1 << bit,
+  }
+}
+
+fn bit(word: u32, i: u32) -> u32 {
+  word >> i & 1
+}
+
+fn round_2_step(a: u32, b: u32, c: u32, d: u32, word: u32, shift: u32) -> u32 {
+  a.wrapping_add(g(b, c, d))
+    .wrapping_add(word)
+    .wrapping_add(ROUND_CONSTANTS[1])
+    .rotate_left(shift)
+}
+
+// a5 = (a4 + G(b4, c4, d4) + m0 + k) <<< 3 and d5 likewise with m4. Bit i
+// of a5 moves with bit i of a1, through m0, and bit i of d5 with bit i - 2
+// of a2, through m4. Flipping those first round bits touches none of that
+// round's conditions, and re-solving for the message words of the next
+// four steps keeps every later state word as it was.
+fn meet_round_2_conditions(m: &mut [u32; 16], mut states: Round1States) {
+  let (a4, d4, c4, b4) = (states[16], states[17], states[18], states[19]);
+
+  let a5_conditions = [(18, bit(c4, 18)), (25, 1), (26, 0), (28, 1), (31, 1)];
+  for &(i, wanted) in a5_conditions.iter() {
+    let a5 = round_2_step(a4, b4, c4, d4, m[0], SHIFTS[1][0]);
+    if bit(a5, i) != wanted {
+      states[4] ^= 1 << i;
+      for (step, word) in m.iter_mut().enumerate().take(5) {
+        *word = round_1_message_word(&states, step);
+      }
+    }
+  }
+
+  let a5 = round_2_step(a4, b4, c4, d4, m[0], SHIFTS[1][0]);
+  let d5_conditions = [
+    (18, bit(a5, 18)),
+    (25, bit(b4, 25)),
+    (26, bit(b4, 26)),
+    (28, bit(b4, 28)),
+    (31, bit(b4, 31)),
+  ];
+  for &(i, wanted) in d5_conditions.iter() {
+    let d5 = round_2_step(d4, a5, b4, c4, m[4], SHIFTS[1][1]);
+    if bit(d5, i) != wanted {
+      states[8] ^= 1 << (i - 2);
+      for (step, word) in m.iter_mut().enumerate().skip(4).take(5) {
+        *word = round_1_message_word(&states, step);
+      }
+    }
+  }
+}
+
+fn compress_words(m: &[u32; 16]) -> [u32; 4] {
+  let mut words = INITIAL_WORDS;
+  md4::compress(&mut words, &words_to_bytes(m));
+  words
+}
+
+fn words_to_bytes(m: &[u32; 16]) -> Vec<u8> {
+  let mut bytes = vec![0u8; 64];
+  LittleEndian::write_u32_into(m, &mut bytes);
+  bytes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::md4::md4;
+
+  // the first collision in the paper
+  #[test]
+  fn the_published_messages_collide() {
+    let words = [
+      0x4d7a9c83, 0x56cb927a, 0xb9d5a578, 0x57a7a5ee, 0xde748a3c, 0xdcc366b3,
+      0xb683a020, 0x3b2a5d9f, 0xc69d71b3, 0xf9e99198, 0xd79f805e, 0xa63bb2e8,
+      0x45dd8e31, 0x97e31fe5, 0x2794bf08, 0xb9e8c3e9,
+    ];
+    let m = words_to_bytes(&words);
+    let m_prime = words_to_bytes(&differential(&words));
+
+    assert_ne!(m, m_prime);
+    assert_eq!(md4(&m), md4(&m_prime));
+  }
+
+  #[test]
+  fn it_meets_the_first_round_conditions() {
+    let mut m = [0u32; 16];
+    for word in m.iter_mut() {
+      *word = rand::random();
+    }
+    let states = meet_round_1_conditions(&mut m);
+
+    for i in 0..16 {
+      assert_eq!(round_1_step(&states, i, m[i]), states[i + 4]);
+      for condition in ROUND_1_CONDITIONS[i].iter() {
+        assert_eq!(
+          apply(states[i + 4], states[i + 3], *condition),
+          states[i + 4]
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn it_finds_an_md4_collision() {
+    let collision = find_collision();
+    let (m, m_prime) = &collision.messages;
+
+    assert_eq!(m.len(), 64);
+    assert_ne!(m, m_prime);
+    assert_eq!(md4(m), md4(m_prime));
+  }
+}
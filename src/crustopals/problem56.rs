@@ -0,0 +1,365 @@
+use crustopals::base64;
+use crustopals::tools::aes;
+use crustopals::tools::rc4::Rc4;
+use crustopals::tools::stream_cipher::StreamCipher;
+use std::thread;
+
+// RC4 single byte biases. Some keystream bytes are more likely than others
+// whatever the key, so when the same plaintext is encrypted under enough
+// fresh keys the most common ciphertext byte at a biased position is the
+// plaintext byte xor the favoured keystream byte. A request prefix of our
+// choosing slides each cookie byte under every biased position in turn.
+
+pub const COOKIE: &str = "QkUgU1VSRSBUTyBEUklOSyBZT1VSIE9WQUxUSU5F";
+
+// Keystream byte `position` (0 based, so Z16 sits at 15) equals `value`
+// with `probability` instead of 1/256.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeystreamBias {
+  pub position: usize,
+  pub value: u8,
+  pub probability: f64,
+}
+
+// Mantin and Shamir's Z2 == 0, twice as likely as it should be.
+pub const Z2: KeystreamBias = KeystreamBias {
+  position: 1,
+  value: 0,
+  probability: 2.0 / 256.0,
+};
+
+// AlFardan et al.'s Z16 == 240 and Z32 == 224, only about 1 + 2^-4.8 and
+// 1 + 2^-5.3 times as likely as they should be (measured here over 2^27
+// keys), so each byte takes millions of ciphertexts. They are far enough in
+// to cover a whole cookie though.
+pub const Z16: KeystreamBias = KeystreamBias {
+  position: 15,
+  value: 240,
+  probability: 1.0375 / 256.0,
+};
+
+pub const Z32: KeystreamBias = KeystreamBias {
+  position: 31,
+  value: 224,
+  probability: 1.0245 / 256.0,
+};
+
+pub struct Rc4Oracle {
+  cookie: Vec<u8>,
+  drop: usize,
+}
+
+impl Rc4Oracle {
+  pub fn new(cookie: &[u8]) -> Rc4Oracle {
+    Rc4Oracle::with_drop(cookie, 0)
+  }
+
+  pub fn with_drop(cookie: &[u8], drop: usize) -> Rc4Oracle {
+    Rc4Oracle {
+      cookie: cookie.to_vec(),
+      drop,
+    }
+  }
+
+  // request_prefix || cookie under a fresh random 128 bit key.
+  pub fn encrypt(&self, request_prefix: &[u8]) -> Vec<u8> {
+    Rc4::with_drop(&aes::generate_key(), self.drop)
+      .expect("16 byte keys are valid")
+      .apply_keystream(&[request_prefix, &self.cookie[..]].concat())
+  }
+}
+
+pub struct RecoveryConfig {
+  pub biases: Vec<KeystreamBias>,
+  // ciphertexts requested for every prefix length
+  pub samples_per_prefix: usize,
+  pub threads: usize,
+}
+
+impl RecoveryConfig {
+  pub fn new(samples_per_prefix: usize) -> RecoveryConfig {
+    RecoveryConfig {
+      biases: vec![Z16, Z32],
+      samples_per_prefix,
+      threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteGuess {
+  pub value: u8,
+  // the posterior probability of `value` over all 256 candidates, assuming
+  // the biases are what they claim to be
+  pub confidence: f64,
+  pub samples: usize,
+}
+
+#[derive(Debug)]
+pub struct RecoveryReport {
+  // None for bytes no bias ever lined up with
+  pub bytes: Vec<Option<ByteGuess>>,
+  pub ciphertexts: usize,
+}
+
+impl RecoveryReport {
+  // The recovered plaintext, if every byte was covered.
+  pub fn plaintext(&self) -> Option<Vec<u8>> {
+    self
+      .bytes
+      .iter()
+      .map(|guess| guess.map(|guess| guess.value))
+      .collect()
+  }
+
+  pub fn min_confidence(&self) -> f64 {
+    self
+      .bytes
+      .iter()
+      .map(|guess| guess.map_or(0.0, |guess| guess.confidence))
+      .fold(1.0, f64::min)
+  }
+}
+
+// Recovers the secret appended to whatever prefix `encrypt` is given, as
+// long as each call uses a fresh RC4 key.
+pub fn recover_plaintext<F>(
+  encrypt: &F,
+  config: &RecoveryConfig,
+) -> RecoveryReport
+where
+  F: Fn(&[u8]) -> Vec<u8> + Sync,
+{
+  let secret_len = encrypt(&[]).len();
+  let max_position = config.biases.iter().map(|b| b.position).max();
+  let mut evidence: Vec<Vec<(KeystreamBias, Vec<u64>)>> =
+    vec![vec![]; secret_len];
+  let mut ciphertexts = 0;
+
+  for prefix_len in 0..=max_position.unwrap_or(0) {
+    let biases: Vec<KeystreamBias> = config
+      .biases
+      .iter()
+      .filter(|bias| {
+        bias.position >= prefix_len && bias.position - prefix_len < secret_len
+      })
+      .cloned()
+      .collect();
+    if biases.is_empty() {
+      continue;
+    }
+    let positions: Vec<usize> = biases.iter().map(|b| b.position).collect();
+    let histograms =
+      collect_histograms(encrypt, &vec![b'A'; prefix_len], &positions, config);
+    ciphertexts += config.samples_per_prefix;
+    for (bias, histogram) in biases.into_iter().zip(histograms) {
+      evidence[bias.position - prefix_len].push((bias, histogram));
+    }
+  }
+
+  RecoveryReport {
+    bytes: evidence
+      .iter()
+      .map(|byte_evidence| {
+        if byte_evidence.is_empty() {
+          None
+        } else {
+          Some(best_guess(byte_evidence))
+        }
+      })
+      .collect(),
+    ciphertexts,
+  }
+}
+
+// How often keystream byte `position` equals `value` across random keys.
+pub fn observed_probability(
+  position: usize,
+  value: u8,
+  drop: usize,
+  samples: usize,
+  threads: usize,
+) -> f64 {
+  let zeros = vec![0u8; position + 1];
+  let encrypt = |prefix: &[u8]| {
+    let rc4 = Rc4::with_drop(&aes::generate_key(), drop).unwrap();
+    rc4.apply_keystream(prefix)
+  };
+  let config = RecoveryConfig {
+    biases: vec![],
+    samples_per_prefix: samples,
+    threads,
+  };
+  let histograms = collect_histograms(&encrypt, &zeros, &[position], &config);
+  histograms[0][value as usize] as f64 / samples as f64
+}
+
+// Byte histograms at each of `positions` over the configured number of
+// ciphertexts, split across the configured number of threads.
+fn collect_histograms<F>(
+  encrypt: &F,
+  prefix: &[u8],
+  positions: &[usize],
+  config: &RecoveryConfig,
+) -> Vec<Vec<u64>>
+where
+  F: Fn(&[u8]) -> Vec<u8> + Sync,
+{
+  let threads = config.threads.max(1);
+  thread::scope(|scope| {
+    let workers: Vec<_> = (0..threads)
+      .map(|worker| {
+        let samples = config.samples_per_prefix / threads
+          + usize::from(worker < config.samples_per_prefix % threads);
+        scope.spawn(move || {
+          let mut histograms = vec![vec![0u64; 256]; positions.len()];
+          for _ in 0..samples {
+            let ciphertext = encrypt(prefix);
+            for (histogram, position) in histograms.iter_mut().zip(positions) {
+              histogram[ciphertext[*position] as usize] += 1;
+            }
+          }
+          histograms
+        })
+      })
+      .collect();
+    workers.into_iter().fold(
+      vec![vec![0u64; 256]; positions.len()],
+      |mut totals, worker| {
+        let histograms = worker.join().expect("worker thread panicked");
+        for (total, histogram) in totals.iter_mut().zip(histograms) {
+          for (t, h) in total.iter_mut().zip(histogram) {
+            *t += h;
+          }
+        }
+        totals
+      },
+    )
+  })
+}
+
+// Scores every candidate p by log likelihood: a ciphertext byte c with
+// c ^ p == bias.value is a hit with the bias's probability, any other byte a
+// miss with the rest spread evenly. The confidence is the best candidate's
+// share of the total likelihood.
+fn best_guess(evidence: &[(KeystreamBias, Vec<u64>)]) -> ByteGuess {
+  let mut scores = [0f64; 256];
+  let mut samples = 0;
+  for (bias, histogram) in evidence.iter() {
+    let hit = bias.probability.ln();
+    let miss = ((1.0 - bias.probability) / 255.0).ln();
+    let total: u64 = histogram.iter().sum();
+    samples += total as usize;
+    for (candidate, score) in scores.iter_mut().enumerate() {
+      let hits = histogram[candidate ^ bias.value as usize] as f64;
+      *score += hits * hit + (total as f64 - hits) * miss;
+    }
+  }
+  let (value, best) = scores.iter().enumerate().fold(
+    (0, f64::NEG_INFINITY),
+    |(best_value, best), (value, score)| {
+      if *score > best {
+        (value, *score)
+      } else {
+        (best_value, best)
+      }
+    },
+  );
+  let normaliser: f64 = scores.iter().map(|score| (score - best).exp()).sum();
+  ByteGuess {
+    value: value as u8,
+    confidence: 1.0 / normaliser,
+    samples,
+  }
+}
+
+pub fn cookie() -> Vec<u8> {
+  base64::decode(COOKIE).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::rand;
+
+  #[test]
+  fn drop_n_removes_the_z2_bias() {
+    let samples = 1 << 16;
+
+    assert!(observed_probability(1, 0, 0, samples, 4) > 1.5 / 256.0);
+    assert!(observed_probability(1, 0, 768, samples, 4) < 1.5 / 256.0);
+  }
+
+  #[test]
+  fn it_picks_the_most_likely_byte() {
+    let mut histogram = vec![1000u64; 256];
+    histogram[b'x' as usize ^ Z16.value as usize] += 400;
+    let guess = best_guess(&[(Z16, histogram)]);
+
+    assert_eq!(guess.value, b'x');
+    assert!(guess.confidence > 0.99);
+    assert_eq!(guess.samples, 256 * 1000 + 400);
+  }
+
+  #[test]
+  fn it_recovers_the_bytes_under_the_z2_bias() {
+    let oracle = Rc4Oracle::new(&cookie());
+    let config = RecoveryConfig {
+      biases: vec![Z2],
+      samples_per_prefix: 1 << 15,
+      threads: 4,
+    };
+    let report =
+      recover_plaintext(&|prefix: &[u8]| oracle.encrypt(prefix), &config);
+
+    assert_eq!(report.ciphertexts, 2 << 15);
+    assert_eq!(report.bytes.len(), cookie().len());
+    for (guess, expected) in report.bytes[..2].iter().zip(cookie()) {
+      let guess = guess.unwrap();
+      assert_eq!(guess.value, expected);
+      assert!(guess.confidence > 0.99);
+    }
+    assert!(report.bytes[2..].iter().all(|guess| guess.is_none()));
+    assert_eq!(report.plaintext(), None);
+    assert_eq!(report.min_confidence(), 0.0);
+  }
+
+  // Real Z16 and Z32 need millions of ciphertexts per byte, far too many for
+  // a test, so this keystream is uniform except at those two positions,
+  // where it lands on the biased value three times as often as it should.
+  fn exaggerated_z16_z32(plaintext: &[u8]) -> Vec<u8> {
+    plaintext
+      .iter()
+      .enumerate()
+      .map(|(position, byte)| {
+        let lean = rand::random::<u8>() < 2;
+        let keystream = match position {
+          15 if lean => Z16.value,
+          31 if lean => Z32.value,
+          _ => rand::random(),
+        };
+        byte ^ keystream
+      })
+      .collect()
+  }
+
+  #[test]
+  fn it_recovers_the_whole_cookie_under_z16_and_z32() {
+    let cookie = cookie();
+    let encrypt =
+      |prefix: &[u8]| exaggerated_z16_z32(&[prefix, &cookie[..]].concat());
+    let config = RecoveryConfig {
+      biases: vec![Z16, Z32],
+      samples_per_prefix: 1 << 12,
+      threads: 4,
+    };
+    let report = recover_plaintext(&encrypt, &config);
+
+    // a prefix of each length from 0 to 31 slides every byte under Z32 and
+    // the first 16 under Z16 as well
+    assert_eq!(report.ciphertexts, 32 << 12);
+    assert_eq!(report.plaintext(), Some(cookie.clone()));
+    assert_eq!(report.bytes[15].unwrap().samples, 2 << 12);
+    assert_eq!(report.bytes[29].unwrap().samples, 1 << 12);
+  }
+}
@@ -30,7 +30,7 @@ pub fn possible_solution_for_keysize(
 ) -> (f32, String) {
   let key = find_likely_key(&bytes, keysize);
   let expanded_key = tools::expand_bytes(&key, bytes.len());
-  let plaintext_bytes = xor_bytes(bytes, &expanded_key);
+  let plaintext_bytes = xor_bytes(bytes, &expanded_key).unwrap();
   let possible_pt = tools::bytes_to_string(plaintext_bytes);
   let english_distance = freq_analysis::english_distance(&possible_pt);
   (english_distance, possible_pt)
@@ -95,7 +95,8 @@ fn h_dist_for_keysize(bytes: &Vec<u8>, keysize: usize) -> f32 {
   for (i, block1) in blocks.to_vec().iter().enumerate() {
     for (j, block2) in blocks.to_vec().iter().enumerate() {
       if j > i {
-        let hamming_distance = tools::hamming_distance(block1, block2);
+        let hamming_distance =
+          tools::hamming_distance(block1, block2).unwrap();
         normalized_h_distances += hamming_distance as f32 / keysize as f32;
         comparisons += 1;
       }
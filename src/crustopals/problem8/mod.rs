@@ -18,10 +18,14 @@ pub fn detect_aes_ecb(filepath: String) -> String {
 }
 
 pub fn has_repeat_blocks(blob: &[u8]) -> bool {
-  let num_blocks = blob.len() / 16;
+  has_repeat_blocks_of_size(blob, 16)
+}
+
+pub fn has_repeat_blocks_of_size(blob: &[u8], block_size: usize) -> bool {
+  let num_blocks = blob.len() / block_size;
   let mut deduped_blocks = HashSet::new();
 
-  for block in blob.chunks(16) {
+  for block in blob.chunks(block_size) {
     deduped_blocks.insert(block);
   }
 
@@ -33,6 +37,8 @@ mod tests {
   extern crate hex;
 
   use super::*;
+  use crustopals::tools::des::Des;
+  use crustopals::tools::{block_cipher, des};
 
   #[test]
   fn detects_aes_ecb_encryption() {
@@ -48,4 +54,17 @@ c1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd5664891547\
 
     assert_eq!(detected_aes_ecb_hex, aes_hex_example);
   }
+
+  #[test]
+  fn detects_ecb_with_eight_byte_blocks() {
+    let des = Des::new(&des::generate_key()).unwrap();
+    let iv = des::generate_iv();
+    let repeated_message = "16 bytes repeats16 bytes repeats".as_bytes();
+    let ecb_ct = block_cipher::encrypt_ecb(&des, repeated_message);
+    let cbc_ct =
+      block_cipher::encrypt_cbc(&des, repeated_message, &iv).unwrap();
+
+    assert!(has_repeat_blocks_of_size(&ecb_ct, 8));
+    assert!(!has_repeat_blocks_of_size(&cbc_ct, 8));
+  }
 }
@@ -1,3 +1,5 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::crc32::crc32;
 use crustopals::tools::*;
 
 lazy_static! {
@@ -7,28 +9,76 @@ lazy_static! {
     ";comment2=%20like%20a%20pound%20of%20bacon".to_string();
 }
 
-pub fn cbc_encrypt(key: &[u8], iv: &[u8], pt_bytes: &[u8]) -> Vec<u8> {
+pub fn cbc_encrypt(
+  key: &[u8],
+  iv: &[u8],
+  pt_bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
   let mut msg = PREPEND_STR.as_bytes().to_vec().clone();
   msg.extend(filter_pt(pt_bytes));
   msg.extend(APPEND_STR.as_bytes().to_vec());
   aes::encrypt_message_cbc(&msg, key, iv)
 }
 
-pub fn ctr_encrypt(key: &[u8], nonce: &[u8], pt_bytes: &[u8]) -> Vec<u8> {
+pub fn ctr_encrypt(
+  key: &[u8],
+  nonce: &[u8],
+  pt_bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
   let mut msg = PREPEND_STR.as_bytes().to_vec().clone();
   msg.extend(filter_pt(pt_bytes));
   msg.extend(APPEND_STR.as_bytes().to_vec());
   aes::encrypt_ctr(&msg, key, nonce)
 }
 
-pub fn cbc_decrypt(key: &[u8], iv: &[u8], ct_bytes: &[u8]) -> Vec<u8> {
-  aes::decrypt_message_cbc(&ct_bytes, key, iv).unwrap()
+// As `ctr_encrypt`, with a CRC-32 of the message appended before
+// encrypting, for a receiver that checks it.
+pub fn ctr_encrypt_with_crc(
+  key: &[u8],
+  nonce: &[u8],
+  pt_bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let mut msg = PREPEND_STR.as_bytes().to_vec();
+  msg.extend(filter_pt(pt_bytes));
+  msg.extend(APPEND_STR.as_bytes());
+  let mut crc = [0u8; 4];
+  LittleEndian::write_u32(&mut crc, crc32(&msg));
+  msg.extend(crc.iter());
+  aes::encrypt_ctr(&msg, key, nonce)
 }
 
-pub fn ctr_decrypt(key: &[u8], nonce: &[u8], ct_bytes: &[u8]) -> Vec<u8> {
+pub fn cbc_decrypt(
+  key: &[u8],
+  iv: &[u8],
+  ct_bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  aes::decrypt_message_cbc(ct_bytes, key, iv)
+}
+
+pub fn ctr_decrypt(
+  key: &[u8],
+  nonce: &[u8],
+  ct_bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
   aes::decrypt_ctr(&ct_bytes, key, nonce)
 }
 
+pub fn ctr_decrypt_with_crc(
+  key: &[u8],
+  nonce: &[u8],
+  ct_bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let decrypted = aes::decrypt_ctr(ct_bytes, key, nonce)?;
+  if decrypted.len() < 4 {
+    return Err(CryptoError::AuthenticationFailed);
+  }
+  let (msg, crc) = decrypted.split_at(decrypted.len() - 4);
+  if LittleEndian::read_u32(crc) != crc32(msg) {
+    return Err(CryptoError::AuthenticationFailed);
+  }
+  Ok(msg.to_vec())
+}
+
 pub fn filter_pt(bytes: &[u8]) -> Vec<u8> {
   let mut quoted_bytes: Vec<u8> = vec![];
   for b in bytes.iter() {
@@ -48,8 +98,10 @@ pub fn cbc_decrypts_with_admin_rights(
   iv: &[u8],
   ct_bytes: &[u8],
 ) -> bool {
-  let decrypted: Vec<u8> = cbc_decrypt(key, iv, ct_bytes);
-  has_admin_rights(&decrypted)
+  match cbc_decrypt(key, iv, ct_bytes) {
+    Ok(decrypted) => has_admin_rights(&decrypted),
+    Err(_) => false,
+  }
 }
 
 pub fn ctr_decrypts_with_admin_rights(
@@ -57,20 +109,30 @@ pub fn ctr_decrypts_with_admin_rights(
   nonce: &[u8],
   ct_bytes: &[u8],
 ) -> bool {
-  let decrypted: Vec<u8> = ctr_decrypt(key, nonce, ct_bytes);
-  has_admin_rights(&decrypted)
+  match ctr_decrypt(key, nonce, ct_bytes) {
+    Ok(decrypted) => has_admin_rights(&decrypted),
+    Err(_) => false,
+  }
+}
+
+pub fn ctr_crc_decrypts_with_admin_rights(
+  key: &[u8],
+  nonce: &[u8],
+  ct_bytes: &[u8],
+) -> bool {
+  match ctr_decrypt_with_crc(key, nonce, ct_bytes) {
+    Ok(decrypted) => has_admin_rights(&decrypted),
+    Err(_) => false,
+  }
 }
 
 pub fn has_admin_rights(decrypted: &[u8]) -> bool {
   let admin_str = ";admin=true;".to_string();
 
   let admin_bytes = admin_str.as_bytes();
-  for i in 0..=(decrypted.len() - admin_bytes.len()) {
-    if decrypted[i..(i + admin_bytes.len())] == admin_bytes[..] {
-      return true;
-    }
-  }
-  return false;
+  decrypted
+    .windows(admin_bytes.len())
+    .any(|window| window == admin_bytes)
 }
 
 #[cfg(test)]
@@ -99,10 +161,33 @@ mod tests {
     let iv: Vec<u8> = aes::generate_iv();
     let string = ";admin=true;".to_string();
     let bytes: &[u8] = string.as_bytes();
-    let ct = cbc_encrypt(&key, &iv, &bytes);
-    let decrypted_bytes = cbc_decrypt(&key, &iv, &ct);
+    let ct = cbc_encrypt(&key, &iv, bytes).unwrap();
+    let decrypted_bytes = cbc_decrypt(&key, &iv, &ct).unwrap();
 
     assert_ne!(bytes.to_vec(), decrypted_bytes);
     assert!(!cbc_decrypts_with_admin_rights(&key, &iv, &ct));
   }
+
+  #[test]
+  fn the_crc_catches_flipped_bits() {
+    let key: Vec<u8> = aes::generate_key();
+    let nonce: Vec<u8> = aes::generate_rand_bytes(8);
+    let mut ct = ctr_encrypt_with_crc(&key, &nonce, b"data").unwrap();
+
+    assert_eq!(
+      ctr_decrypt_with_crc(&key, &nonce, &ct).unwrap(),
+      [PREPEND_STR.as_bytes(), b"data", APPEND_STR.as_bytes()].concat()
+    );
+    ct[3] ^= 1;
+    assert_eq!(
+      ctr_decrypt_with_crc(&key, &nonce, &ct),
+      Err(CryptoError::AuthenticationFailed)
+    );
+  }
+
+  #[test]
+  fn short_plaintexts_dont_have_admin_rights() {
+    assert!(!has_admin_rights(b"admin"));
+    assert!(has_admin_rights(b";admin=true;"));
+  }
 }
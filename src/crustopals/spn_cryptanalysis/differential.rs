@@ -0,0 +1,114 @@
+extern crate rand;
+
+use super::*;
+use crustopals::tools::sbox::SBox;
+use crustopals::tools::spn;
+
+// The most probable characteristic over `rounds` rounds (one less than the
+// cipher's) whose output difference touches at most `max_active_last`
+// S-boxes of the final round.
+pub fn best_characteristic(
+  sbox: &[u8; 16],
+  rounds: usize,
+  max_active_last: usize,
+) -> Option<Trail> {
+  let ddt = SBox::new(sbox)
+    .expect("S-box entries are 4 bits")
+    .difference_distribution_table();
+  let mut probabilities = [[0f64; 16]; 16];
+  for (row, ddt_row) in probabilities.iter_mut().zip(ddt.iter()) {
+    for (probability, count) in row.iter_mut().zip(ddt_row.iter()) {
+      *probability = *count as f64 / 16.0;
+    }
+  }
+  search_trail(&probabilities, rounds, max_active_last)
+}
+
+// Chosen plaintext attack on the last round subkey: encrypt `num_pairs`
+// pairs with the characteristic's input difference, drop pairs whose
+// ciphertexts differ outside the active S-boxes (they can't be right pairs)
+// and count, for every guess of the active subkey bits, how often partially
+// decrypting the pair lands on the characteristic's output difference. The
+// right guess hits about `probability * num_pairs` times.
+pub fn recover_last_round_subkey<F: Fn(u16) -> u16>(
+  encrypt: &F,
+  sbox: &[u8; 16],
+  characteristic: &Trail,
+  num_pairs: usize,
+) -> PartialSubkey {
+  let active = characteristic.active_sboxes();
+  let mask = sbox_mask(&active);
+  let inv_sbox = spn::invert_sbox(sbox);
+  let ciphertext_pairs: Vec<(u16, u16)> = (0..num_pairs)
+    .map(|_| {
+      let plaintext = rand::random::<u16>();
+      (
+        encrypt(plaintext),
+        encrypt(plaintext ^ characteristic.input),
+      )
+    })
+    .filter(|(c1, c2)| (c1 ^ c2) & !mask == 0)
+    .collect();
+
+  candidate_subkeys(&active)
+    .into_iter()
+    .map(|key| {
+      let hits = ciphertext_pairs
+        .iter()
+        .filter(|(c1, c2)| {
+          partial_decrypt(&inv_sbox, *c1, key, &active)
+            ^ partial_decrypt(&inv_sbox, *c2, key, &active)
+            == characteristic.output
+        })
+        .count();
+      PartialSubkey {
+        key,
+        mask,
+        score: hits as f64 / num_pairs as f64,
+      }
+    })
+    .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::aes;
+  use crustopals::tools::spn::{Spn, HEYS_SBOX};
+
+  #[test]
+  fn it_finds_a_characteristic_at_least_as_good_as_the_tutorials() {
+    let characteristic = best_characteristic(&HEYS_SBOX, 3, 2).unwrap();
+
+    assert!(characteristic.weight >= 27.0 / 1024.0);
+    assert!(characteristic.active_sboxes().len() <= 2);
+    assert_eq!(active_sboxes(characteristic.input).len(), 1);
+    let ddt = SBox::new(&HEYS_SBOX)
+      .unwrap()
+      .difference_distribution_table();
+    let probability = characteristic.steps.iter().fold(1.0, |acc, step| {
+      acc * ddt[step.input as usize][step.output as usize] as f64 / 16.0
+    });
+    assert!((probability - characteristic.weight).abs() < 1e-12);
+  }
+
+  #[test]
+  fn it_recovers_last_round_subkey_bits() {
+    let key = aes::generate_rand_bytes(10);
+    let spn = Spn::new(&key).unwrap();
+    let last_subkey = ((key[8] as u16) << 8) | key[9] as u16;
+    let characteristic = best_characteristic(&HEYS_SBOX, 3, 2).unwrap();
+    let num_pairs = (100.0 / characteristic.weight) as usize;
+
+    let recovered = recover_last_round_subkey(
+      &|block| spn.encrypt(block),
+      &HEYS_SBOX,
+      &characteristic,
+      num_pairs,
+    );
+
+    assert_eq!(recovered.key, last_subkey & recovered.mask);
+    assert!(recovered.score > characteristic.weight / 2.0);
+  }
+}
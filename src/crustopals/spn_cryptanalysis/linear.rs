@@ -0,0 +1,169 @@
+use super::*;
+use crustopals::tools::sbox::SBox;
+use crustopals::tools::spn;
+use std::collections::HashMap;
+
+// Matsui's piling-up lemma: xoring n independent approximations with biases
+// e_i gives an approximation with bias 2^(n-1) * e_1 * ... * e_n.
+pub fn piling_up(biases: &[f64]) -> f64 {
+  biases.iter().fold(0.5, |acc, bias| acc * 2.0 * bias)
+}
+
+// The bias of a whole approximation, piled up from its S-box steps.
+pub fn approximation_bias(sbox: &[u8; 16], approximation: &Trail) -> f64 {
+  let lat = SBox::new(sbox)
+    .expect("S-box entries are 4 bits")
+    .linear_approximation_table();
+  let biases: Vec<f64> = approximation
+    .steps
+    .iter()
+    .map(|step| lat[step.input as usize][step.output as usize] as f64 / 16.0)
+    .collect();
+  piling_up(&biases)
+}
+
+// The linear approximation over `rounds` rounds (one less than the cipher's)
+// with the largest absolute bias whose output mask touches at most
+// `max_active_last` S-boxes of the final round.
+pub fn best_approximation(
+  sbox: &[u8; 16],
+  rounds: usize,
+  max_active_last: usize,
+) -> Option<Trail> {
+  let lat = SBox::new(sbox)
+    .expect("S-box entries are 4 bits")
+    .linear_approximation_table();
+  let mut correlations = [[0f64; 16]; 16];
+  for (row, lat_row) in correlations.iter_mut().zip(lat.iter()) {
+    for (correlation, entry) in row.iter_mut().zip(lat_row.iter()) {
+      *correlation = (*entry as f64 / 8.0).abs();
+    }
+  }
+  search_trail(&correlations, rounds, max_active_last)
+}
+
+// Roughly how many known plaintexts an approximation needs: about 1 / bias^2
+// for the right subkey to stand out.
+pub fn required_known_plaintexts(bias: f64) -> usize {
+  (1.0 / (bias * bias)).ceil() as usize
+}
+
+// Matsui's Algorithm 2: for every guess of the subkey bits under the
+// approximation's active last round S-boxes, partially decrypt each known
+// ciphertext and count how often the approximation holds. Guesses are ranked
+// by how far their count strays from half, best first.
+pub fn matsui_algorithm_2(
+  known_pairs: &[(u16, u16)],
+  sbox: &[u8; 16],
+  approximation: &Trail,
+) -> Vec<PartialSubkey> {
+  let active = approximation.active_sboxes();
+  let mask = sbox_mask(&active);
+  let inv_sbox = spn::invert_sbox(sbox);
+  // only the active ciphertext bits and the plaintext parity matter, so
+  // tally those once instead of walking every pair for every guess
+  let mut tally: HashMap<(u16, bool), usize> = HashMap::new();
+  for (plaintext, ciphertext) in known_pairs {
+    let key = (ciphertext & mask, parity(plaintext & approximation.input));
+    *tally.entry(key).or_insert(0) += 1;
+  }
+
+  let mut ranking: Vec<PartialSubkey> = candidate_subkeys(&active)
+    .into_iter()
+    .map(|key| {
+      let holds: usize = tally
+        .iter()
+        .filter(|((ciphertext, plaintext_parity), _)| {
+          let last_round_input =
+            partial_decrypt(&inv_sbox, *ciphertext, key, &active);
+          *plaintext_parity == parity(last_round_input & approximation.output)
+        })
+        .map(|(_, count)| count)
+        .sum();
+      let bias = holds as f64 / known_pairs.len() as f64 - 0.5;
+      PartialSubkey {
+        key,
+        mask,
+        score: bias.abs(),
+      }
+    })
+    .collect();
+  ranking.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+  ranking
+}
+
+fn parity(bits: u16) -> bool {
+  bits.count_ones() % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::aes;
+  use crustopals::tools::spn::{Spn, HEYS_SBOX};
+
+  #[test]
+  fn it_piles_up_biases() {
+    // the tutorial's trail: one +1/4 and three -1/4 approximations
+    assert_eq!(piling_up(&[0.25, -0.25, -0.25, -0.25]), -1.0 / 32.0);
+    assert_eq!(piling_up(&[0.25]), 0.25);
+    assert_eq!(required_known_plaintexts(1.0 / 32.0), 1024);
+  }
+
+  #[test]
+  fn it_finds_an_approximation_at_least_as_good_as_the_tutorials() {
+    let approximation = best_approximation(&HEYS_SBOX, 3, 2).unwrap();
+    let bias = approximation_bias(&HEYS_SBOX, &approximation);
+
+    assert!(bias.abs() >= 1.0 / 32.0);
+    assert!((bias.abs() * 2.0 - approximation.weight).abs() < 1e-12);
+    assert!(approximation.active_sboxes().len() <= 2);
+  }
+
+  #[test]
+  fn the_approximation_holds_with_the_predicted_bias() {
+    let key = aes::generate_rand_bytes(8);
+    let three_rounds = Spn::with_rounds(&key, 3).unwrap();
+    let approximation = best_approximation(&HEYS_SBOX, 3, 2).unwrap();
+    let bias = approximation_bias(&HEYS_SBOX, &approximation);
+    // the three round cipher ends with S-boxes and a key mix; stripping the
+    // key and permuting gives the fourth round's S-box input up to its
+    // (constant) key bits
+    let last_subkey = ((key[6] as u16) << 8) | key[7] as u16;
+    let holds = (0..=0xffffu16)
+      .filter(|plaintext| {
+        let u4 = spn::permute(three_rounds.encrypt(*plaintext) ^ last_subkey);
+        parity(plaintext & approximation.input)
+          == parity(u4 & approximation.output)
+      })
+      .count();
+    let measured = holds as f64 / 65536.0 - 0.5;
+
+    // the key bits xored in along the way can flip the sign
+    assert!((measured.abs() - bias.abs()).abs() < bias.abs() / 2.0);
+  }
+
+  #[test]
+  fn it_recovers_last_round_subkey_bits_from_known_plaintexts() {
+    let key = [0x3a, 0x94, 0xd6, 0x3f, 0x52, 0x1e, 0x0b, 0x87, 0xc4, 0x6d];
+    let spn = Spn::new(&key).unwrap();
+    let last_subkey = ((key[8] as u16) << 8) | key[9] as u16;
+    let approximation = best_approximation(&HEYS_SBOX, 3, 2).unwrap();
+    let bias = approximation_bias(&HEYS_SBOX, &approximation);
+    // the whole codebook leaves no sampling noise, so the ranking is down to
+    // the key alone; under 1% of keys push a wrong guess past the right one
+    // however many plaintexts there are, this isn't one of them
+    let known_pairs: Vec<(u16, u16)> = (0..=0xffffu16)
+      .map(|plaintext| (plaintext, spn.encrypt(plaintext)))
+      .collect();
+
+    let ranking = matsui_algorithm_2(&known_pairs, &HEYS_SBOX, &approximation);
+    let position = ranking
+      .iter()
+      .position(|guess| guess.key == last_subkey & guess.mask)
+      .unwrap();
+
+    assert_eq!(position, 0);
+    assert!(ranking[position].score > bias.abs() / 4.0);
+  }
+}
@@ -0,0 +1,232 @@
+pub mod differential;
+pub mod linear;
+
+use crustopals::tools::spn;
+
+// Differential and linear cryptanalysis of the Heys toy SPN (tools::spn),
+// following the structure of the tutorial: tabulate the S-box, chain S-box
+// transitions into a trail over all but the last round, then guess the last
+// round subkey bits under the trail's active S-boxes.
+//
+// Both attacks share the trail search. A differential characteristic
+// multiplies S-box probabilities; a linear approximation multiplies S-box
+// correlations (2 * bias), which is the piling-up lemma in disguise. So a
+// trail carries a single multiplicative `weight`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SboxStep {
+  pub round: usize,
+  pub sbox: usize,
+  pub input: u8,
+  pub output: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trail {
+  // plaintext difference or mask
+  pub input: u16,
+  // difference or mask at the input of the last round's S-boxes
+  pub output: u16,
+  pub steps: Vec<SboxStep>,
+  pub weight: f64,
+}
+
+impl Trail {
+  pub fn active_sboxes(&self) -> Vec<usize> {
+    active_sboxes(self.output)
+  }
+}
+
+// The last round subkey bits an attack settled on. `key` only has bits set
+// under `mask`; `score` is whatever the attack ranked candidates by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialSubkey {
+  pub key: u16,
+  pub mask: u16,
+  pub score: f64,
+}
+
+pub fn active_sboxes(block: u16) -> Vec<usize> {
+  (0..4).filter(|idx| spn::nibble(block, *idx) != 0).collect()
+}
+
+// Branch and bound over trails covering `rounds` rounds that start with a
+// single active S-box and end with at most `max_active_last` active S-boxes
+// (each one costs the key recovery a factor of 16). `weights[a][b]` is the
+// weight of S-box input a going to output b.
+fn search_trail(
+  weights: &[[f64; 16]; 16],
+  rounds: usize,
+  max_active_last: usize,
+) -> Option<Trail> {
+  let transitions: Vec<Vec<(u8, f64)>> = weights
+    .iter()
+    .map(|row| {
+      let mut options: Vec<(u8, f64)> = (1..16)
+        .filter(|out| row[*out] > 0.0)
+        .map(|out| (out as u8, row[out]))
+        .collect();
+      // heavy transitions first so good trails tighten the bound early
+      options.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+      options
+    })
+    .collect();
+  let max_weight = transitions[1..]
+    .iter()
+    .flat_map(|options| options.iter().map(|option| option.1))
+    .fold(0.0, f64::max);
+  let mut search = TrailSearch {
+    transitions,
+    max_weight,
+    rounds,
+    max_active_last,
+    input: 0,
+    best: None,
+  };
+  for sbox in 0..4 {
+    for value in 1..16u16 {
+      search.input = value << (12 - 4 * sbox);
+      let input = search.input;
+      search.extend(0, input, 1.0, &mut vec![]);
+    }
+  }
+  search.best
+}
+
+struct TrailSearch {
+  transitions: Vec<Vec<(u8, f64)>>,
+  max_weight: f64,
+  rounds: usize,
+  max_active_last: usize,
+  // the input of the trails currently being extended
+  input: u16,
+  best: Option<Trail>,
+}
+
+impl TrailSearch {
+  fn beats_best(&self, weight: f64) -> bool {
+    match self.best {
+      Some(ref trail) => weight > trail.weight,
+      None => weight > 0.0,
+    }
+  }
+
+  fn extend(
+    &mut self,
+    round: usize,
+    state: u16,
+    weight: f64,
+    steps: &mut Vec<SboxStep>,
+  ) {
+    if round == self.rounds {
+      if active_sboxes(state).len() <= self.max_active_last
+        && self.beats_best(weight)
+      {
+        self.best = Some(Trail {
+          input: self.input,
+          output: state,
+          steps: steps.clone(),
+          weight,
+        });
+      }
+      return;
+    }
+    let active: Vec<(usize, u8)> = active_sboxes(state)
+      .into_iter()
+      .map(|idx| (idx, spn::nibble(state, idx)))
+      .collect();
+    self.expand(round, &active, 0, 0, weight, steps);
+  }
+
+  // Picks an output for each active S-box of this round in turn; `done` of
+  // them already have one.
+  fn expand(
+    &mut self,
+    round: usize,
+    active: &[(usize, u8)],
+    done: usize,
+    output: u16,
+    weight: f64,
+    steps: &mut Vec<SboxStep>,
+  ) {
+    // every later round has at least one active S-box
+    let remaining = (self.rounds - round - 1) + (active.len() - done);
+    if !self.beats_best(weight * self.max_weight.powi(remaining as i32)) {
+      return;
+    }
+    if done == active.len() {
+      self.extend(round + 1, spn::permute(output), weight, steps);
+      return;
+    }
+    let (sbox, input) = active[done];
+    let options = self.transitions[input as usize].clone();
+    for (out, out_weight) in options {
+      steps.push(SboxStep {
+        round,
+        sbox,
+        input,
+        output: out,
+      });
+      let next_output = output | ((out as u16) << (12 - 4 * sbox));
+      self.expand(
+        round,
+        active,
+        done + 1,
+        next_output,
+        weight * out_weight,
+        steps,
+      );
+      steps.pop();
+    }
+  }
+}
+
+// Every assignment of the subkey bits under the active S-boxes.
+fn candidate_subkeys(active: &[usize]) -> Vec<u16> {
+  (0..(1u32 << (4 * active.len())))
+    .map(|guess| {
+      active.iter().enumerate().fold(0u16, |acc, (i, sbox)| {
+        let bits = ((guess >> (4 * i)) & 0xf) as u16;
+        acc | (bits << (12 - 4 * sbox))
+      })
+    })
+    .collect()
+}
+
+fn sbox_mask(active: &[usize]) -> u16 {
+  active
+    .iter()
+    .fold(0, |acc, sbox| acc | (0xf000 >> (4 * sbox)))
+}
+
+// Peels the final key mix and S-box layer off the active S-boxes, giving
+// their part of the last round's S-box input.
+fn partial_decrypt(
+  inv_sbox: &[u8; 16],
+  ciphertext: u16,
+  subkey: u16,
+  active: &[usize],
+) -> u16 {
+  spn::substitute(inv_sbox, ciphertext ^ subkey) & sbox_mask(active)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_lists_active_sboxes() {
+    assert_eq!(active_sboxes(0x0606), vec![1, 3]);
+    assert_eq!(active_sboxes(0x0000), Vec::<usize>::new());
+    assert_eq!(sbox_mask(&[1, 3]), 0x0f0f);
+  }
+
+  #[test]
+  fn it_enumerates_subkey_candidates() {
+    let candidates = candidate_subkeys(&[1, 3]);
+
+    assert_eq!(candidates.len(), 256);
+    assert!(candidates.iter().all(|key| key & !0x0f0f == 0));
+    assert!(candidates.contains(&0x0a05));
+  }
+}
@@ -0,0 +1,217 @@
+use crustopals::tools::aes;
+use crustopals::tools::block_cipher;
+use crustopals::tools::block_cipher::BlockCipher;
+use std::collections::HashMap;
+
+// Sweet32 (Bhargavan & Leurent): with an n bit block, CBC ciphertext blocks
+// start colliding after about 2^(n/2) blocks. A collision C_i == C_j means
+// P_i ^ C_(i-1) == P_j ^ C_(j-1), so when one of the two plaintext blocks is
+// known the other falls out of a couple of xors. Against 3DES/Blowfish that
+// is 2^32 blocks (32GB) of traffic; the tests use a 32 bit block so the same
+// code finishes after ~2^16 blocks.
+
+pub struct Sweet32Victim<C: BlockCipher> {
+  cipher: C,
+  iv: Vec<u8>,
+  cookie: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Sweet32Report {
+  pub cookie: Vec<u8>,
+  // ciphertext blocks read before the last cookie block was recovered
+  pub blocks_needed: usize,
+  // every collision seen up to that point, useful or not
+  pub collisions: usize,
+  pub birthday_bound: f64,
+}
+
+impl<C: BlockCipher> Sweet32Victim<C> {
+  pub fn new(cipher: C, cookie: &[u8]) -> Sweet32Victim<C> {
+    let iv = aes::generate_rand_bytes(cipher.block_size());
+    Sweet32Victim {
+      cipher,
+      iv,
+      cookie: cookie.to_vec(),
+    }
+  }
+
+  // Sends `num_requests` identical requests over one long lived connection
+  // (a single CBC stream) and returns what an eavesdropper captures: the IV
+  // followed by the ciphertext.
+  pub fn captured_traffic(&self, num_requests: usize) -> Vec<u8> {
+    let (request, _) = build_request(&self.cookie, self.cipher.block_size());
+    let stream: Vec<u8> = request
+      .iter()
+      .cycle()
+      .take(request.len() * num_requests)
+      .cloned()
+      .collect();
+    let ct = block_cipher::encrypt_cbc(&self.cipher, &stream, &self.iv)
+      .expect("the IV is generated for this cipher's block size");
+    [&self.iv[..], &ct[..]].concat()
+  }
+}
+
+// Builds an HTTP request around the cookie, padding the path and a dummy
+// header so the cookie starts on a block boundary and the request is a whole
+// number of blocks. Returns the request and the cookie's offset in it.
+pub fn build_request(cookie: &[u8], block_size: usize) -> (Vec<u8>, usize) {
+  let mut path = "/".to_string();
+  let mut prefix = request_prefix(&path);
+  while !prefix.len().is_multiple_of(block_size) {
+    path.push('a');
+    prefix = request_prefix(&path);
+  }
+  let mut x_pad = String::new();
+  let mut suffix = request_suffix(&x_pad);
+  let fixed_len = prefix.len() + cookie.len();
+  while !(fixed_len + suffix.len()).is_multiple_of(block_size) {
+    x_pad.push('a');
+    suffix = request_suffix(&x_pad);
+  }
+  let mut request = prefix.as_bytes().to_vec();
+  request.extend(cookie);
+  request.extend(suffix.as_bytes());
+  (request, prefix.len())
+}
+
+fn request_prefix(path: &str) -> String {
+  format!(
+    "GET {} HTTP/1.1\r\nHost: example.com\r\nCookie: session=",
+    path
+  )
+}
+
+fn request_suffix(x_pad: &str) -> String {
+  format!("\r\nX-Pad: {}\r\n\r\n", x_pad)
+}
+
+pub fn birthday_bound(block_size: usize) -> f64 {
+  2f64.powi((block_size * 8 / 2) as i32)
+}
+
+// Scans captured traffic (IV first) for colliding ciphertext blocks. The
+// attacker knows the request layout and the cookie's length but not its
+// value. Returns None if the traffic runs out before every cookie block has
+// been paired with a known plaintext block.
+pub fn recover_cookie(
+  traffic: &[u8],
+  block_size: usize,
+  cookie_len: usize,
+) -> Option<Sweet32Report> {
+  let (known_request, cookie_offset) =
+    build_request(&vec![0u8; cookie_len], block_size);
+  let blocks_per_request = known_request.len() / block_size;
+  let cookie_blocks = cookie_len.div_ceil(block_size);
+  let first_cookie_block = cookie_offset / block_size;
+  // the trailing block is CBC padding
+  let num_blocks = (traffic.len() / block_size).saturating_sub(2);
+  let num_request_blocks = num_blocks - num_blocks % blocks_per_request;
+  // blocks of the traffic, where block 0 is the IV
+  let block =
+    |idx: usize| &traffic[(idx * block_size)..((idx + 1) * block_size)];
+  let cookie_block_num = |ct_idx: usize| {
+    let position = ct_idx % blocks_per_request;
+    if position >= first_cookie_block
+      && position < first_cookie_block + cookie_blocks
+    {
+      Some(position - first_cookie_block)
+    } else {
+      None
+    }
+  };
+  let known_block = |ct_idx: usize| {
+    let start = (ct_idx % blocks_per_request) * block_size;
+    &known_request[start..(start + block_size)]
+  };
+
+  let mut seen: HashMap<&[u8], usize> = HashMap::new();
+  let mut recovered: Vec<Option<Vec<u8>>> = vec![None; cookie_blocks];
+  let mut collisions = 0;
+  for j in 0..num_request_blocks {
+    let ct_block = block(j + 1);
+    let i = match seen.get(ct_block) {
+      Some(i) => *i,
+      None => {
+        seen.insert(ct_block, j);
+        continue;
+      }
+    };
+    collisions += 1;
+    let (cookie_idx, known_idx) =
+      match (cookie_block_num(i), cookie_block_num(j)) {
+        (Some(_), Some(_)) | (None, None) => continue,
+        (Some(_), None) => (i, j),
+        (None, Some(_)) => (j, i),
+      };
+    // P_cookie = P_known ^ C_(known - 1) ^ C_(cookie - 1)
+    let cookie_bytes: Vec<u8> = known_block(known_idx)
+      .iter()
+      .zip(block(known_idx).iter())
+      .zip(block(cookie_idx).iter())
+      .map(|((p, c1), c2)| p ^ c1 ^ c2)
+      .collect();
+    recovered[cookie_block_num(cookie_idx).unwrap()] = Some(cookie_bytes);
+
+    if recovered.iter().all(|blk| blk.is_some()) {
+      let mut cookie: Vec<u8> =
+        recovered.into_iter().flat_map(|blk| blk.unwrap()).collect();
+      // a partial last block also holds (known) request bytes
+      cookie.truncate(cookie_len);
+      return Some(Sweet32Report {
+        cookie,
+        blocks_needed: j + 1,
+        collisions,
+        birthday_bound: birthday_bound(block_size),
+      });
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::speck::Speck32;
+
+  #[test]
+  fn it_aligns_the_cookie_and_request_to_blocks() {
+    let cookie = "8bytes!!".as_bytes();
+    for block_size in [4, 8, 16].iter() {
+      let (request, offset) = build_request(cookie, *block_size);
+
+      assert_eq!(offset % block_size, 0);
+      assert_eq!(request.len() % block_size, 0);
+      assert_eq!(&request[offset..(offset + cookie.len())], cookie);
+    }
+  }
+
+  #[test]
+  fn it_recovers_the_cookie_from_block_collisions() {
+    let cookie = aes::generate_rand_bytes(8);
+    let speck = Speck32::new(&aes::generate_rand_bytes(8)).unwrap();
+    let victim = Sweet32Victim::new(speck, &cookie);
+    // ~2^20 blocks: about 100 collisions for a 32 bit block
+    let traffic = victim.captured_traffic(1 << 16);
+
+    let report = recover_cookie(&traffic, 4, cookie.len()).unwrap();
+
+    assert_eq!(report.cookie, cookie);
+    assert_eq!(report.birthday_bound, 65536.0);
+    assert!(report.collisions > 0);
+    // recovering every cookie block well before 2^15 blocks is vanishingly
+    // unlikely
+    assert!(report.blocks_needed as f64 > report.birthday_bound / 2.0);
+  }
+
+  #[test]
+  fn it_needs_enough_traffic() {
+    let cookie = aes::generate_rand_bytes(8);
+    let speck = Speck32::new(&aes::generate_rand_bytes(8)).unwrap();
+    let victim = Sweet32Victim::new(speck, &cookie);
+    let traffic = victim.captured_traffic(10);
+
+    assert!(recover_cookie(&traffic, 4, cookie.len()).is_none());
+  }
+}
@@ -9,8 +9,9 @@ use self::byte_operations::*;
 use self::key_schedule::KeySchedule;
 use self::state_array::StateArray;
 use self::word::Word;
-use crustopals::byteorder::{LittleEndian, WriteBytesExt};
-use crustopals::tools;
+use crustopals::tools::block_cipher;
+use crustopals::tools::block_cipher::BlockCipher;
+use crustopals::tools::CryptoError;
 
 pub fn generate_key() -> Vec<u8> {
   generate_rand_bytes(16)
@@ -24,104 +25,86 @@ pub fn generate_rand_bytes(length: usize) -> Vec<u8> {
   (0..length).map(|_| rand::random::<u8>()).collect()
 }
 
-pub fn encrypt_ctr(bytes: &[u8], key: &[u8], nonce: &[u8]) -> Vec<u8> {
-  ctr_prf(&bytes, &key, &nonce)
+// AES-128 keyed with its expanded round keys, for use with the generic modes
+// in `tools::block_cipher`.
+pub struct Aes128 {
+  round_keys: KeySchedule,
 }
 
-pub fn decrypt_ctr(bytes: &[u8], key: &[u8], nonce: &[u8]) -> Vec<u8> {
-  ctr_prf(&bytes, &key, &nonce)
+impl Aes128 {
+  pub fn new(key: &[u8]) -> Result<Aes128, CryptoError> {
+    Ok(Aes128 {
+      round_keys: key_schedule(key)?,
+    })
+  }
 }
 
-fn ctr_prf(bytes: &[u8], key: &[u8], nonce: &[u8]) -> Vec<u8> {
-  tools::xor_bytes(&generate_ctr_stream(&key, &nonce, bytes.len()), bytes)
-}
+impl BlockCipher for Aes128 {
+  fn block_size(&self) -> usize {
+    16
+  }
 
-pub fn generate_ctr_stream(key: &[u8], nonce: &[u8], len: usize) -> Vec<u8> {
-  let round_keys = key_schedule(key);
-  let mut stream: Vec<u8> = vec![];
-  let mut num_blocks = len / 16;
-  if len % 16 != 0 {
-    num_blocks += 1;
+  fn encrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    encrypt_block(StateArray::new(block), &self.round_keys).to_u8()
   }
-  for i in 0..num_blocks {
-    let mut counter: Vec<u8> = vec![];
-    counter.write_u64::<LittleEndian>(i as u64).unwrap();
-    let block = [&nonce[..], &counter[..]].concat();
-    let state_array = StateArray::new(&block);
-    let encrypted_block = encrypt_block(state_array, &round_keys);
-    let result = encrypted_block.to_u8();
-    stream.extend(result);
+
+  fn decrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    decrypt_block(StateArray::new(block), &self.round_keys).to_u8()
   }
-  stream.truncate(len);
-  stream
 }
 
-pub fn encrypt_message_cbc(bytes: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
-  let round_keys = key_schedule(key);
-  let padded_bytes = pad_bytes(bytes);
-  let mut encrypted_message: Vec<u8> = vec![];
-  for block in padded_bytes.chunks(16) {
-    let prev_block = if encrypted_message.len() == 0 {
-      StateArray::new(&iv)
-    } else {
-      let prev_block_start: usize = (encrypted_message.len() - 16) as usize;
-      StateArray::new(&encrypted_message[prev_block_start..])
-    };
-
-    let mut state_array = StateArray::new(block);
-    state_array.xor(&prev_block);
-    let encrypted_block = encrypt_block(state_array, &round_keys);
-    let result = encrypted_block.to_u8();
-    encrypted_message.extend(result);
-  }
-  encrypted_message
+pub fn encrypt_ctr(
+  bytes: &[u8],
+  key: &[u8],
+  nonce: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  block_cipher::encrypt_ctr(&Aes128::new(key)?, bytes, nonce)
 }
 
-pub fn encrypt_message_ecb(bytes: &[u8], key: &[u8]) -> Vec<u8> {
-  let round_keys = key_schedule(key);
-  let padded_bytes = pad_bytes(bytes);
-  let mut encrypted_message: Vec<u8> = vec![];
-  for block in padded_bytes.chunks(16) {
-    let state_array = StateArray::new(block);
-    let encrypted_block = encrypt_block(state_array, &round_keys);
-    let result = encrypted_block.to_u8();
-    encrypted_message.extend(result);
-  }
-  encrypted_message
+pub fn decrypt_ctr(
+  bytes: &[u8],
+  key: &[u8],
+  nonce: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  block_cipher::decrypt_ctr(&Aes128::new(key)?, bytes, nonce)
 }
 
-pub fn decrypt_message_cbc(
+pub fn generate_ctr_stream(
+  key: &[u8],
+  nonce: &[u8],
+  len: usize,
+) -> Result<Vec<u8>, CryptoError> {
+  block_cipher::generate_ctr_stream(&Aes128::new(key)?, nonce, len)
+}
+
+pub fn encrypt_message_cbc(
   bytes: &[u8],
   key: &[u8],
   iv: &[u8],
-) -> Result<Vec<u8>, String> {
-  let decrypt_pre_xor = decrypt_message(bytes, key);
-  let mut iv_with_ciphertext: Vec<u8> = vec![];
-  iv_with_ciphertext.extend(iv.to_vec());
-  iv_with_ciphertext.extend(bytes);
-  iv_with_ciphertext.truncate(decrypt_pre_xor.len());
-  let pt_with_padding = tools::xor_bytes(&decrypt_pre_xor, &iv_with_ciphertext);
-  tools::strip_pkcs7_padding(pt_with_padding)
+) -> Result<Vec<u8>, CryptoError> {
+  block_cipher::encrypt_cbc(&Aes128::new(key)?, bytes, iv)
 }
 
-fn decrypt_message(bytes: &[u8], key: &[u8]) -> Vec<u8> {
-  let round_keys = key_schedule(key);
-  let mut decrypted_message: Vec<u8> = vec![];
-  for block in bytes.chunks(16) {
-    let state_array = StateArray::new(block);
-    let decrypted_block = decrypt_block(state_array, &round_keys);
-    let result = decrypted_block.to_u8();
-    decrypted_message.extend(result);
-  }
-  decrypted_message
+pub fn encrypt_message_ecb(
+  bytes: &[u8],
+  key: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  Ok(block_cipher::encrypt_ecb(&Aes128::new(key)?, bytes))
+}
+
+pub fn decrypt_message_cbc(
+  bytes: &[u8],
+  key: &[u8],
+  iv: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  block_cipher::decrypt_cbc(&Aes128::new(key)?, bytes, iv)
 }
 
 pub fn decrypt_message_ecb(
   bytes: &[u8],
   key: &[u8],
-) -> Result<Vec<u8>, String> {
-  let decrypted_message = decrypt_message(bytes, key);
-  tools::strip_pkcs7_padding(decrypted_message)
+) -> Result<Vec<u8>, CryptoError> {
+  block_cipher::decrypt_ecb(&Aes128::new(key)?, bytes)
 }
 
 fn encrypt_block(mut state: StateArray, keys: &KeySchedule) -> StateArray {
@@ -150,10 +133,13 @@ fn decrypt_block(mut state: StateArray, keys: &KeySchedule) -> StateArray {
   state
 }
 
-pub fn key_schedule(key: &[u8]) -> KeySchedule {
+pub fn key_schedule(key: &[u8]) -> Result<KeySchedule, CryptoError> {
   // takes 4 words (32 bits each) and transform them into 44 words
   if key.len() != 16 {
-    panic!("Wrong size key. Must be 16 bytes.");
+    return Err(CryptoError::InvalidKeyLength {
+      expected: 16,
+      actual: key.len(),
+    });
   }
   let mut expanded_key: Vec<Word> = vec![];
 
@@ -177,11 +163,7 @@ pub fn key_schedule(key: &[u8]) -> KeySchedule {
     expanded_key.push(word);
   }
 
-  KeySchedule::new(expanded_key)
-}
-
-fn pad_bytes(bytes: &[u8]) -> Vec<u8> {
-  tools::pad_bytes(bytes, 16)
+  Ok(KeySchedule::new(expanded_key))
 }
 
 fn rcon(word_idx: usize) -> Word {
@@ -198,13 +180,48 @@ mod tests {
   extern crate hex;
 
   use super::*;
-  use std::panic;
+  use crustopals::tools;
 
   #[test]
-  fn panics_with_wrong_keysize() {
+  fn errors_with_wrong_keysize() {
     let key = b"Hello world";
-    let result = panic::catch_unwind(|| key_schedule(key));
-    assert!(result.is_err());
+    let result = key_schedule(key);
+
+    match result {
+      Err(CryptoError::InvalidKeyLength {
+        expected: 16,
+        actual: 11,
+      }) => (),
+      _ => panic!("expected an invalid key length error"),
+    }
+  }
+
+  #[test]
+  fn errors_with_wrong_iv_size() {
+    let key = "YELLOW SUBMARINE".as_bytes();
+    let result = encrypt_message_cbc(b"message", key, &[0u8; 8]);
+
+    assert_eq!(
+      result,
+      Err(CryptoError::InvalidIvLength {
+        expected: 16,
+        actual: 8,
+      })
+    );
+  }
+
+  #[test]
+  fn errors_on_truncated_ciphertext() {
+    let key = "YELLOW SUBMARINE".as_bytes();
+    let result = decrypt_message_ecb(&[0u8; 20], key);
+
+    assert_eq!(
+      result,
+      Err(CryptoError::InvalidBlockLength {
+        block_size: 16,
+        actual: 20,
+      })
+    );
   }
 
   #[test]
@@ -214,8 +231,8 @@ mod tests {
     let ctr1 = vec![0u8; 8];
     let ctr2 = vec![1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8];
     let msg = [&nonce[..], &ctr1[..], &nonce[..], &ctr2[..]].concat();
-    let ecb_mode_enc = encrypt_message_ecb(&msg, &key);
-    let ctr_stream = generate_ctr_stream(&key, &nonce, msg.len());
+    let ecb_mode_enc = encrypt_message_ecb(&msg, key).unwrap();
+    let ctr_stream = generate_ctr_stream(key, &nonce, msg.len()).unwrap();
 
     assert_eq!(ctr_stream[..], ecb_mode_enc[..32]);
   }
@@ -225,7 +242,7 @@ mod tests {
     let key = "YELLOW SUBMARINE".as_bytes();
     let nonce = [0u8; 8];
     let ctr_encrypted_msg = base64::decode("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==").unwrap();
-    let ctr_decrypted = decrypt_ctr(&ctr_encrypted_msg, &key, &nonce);
+    let ctr_decrypted = decrypt_ctr(&ctr_encrypted_msg, key, &nonce).unwrap();
     assert_eq!(
       tools::bytes_to_string(ctr_decrypted),
       "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
@@ -246,7 +263,7 @@ mod tests {
       "e13f0cc8", "b6630ca6",
     ];
 
-    let computed_round_keys = key_schedule(&key);
+    let computed_round_keys = key_schedule(&key).unwrap();
 
     for (i, word) in expanded_round_key_words.iter().enumerate() {
       assert_eq!(
@@ -263,7 +280,7 @@ mod tests {
     let iv = [0 as u8; 16];
     let expected_ciphertext =
       base64::decode("nRGOqUe3iBURUPUe5NjYJTSrsoTAS1bzHKzpdPDcoFg=").unwrap();
-    let encrypted = encrypt_message_cbc(message, key, &iv);
+    let encrypted = encrypt_message_cbc(message, key, &iv).unwrap();
 
     assert_eq!(encrypted, expected_ciphertext);
   }
@@ -288,7 +305,7 @@ mod tests {
     let message = "here is the mess".as_bytes();
     let expected_ciphertext =
       base64::decode("nRGOqUe3iBURUPUe5NjYJWD6NnB+RfSZ26DyW5IjAaU=").unwrap();
-    let aes_128_bit_encrypted = encrypt_message_ecb(message, key);
+    let aes_128_bit_encrypted = encrypt_message_ecb(message, key).unwrap();
 
     assert_eq!(aes_128_bit_encrypted, expected_ciphertext);
   }
@@ -311,7 +328,7 @@ mod tests {
     ).unwrap();
     let plaintext = hex::decode("00112233445566778899aabbccddeeff").unwrap();
     let aes_128_bit_decrypted = decrypt_message_ecb(&ciphertext, &key).unwrap();
-    let aes_128_bit_encrypted = encrypt_message_ecb(&plaintext, &key);
+    let aes_128_bit_encrypted = encrypt_message_ecb(&plaintext, &key).unwrap();
 
     assert_eq!(ciphertext, aes_128_bit_encrypted);
     assert_eq!(aes_128_bit_decrypted, plaintext);
@@ -1,4 +1,5 @@
 use super::*;
+use crustopals::tools::Gf256;
 use std::fmt;
 
 pub struct StateArray {
@@ -72,11 +73,14 @@ impl StateArray {
     for row in 0..4 {
       let mut bytes: Vec<u8> = vec![];
       for coefficients in coef_matrix.iter() {
-        let mut result: u8 = 0;
-        for (b, coef) in self.block[row].bytes.iter().zip(coefficients.iter()) {
-          result = result ^ tools::mult_bytes(b.clone(), coef.clone());
-        }
-        bytes.push(result);
+        let result = self.block[row]
+          .bytes
+          .iter()
+          .zip(coefficients.iter())
+          .fold(Gf256::zero(), |acc, (b, coef)| {
+            acc + Gf256::new(*b) * Gf256::new(*coef)
+          });
+        bytes.push(result.value());
       }
       self.block[row] = Word::new(&bytes);
     }
@@ -92,11 +96,14 @@ impl StateArray {
     for row in 0..4 {
       let mut bytes: Vec<u8> = vec![];
       for coefficients in coef_matrix.iter() {
-        let mut result: u8 = 0;
-        for (b, coef) in self.block[row].bytes.iter().zip(coefficients.iter()) {
-          result = result ^ tools::mult_bytes(b.clone(), coef.clone());
-        }
-        bytes.push(result);
+        let result = self.block[row]
+          .bytes
+          .iter()
+          .zip(coefficients.iter())
+          .fold(Gf256::zero(), |acc, (b, coef)| {
+            acc + Gf256::new(*b) * Gf256::new(*coef)
+          });
+        bytes.push(result.value());
       }
       self.block[row] = Word::new(&bytes);
     }
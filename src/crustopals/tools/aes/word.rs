@@ -15,7 +15,11 @@ impl Word {
   }
 
   pub fn xor(&self, other: &Word) -> Word {
-    Word::new(&tools::xor_bytes(&self.bytes, &other.bytes)[..])
+    let mut bytes = self.bytes;
+    for (b, other_b) in bytes.iter_mut().zip(other.bytes.iter()) {
+      *b ^= other_b;
+    }
+    Word { bytes }
   }
 
   pub fn rotated(&self) -> Word {
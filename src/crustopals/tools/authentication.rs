@@ -1,33 +1,115 @@
-extern crate sha1;
-extern crate md4;
+use crustopals::tools::constant_time::ct_eq;
+use crustopals::tools::hash_function::HashFunction;
+use crustopals::tools::md4::Md4;
+use crustopals::tools::md5::Md5;
+use crustopals::tools::sha1::Sha1;
+use crustopals::tools::sha2::Sha256;
 
-use self::md4::{Md4, Digest};
+// H(key || message), the MAC a length extension forges.
+pub fn secret_prefix_mac<H: HashFunction>(
+  key: &[u8],
+  message: &[u8],
+) -> Vec<u8> {
+  let mut hasher = H::new();
+  hasher.update(key);
+  hasher.update(message);
+  hasher.digest()
+}
+
+// Compares `tag` against the message's secret-prefix MAC in constant time.
+pub fn verify_secret_prefix_mac<H: HashFunction>(
+  key: &[u8],
+  message: &[u8],
+  tag: &[u8],
+) -> bool {
+  ct_eq(&secret_prefix_mac::<H>(key, message), tag)
+}
 
 pub fn sha1_mac(key: &[u8], message_bytes: &[u8]) -> Vec<u8> {
-  let mut sha1 = sha1::Sha1::new();
-  sha1.update(&key[..]);
-  sha1.update(&message_bytes[..]);
-  hex::decode(sha1.hexdigest()).unwrap()
+  secret_prefix_mac::<Sha1>(key, message_bytes)
+}
+
+pub fn sha256_mac(key: &[u8], message_bytes: &[u8]) -> Vec<u8> {
+  secret_prefix_mac::<Sha256>(key, message_bytes)
 }
 
 pub fn md4_mac(key: &[u8], message_bytes: &[u8]) -> Vec<u8> {
-  let mut md4 = self::Md4::new();
-  md4.input(&key[..]);
-  md4.input(&message_bytes[..]);
-  md4.result().to_vec()
+  secret_prefix_mac::<Md4>(key, message_bytes)
+}
+
+pub fn md5_mac(key: &[u8], message_bytes: &[u8]) -> Vec<u8> {
+  secret_prefix_mac::<Md5>(key, message_bytes)
 }
 
 pub fn valid_sha1_mac(key: &[u8], message: &[u8], mac: Vec<u8>) -> bool {
-  sha1_mac(key, message) == mac
+  verify_secret_prefix_mac::<Sha1>(key, message, &mac)
+}
+
+pub fn valid_sha256_mac(key: &[u8], message: &[u8], mac: Vec<u8>) -> bool {
+  verify_secret_prefix_mac::<Sha256>(key, message, &mac)
 }
 
 pub fn valid_md4_mac(key: &[u8], message: &[u8], mac: Vec<u8>) -> bool {
-  md4_mac(key, message) == mac
+  verify_secret_prefix_mac::<Md4>(key, message, &mac)
+}
+
+pub fn valid_md5_mac(key: &[u8], message: &[u8], mac: Vec<u8>) -> bool {
+  verify_secret_prefix_mac::<Md5>(key, message, &mac)
+}
+
+// HMAC (RFC 2104): H((K ^ opad) || H((K ^ ipad) || message)), with K the
+// key zero padded to a block, or hashed first if it's longer. The outer
+// hash hides the inner one's state, so unlike the secret-prefix MACs above
+// a tag can't be length extended.
+#[derive(Clone)]
+pub struct Hmac<H: HashFunction> {
+  inner: H,
+  outer: H,
+}
+
+impl<H: HashFunction> Hmac<H> {
+  pub fn new(key: &[u8]) -> Hmac<H> {
+    let mut block_sized_key = if key.len() > H::BLOCK_SIZE {
+      H::hash(key)
+    } else {
+      key.to_vec()
+    };
+    block_sized_key.resize(H::BLOCK_SIZE, 0);
+    let i_key_pad: Vec<u8> = block_sized_key.iter().map(|b| b ^ 0x36).collect();
+    let o_key_pad: Vec<u8> = block_sized_key.iter().map(|b| b ^ 0x5c).collect();
+    let mut inner = H::new();
+    inner.update(&i_key_pad);
+    let mut outer = H::new();
+    outer.update(&o_key_pad);
+    Hmac { inner, outer }
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    self.inner.update(bytes);
+  }
+
+  pub fn digest(&self) -> Vec<u8> {
+    let mut outer = self.outer.clone();
+    outer.update(&self.inner.digest());
+    outer.digest()
+  }
+
+  // Compares against `tag` in constant time.
+  pub fn verify(&self, tag: &[u8]) -> bool {
+    ct_eq(&self.digest(), tag)
+  }
+}
+
+pub fn hmac<H: HashFunction>(key: &[u8], message: &[u8]) -> Vec<u8> {
+  let mut hmac = Hmac::<H>::new(key);
+  hmac.update(message);
+  hmac.digest()
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crustopals::tools::sha2::{sha512, Sha512};
 
   ///////////////////////////////////////////////////////////////////////
   // SHA1
@@ -61,6 +143,24 @@ mod tests {
     assert!(!valid_sha1_mac(&key, "this is a forged message".as_bytes(), hash));
   }
 
+  ///////////////////////////////////////////////////////////////////////
+  // SHA256
+  ///////////////////////////////////////////////////////////////////////
+  #[test]
+  fn it_hashes_the_key_and_msg_sha256() {
+    let key = "secretcode".as_bytes();
+    let message = "this is the message".as_bytes();
+    let hash = sha256_mac(key, message);
+    let expected_result = hex::decode(
+      "55eaf53682fd5e44c22dd2baeda63a54cb8e9cce331468c231756a92d30004b4",
+    )
+    .unwrap();
+
+    assert_eq!(hash, expected_result);
+    assert!(valid_sha256_mac(key, message, hash.clone()));
+    assert!(!valid_sha256_mac(key, "this is a forged message".as_bytes(), hash));
+  }
+
   ///////////////////////////////////////////////////////////////////////
   // MD4
   ///////////////////////////////////////////////////////////////////////
@@ -92,4 +192,165 @@ mod tests {
 
     assert!(!valid_md4_mac(&key, "this is a forged message".as_bytes(), hash));
   }
+
+  ///////////////////////////////////////////////////////////////////////
+  // MD5
+  ///////////////////////////////////////////////////////////////////////
+  #[test]
+  fn it_hashes_the_key_and_msg_md5() {
+    let key = "secretcode".as_bytes();
+    let message = "this is the message".as_bytes();
+    let hash = md5_mac(key, message);
+    let expected_result =
+      hex::decode("f3e24521a45800bf3e1dee08175a7e81").unwrap();
+
+    assert_eq!(hash, expected_result);
+  }
+
+  #[test]
+  fn it_wont_verify_invalid_md5_macs() {
+    let key = "secretcode".as_bytes();
+    let message = "this is the message".as_bytes();
+    let hash = md5_mac(key, message);
+
+    assert!(valid_md5_mac(key, message, hash.clone()));
+    assert!(!valid_md5_mac(key, "this is a forged message".as_bytes(), hash));
+  }
+
+  #[test]
+  fn it_verifies_secret_prefix_macs_over_any_hash() {
+    let key = "secretcode".as_bytes();
+    let message = "this is the message".as_bytes();
+    let tag = secret_prefix_mac::<Sha512>(key, message);
+
+    assert_eq!(tag, sha512(&[key, message].concat()));
+    assert!(verify_secret_prefix_mac::<Sha512>(key, message, &tag));
+    assert!(!verify_secret_prefix_mac::<Sha512>(
+      key,
+      message,
+      &tag[..32]
+    ));
+    assert!(!verify_secret_prefix_mac::<Sha512>(
+      b"otherkey",
+      message,
+      &tag
+    ));
+  }
+
+  ///////////////////////////////////////////////////////////////////////
+  // HMAC
+  ///////////////////////////////////////////////////////////////////////
+  #[test]
+  fn it_computes_the_rfc_2202_hmacs() {
+    let long_key = [0xaa; 80];
+    let long_msg =
+      "Test Using Larger Than Block-Size Key - Hash Key First".as_bytes();
+    let sha1_examples: [(&[u8], &[u8], &str); 5] = [
+      (&[0x0b; 20], b"Hi There", "b617318655057264e28bc0b6fb378c8ef146be00"),
+      (
+        b"Jefe",
+        b"what do ya want for nothing?",
+        "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+      ),
+      (&[0xaa; 20], &[0xdd; 50], "125d7342b9ac11cd91a39af48aa17b4f63f175d3"),
+      (&long_key, long_msg, "aa4ae5e15272d00e95705637ce8a3b55ed402112"),
+      (
+        &long_key,
+        b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data",
+        "e8e99d0f45237d786d6bbaa7965c7808bbff1a91",
+      ),
+    ];
+    let md5_examples: [(&[u8], &[u8], &str); 4] = [
+      (&[0x0b; 16], b"Hi There", "9294727a3638bb1c13f48ef8158bfc9d"),
+      (
+        b"Jefe",
+        b"what do ya want for nothing?",
+        "750c783e6ab0b503eaa86e310a5db738",
+      ),
+      (&[0xaa; 16], &[0xdd; 50], "56be34521d144c88dbb8c733f0e8b3f6"),
+      (&long_key, long_msg, "6b1ab7fe4bd7bf8f0b62e6ce61b9d0cd"),
+    ];
+
+    for (key, message, tag) in sha1_examples.iter() {
+      assert_eq!(hex::encode(hmac::<Sha1>(key, message)), *tag);
+    }
+    for (key, message, tag) in md5_examples.iter() {
+      assert_eq!(hex::encode(hmac::<Md5>(key, message)), *tag);
+    }
+  }
+
+  #[test]
+  fn it_computes_the_rfc_4231_hmacs() {
+    let long_key = [0xaa; 131];
+    let examples: [(&[u8], &[u8], &str, &str); 4] = [
+      (
+        &[0x0b; 20],
+        b"Hi There",
+        "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+         daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+      ),
+      (
+        b"Jefe",
+        b"what do ya want for nothing?",
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+         9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+      ),
+      (
+        &long_key,
+        b"Test Using Larger Than Block-Size Key - Hash Key First",
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+         6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+      ),
+      (
+        &long_key,
+        b"This is a test using a larger than block-size key and a larger \
+          than block-size data. The key needs to be hashed before being used \
+          by the HMAC algorithm.",
+        "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+        "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944\
+         b6022cac3c4982b10d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58",
+      ),
+    ];
+
+    for (key, message, sha256_tag, sha512_tag) in examples.iter() {
+      assert_eq!(hex::encode(hmac::<Sha256>(key, message)), *sha256_tag);
+      assert_eq!(hex::encode(hmac::<Sha512>(key, message)), *sha512_tag);
+    }
+  }
+
+  #[test]
+  fn it_generates_an_hmac() {
+    let empty_ex = hmac::<Sha1>(b"", b"");
+    let quick_brown_fox_ex =
+      hmac::<Sha1>(b"key", b"The quick brown fox jumps over the lazy dog");
+
+    assert_eq!(
+      empty_ex,
+      hex::decode("fbdb1d1b18aa6c08324b7d64b71fb76370690e1d").unwrap()
+    );
+    assert_eq!(
+      quick_brown_fox_ex,
+      hex::decode("de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9").unwrap()
+    );
+  }
+
+  #[test]
+  fn it_hmacs_incrementally_and_verifies() {
+    let message = [0x42u8; 300];
+    let mut mac = Hmac::<Sha256>::new(b"a key");
+    for chunk in message.chunks(7) {
+      mac.update(chunk);
+    }
+    let tag = hmac::<Sha256>(b"a key", &message);
+
+    assert_eq!(mac.digest(), tag);
+    assert!(mac.verify(&tag));
+    assert!(!mac.verify(&tag[..31]));
+    let mut forged = tag.clone();
+    forged[31] ^= 1;
+    assert!(!mac.verify(&forged));
+  }
 }
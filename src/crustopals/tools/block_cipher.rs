@@ -0,0 +1,141 @@
+use crustopals::tools;
+use crustopals::tools::CryptoError;
+
+// A keyed block cipher. Implementors only ever see blocks of exactly
+// `block_size()` bytes; the mode functions below take care of padding,
+// chaining and the length checks on untrusted input.
+pub trait BlockCipher {
+  fn block_size(&self) -> usize;
+  fn encrypt_block(&self, block: &[u8]) -> Vec<u8>;
+  fn decrypt_block(&self, block: &[u8]) -> Vec<u8>;
+}
+
+pub fn encrypt_ecb<C: BlockCipher>(cipher: &C, bytes: &[u8]) -> Vec<u8> {
+  let padded_bytes = tools::pad_bytes(bytes, cipher.block_size());
+  let mut encrypted_message: Vec<u8> = vec![];
+  for block in padded_bytes.chunks(cipher.block_size()) {
+    encrypted_message.extend(cipher.encrypt_block(block));
+  }
+  encrypted_message
+}
+
+pub fn decrypt_ecb<C: BlockCipher>(
+  cipher: &C,
+  bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let decrypted_message = decrypt_blocks(cipher, bytes)?;
+  tools::unpad_bytes(decrypted_message, cipher.block_size())
+}
+
+pub fn encrypt_cbc<C: BlockCipher>(
+  cipher: &C,
+  bytes: &[u8],
+  iv: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let block_size = cipher.block_size();
+  check_iv_length(iv, block_size)?;
+  let padded_bytes = tools::pad_bytes(bytes, block_size);
+  let mut encrypted_message: Vec<u8> = vec![];
+  let mut prev_block = iv.to_vec();
+  for block in padded_bytes.chunks(block_size) {
+    let xord_block = tools::xor_bytes(block, &prev_block)?;
+    prev_block = cipher.encrypt_block(&xord_block);
+    encrypted_message.extend(prev_block.to_vec());
+  }
+  Ok(encrypted_message)
+}
+
+pub fn decrypt_cbc<C: BlockCipher>(
+  cipher: &C,
+  bytes: &[u8],
+  iv: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  check_iv_length(iv, cipher.block_size())?;
+  let decrypt_pre_xor = decrypt_blocks(cipher, bytes)?;
+  let mut iv_with_ciphertext: Vec<u8> = vec![];
+  iv_with_ciphertext.extend(iv.to_vec());
+  iv_with_ciphertext.extend(bytes);
+  iv_with_ciphertext.truncate(decrypt_pre_xor.len());
+  let pt_with_padding =
+    tools::xor_bytes(&decrypt_pre_xor, &iv_with_ciphertext)?;
+  tools::unpad_bytes(pt_with_padding, cipher.block_size())
+}
+
+pub fn encrypt_ctr<C: BlockCipher>(
+  cipher: &C,
+  bytes: &[u8],
+  nonce: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let stream = generate_ctr_stream(cipher, nonce, bytes.len())?;
+  tools::xor_bytes(&stream, bytes)
+}
+
+pub fn decrypt_ctr<C: BlockCipher>(
+  cipher: &C,
+  bytes: &[u8],
+  nonce: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  encrypt_ctr(cipher, bytes, nonce)
+}
+
+// Each counter block is the nonce followed by a little endian block counter,
+// each taking up half of the block (8 + 8 bytes for AES, 4 + 4 for DES).
+// Small blocks get small counters, so the stream can't be longer than the
+// counter can count: 512 bytes for the SPN, 256 KiB for Speck32.
+pub fn generate_ctr_stream<C: BlockCipher>(
+  cipher: &C,
+  nonce: &[u8],
+  len: usize,
+) -> Result<Vec<u8>, CryptoError> {
+  let block_size = cipher.block_size();
+  let counter_size = block_size / 2;
+  check_iv_length(nonce, block_size - counter_size)?;
+  let max_len = 1usize
+    .checked_shl(8 * counter_size as u32)
+    .and_then(|blocks| blocks.checked_mul(block_size));
+  if let Some(max) = max_len {
+    if len > max {
+      return Err(CryptoError::OutputTooLong { max, actual: len });
+    }
+  }
+  let mut stream: Vec<u8> = vec![];
+  let mut counter: u64 = 0;
+  while stream.len() < len {
+    let mut block = nonce.to_vec();
+    for i in 0..counter_size {
+      block.push((counter >> (8 * i)) as u8);
+    }
+    stream.extend(cipher.encrypt_block(&block));
+    counter += 1;
+  }
+  stream.truncate(len);
+  Ok(stream)
+}
+
+fn decrypt_blocks<C: BlockCipher>(
+  cipher: &C,
+  bytes: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let block_size = cipher.block_size();
+  if !bytes.len().is_multiple_of(block_size) {
+    return Err(CryptoError::InvalidBlockLength {
+      block_size,
+      actual: bytes.len(),
+    });
+  }
+  let mut decrypted_message: Vec<u8> = vec![];
+  for block in bytes.chunks(block_size) {
+    decrypted_message.extend(cipher.decrypt_block(block));
+  }
+  Ok(decrypted_message)
+}
+
+fn check_iv_length(iv: &[u8], expected: usize) -> Result<(), CryptoError> {
+  if iv.len() != expected {
+    return Err(CryptoError::InvalidIvLength {
+      expected,
+      actual: iv.len(),
+    });
+  }
+  Ok(())
+}
@@ -0,0 +1,175 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::stream_cipher::StreamCipher;
+use crustopals::tools::CryptoError;
+
+// "expand 32-byte k"
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+// ChaCha20 as specified in RFC 8439: a 256 bit key, a 96 bit nonce and a
+// 32 bit block counter, which starts at 1 when the cipher sits inside the
+// AEAD (block 0 becomes the Poly1305 key).
+pub struct ChaCha20 {
+  key: [u32; 8],
+  nonce: [u32; 3],
+  initial_counter: u32,
+}
+
+impl ChaCha20 {
+  pub fn new(key: &[u8], nonce: &[u8]) -> Result<ChaCha20, CryptoError> {
+    ChaCha20::with_counter(key, nonce, 0)
+  }
+
+  pub fn with_counter(
+    key: &[u8],
+    nonce: &[u8],
+    initial_counter: u32,
+  ) -> Result<ChaCha20, CryptoError> {
+    if key.len() != 32 {
+      return Err(CryptoError::InvalidKeyLength {
+        expected: 32,
+        actual: key.len(),
+      });
+    }
+    if nonce.len() != 12 {
+      return Err(CryptoError::InvalidIvLength {
+        expected: 12,
+        actual: nonce.len(),
+      });
+    }
+    let mut key_words = [0u32; 8];
+    LittleEndian::read_u32_into(key, &mut key_words);
+    let mut nonce_words = [0u32; 3];
+    LittleEndian::read_u32_into(nonce, &mut nonce_words);
+    Ok(ChaCha20 {
+      key: key_words,
+      nonce: nonce_words,
+      initial_counter,
+    })
+  }
+
+  pub fn block(&self, counter: u32) -> [u8; 64] {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&CONSTANTS);
+    state[4..12].copy_from_slice(&self.key);
+    state[12] = counter;
+    state[13..].copy_from_slice(&self.nonce);
+
+    let mut working = state;
+    for _ in 0..10 {
+      // columns, then diagonals
+      quarter_round(&mut working, 0, 4, 8, 12);
+      quarter_round(&mut working, 1, 5, 9, 13);
+      quarter_round(&mut working, 2, 6, 10, 14);
+      quarter_round(&mut working, 3, 7, 11, 15);
+      quarter_round(&mut working, 0, 5, 10, 15);
+      quarter_round(&mut working, 1, 6, 11, 12);
+      quarter_round(&mut working, 2, 7, 8, 13);
+      quarter_round(&mut working, 3, 4, 9, 14);
+    }
+    for (word, initial) in working.iter_mut().zip(state.iter()) {
+      *word = word.wrapping_add(*initial);
+    }
+    let mut block = [0u8; 64];
+    LittleEndian::write_u32_into(&working, &mut block);
+    block
+  }
+}
+
+impl StreamCipher for ChaCha20 {
+  fn keystream(&self, len: usize) -> Vec<u8> {
+    let mut stream: Vec<u8> = vec![];
+    let mut counter = self.initial_counter;
+    while stream.len() < len {
+      stream.extend(self.block(counter).iter());
+      counter = counter.wrapping_add(1);
+    }
+    stream.truncate(len);
+    stream
+  }
+}
+
+fn quarter_round(
+  state: &mut [u32; 16],
+  a: usize,
+  b: usize,
+  c: usize,
+  d: usize,
+) {
+  state[a] = state[a].wrapping_add(state[b]);
+  state[d] = (state[d] ^ state[a]).rotate_left(16);
+  state[c] = state[c].wrapping_add(state[d]);
+  state[b] = (state[b] ^ state[c]).rotate_left(12);
+  state[a] = state[a].wrapping_add(state[b]);
+  state[d] = (state[d] ^ state[a]).rotate_left(8);
+  state[c] = state[c].wrapping_add(state[d]);
+  state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  #[test]
+  fn it_runs_the_rfc_8439_quarter_round() {
+    // section 2.1.1
+    let mut state = [0u32; 16];
+    state[..4]
+      .copy_from_slice(&[0x11111111, 0x01020304, 0x9b8d6f43, 0x01234567]);
+    quarter_round(&mut state, 0, 1, 2, 3);
+
+    assert_eq!(
+      &state[..4],
+      &[0xea2a92f4, 0xcb1cf8ce, 0x4581472e, 0x5881c4bb]
+    );
+  }
+
+  #[test]
+  fn it_computes_the_rfc_8439_block() {
+    // section 2.3.2
+    let key: Vec<u8> = (0..32).collect();
+    let nonce = hex::decode("000000090000004a00000000").unwrap();
+    let chacha = ChaCha20::new(&key, &nonce).unwrap();
+
+    assert_eq!(
+      hex::encode(&chacha.block(1)[..]),
+      "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+       d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+    );
+  }
+
+  #[test]
+  fn it_encrypts_the_rfc_8439_sunscreen_message() {
+    // section 2.4.2
+    let key: Vec<u8> = (0..32).collect();
+    let nonce = hex::decode("000000000000004a00000000").unwrap();
+    let chacha = ChaCha20::with_counter(&key, &nonce, 1).unwrap();
+    let plaintext = "Ladies and Gentlemen of the class of '99: If I could \
+                     offer you only one tip for the future, sunscreen would \
+                     be it."
+      .as_bytes();
+    let ciphertext = chacha.apply_keystream(plaintext);
+
+    assert_eq!(
+      hex::encode(&ciphertext),
+      "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+       f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+       07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+       5af90bbf74a35be6b40b8eedf2785e42874d"
+    );
+    assert_eq!(chacha.apply_keystream(&ciphertext), plaintext);
+  }
+
+  #[test]
+  fn it_rejects_bad_key_and_nonce_sizes() {
+    assert!(ChaCha20::new(&[0u8; 16], &[0u8; 12]).is_err());
+    assert_eq!(
+      ChaCha20::new(&[0u8; 32], &[0u8; 8]).err(),
+      Some(CryptoError::InvalidIvLength {
+        expected: 12,
+        actual: 8,
+      })
+    );
+  }
+}
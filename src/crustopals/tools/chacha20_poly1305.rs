@@ -0,0 +1,141 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::chacha20::ChaCha20;
+use crustopals::tools::poly1305;
+use crustopals::tools::stream_cipher::StreamCipher;
+use crustopals::tools::CryptoError;
+
+// The ChaCha20-Poly1305 AEAD from RFC 8439 section 2.8. The first ChaCha20
+// block under the nonce is the one time Poly1305 key, the message is
+// encrypted from block 1 on, and the tag covers the additional data and the
+// ciphertext.
+
+pub const TAG_SIZE: usize = 16;
+
+// Returns the ciphertext with the 16 byte tag appended.
+pub fn encrypt(
+  key: &[u8],
+  nonce: &[u8],
+  aad: &[u8],
+  plaintext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let ciphertext =
+    ChaCha20::with_counter(key, nonce, 1)?.apply_keystream(plaintext);
+  let tag = compute_tag(key, nonce, aad, &ciphertext)?;
+  Ok([&ciphertext[..], &tag[..]].concat())
+}
+
+pub fn decrypt(
+  key: &[u8],
+  nonce: &[u8],
+  aad: &[u8],
+  ciphertext_with_tag: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  if ciphertext_with_tag.len() < TAG_SIZE {
+    return Err(CryptoError::AuthenticationFailed);
+  }
+  let (ciphertext, tag) =
+    ciphertext_with_tag.split_at(ciphertext_with_tag.len() - TAG_SIZE);
+  let one_time_key = poly1305_key(key, nonce)?;
+  if !poly1305::verify(&one_time_key, &mac_data(aad, ciphertext), tag)? {
+    return Err(CryptoError::AuthenticationFailed);
+  }
+  Ok(ChaCha20::with_counter(key, nonce, 1)?.apply_keystream(ciphertext))
+}
+
+pub fn poly1305_key(key: &[u8], nonce: &[u8]) -> Result<Vec<u8>, CryptoError> {
+  Ok(ChaCha20::new(key, nonce)?.block(0)[..32].to_vec())
+}
+
+// aad || pad16 || ciphertext || pad16 || len(aad) || len(ciphertext)
+pub fn mac_data(aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+  let mut data = aad.to_vec();
+  data.extend(vec![0u8; pad16(aad.len())]);
+  data.extend(ciphertext);
+  data.extend(vec![0u8; pad16(ciphertext.len())]);
+  let mut lengths = [0u8; 16];
+  LittleEndian::write_u64(&mut lengths[..8], aad.len() as u64);
+  LittleEndian::write_u64(&mut lengths[8..], ciphertext.len() as u64);
+  data.extend(lengths.iter());
+  data
+}
+
+fn compute_tag(
+  key: &[u8],
+  nonce: &[u8],
+  aad: &[u8],
+  ciphertext: &[u8],
+) -> Result<Vec<u8>, CryptoError> {
+  let one_time_key = poly1305_key(key, nonce)?;
+  poly1305::poly1305(&one_time_key, &mac_data(aad, ciphertext))
+}
+
+fn pad16(len: usize) -> usize {
+  (16 - len % 16) % 16
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  fn rfc_8439_inputs() -> (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>) {
+    // section 2.8.2
+    let key: Vec<u8> = (0x80..0xa0).collect();
+    let nonce = hex::decode("070000004041424344454647").unwrap();
+    let aad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+    let plaintext = "Ladies and Gentlemen of the class of '99: If I could \
+                     offer you only one tip for the future, sunscreen would \
+                     be it."
+      .as_bytes()
+      .to_vec();
+    (key, nonce, aad, plaintext)
+  }
+
+  #[test]
+  fn it_seals_the_rfc_8439_example() {
+    let (key, nonce, aad, plaintext) = rfc_8439_inputs();
+    let sealed = encrypt(&key, &nonce, &aad, &plaintext).unwrap();
+
+    assert_eq!(
+      hex::encode(&sealed[..16]),
+      "d31a8d34648e60db7b86afbc53ef7ec2"
+    );
+    assert_eq!(
+      hex::encode(&sealed[(sealed.len() - TAG_SIZE)..]),
+      "1ae10b594f09e26a7e902ecbd0600691"
+    );
+    assert_eq!(decrypt(&key, &nonce, &aad, &sealed).unwrap(), plaintext);
+  }
+
+  #[test]
+  fn it_rejects_tampered_messages() {
+    let (key, nonce, aad, plaintext) = rfc_8439_inputs();
+    let sealed = encrypt(&key, &nonce, &aad, &plaintext).unwrap();
+
+    let mut flipped = sealed.clone();
+    flipped[0] ^= 1;
+    assert_eq!(
+      decrypt(&key, &nonce, &aad, &flipped),
+      Err(CryptoError::AuthenticationFailed)
+    );
+    assert_eq!(
+      decrypt(&key, &nonce, b"other aad", &sealed),
+      Err(CryptoError::AuthenticationFailed)
+    );
+    assert_eq!(
+      decrypt(&key, &nonce, &aad, &sealed[..10]),
+      Err(CryptoError::AuthenticationFailed)
+    );
+  }
+
+  #[test]
+  fn it_pads_the_mac_data_to_16_bytes() {
+    let data = mac_data(&[1, 2, 3], &[4; 17]);
+
+    assert_eq!(data.len(), 16 + 32 + 16);
+    assert_eq!(data[16], 4);
+    assert_eq!(&data[48..56], &[3, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&data[56..], &[17, 0, 0, 0, 0, 0, 0, 0]);
+  }
+}
@@ -0,0 +1,364 @@
+extern crate rand;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Generic collision search on a function cut down to n bits. The birthday
+// table is the simplest, about 2^(n/2) evaluations and as much memory. Rho
+// methods walk x, f(x), f(f(x)), ... which, the outputs being n bits,
+// runs into a cycle after about 2^(n/2) steps; where the walk first meets
+// the cycle two different points map to the same one, in constant memory.
+// Van Oorschot and Wiener's distinguished points split that walking
+// across threads: each walks until it reaches a point that's rare and easy
+// to spot (the top few bits zero) and records it, and two walks ending on
+// the same one have merged somewhere, which retracing both finds.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+  pub inputs: (Vec<u8>, Vec<u8>),
+  pub output: Vec<u8>,
+  // calls to the underlying function
+  pub evaluations: usize,
+}
+
+// A function's first `bits` bits of output. Inputs and outputs are both
+// n bit points, in the first n bits of ceil(n / 8) bytes, so it can be
+// iterated.
+pub struct Truncated<F> {
+  function: F,
+  bits: usize,
+}
+
+impl<F: Fn(&[u8]) -> Vec<u8>> Truncated<F> {
+  pub fn new(function: F, bits: usize) -> Truncated<F> {
+    Truncated { function, bits }
+  }
+
+  pub fn bits(&self) -> usize {
+    self.bits
+  }
+
+  pub fn apply(&self, input: &[u8]) -> Vec<u8> {
+    self.truncate((self.function)(input))
+  }
+
+  pub fn random_point(&self) -> Vec<u8> {
+    self.truncate((0..self.bits.div_ceil(8)).map(|_| rand::random()).collect())
+  }
+
+  fn truncate(&self, mut bytes: Vec<u8>) -> Vec<u8> {
+    bytes.truncate(self.bits.div_ceil(8));
+    if !self.bits.is_multiple_of(8) {
+      if let Some(last) = bytes.last_mut() {
+        *last &= 0xff << (8 - self.bits % 8);
+      }
+    }
+    bytes
+  }
+}
+
+// Random points until two outputs match.
+pub fn birthday<F: Fn(&[u8]) -> Vec<u8>>(f: &Truncated<F>) -> Collision {
+  let mut seen: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+  let mut evaluations = 0;
+  loop {
+    let input = f.random_point();
+    let output = f.apply(&input);
+    evaluations += 1;
+    match seen.get(&output) {
+      Some(other) if *other != input => {
+        return Collision {
+          inputs: (other.clone(), input),
+          output,
+          evaluations,
+        }
+      }
+      _ => {
+        seen.insert(output, input);
+      }
+    }
+  }
+}
+
+// Floyd's tortoise and hare: one walker at twice the speed of the other
+// meets it somewhere on the cycle, a multiple of the cycle's length from
+// the start.
+pub fn floyd<F: Fn(&[u8]) -> Vec<u8>>(f: &Truncated<F>) -> Collision {
+  let mut evaluations = 0;
+  loop {
+    let start = f.random_point();
+    let mut tortoise = f.apply(&start);
+    let mut hare = f.apply(&tortoise);
+    evaluations += 2;
+    while tortoise != hare {
+      tortoise = f.apply(&tortoise);
+      hare = f.apply(&f.apply(&hare));
+      evaluations += 3;
+    }
+    if let Some(collision) = cycle_entry(f, start, hare, &mut evaluations) {
+      return collision;
+    }
+  }
+}
+
+// Brent's variant: the tortoise teleports to the hare at every power of
+// two instead of walking, which finds the cycle's length directly and
+// takes fewer evaluations.
+pub fn brent<F: Fn(&[u8]) -> Vec<u8>>(f: &Truncated<F>) -> Collision {
+  let mut evaluations = 0;
+  loop {
+    let start = f.random_point();
+    let mut power = 1;
+    let mut cycle_len = 1;
+    let mut tortoise = start.clone();
+    let mut hare = f.apply(&start);
+    evaluations += 1;
+    while tortoise != hare {
+      if power == cycle_len {
+        tortoise = hare.clone();
+        power *= 2;
+        cycle_len = 0;
+      }
+      hare = f.apply(&hare);
+      cycle_len += 1;
+      evaluations += 1;
+    }
+    let mut ahead = start.clone();
+    for _ in 0..cycle_len {
+      ahead = f.apply(&ahead);
+    }
+    evaluations += cycle_len;
+    if let Some(collision) = cycle_entry(f, start, ahead, &mut evaluations) {
+      return collision;
+    }
+  }
+}
+
+// With `ahead` a whole number of cycles further along the walk than
+// `behind`, steps both until they land on the same point, the start of the
+// cycle, and returns the two points they came from. None when the walk
+// started on the cycle, leaving nothing to collide with.
+fn cycle_entry<F: Fn(&[u8]) -> Vec<u8>>(
+  f: &Truncated<F>,
+  mut behind: Vec<u8>,
+  mut ahead: Vec<u8>,
+  evaluations: &mut usize,
+) -> Option<Collision> {
+  if behind == ahead {
+    return None;
+  }
+  loop {
+    let next_behind = f.apply(&behind);
+    let next_ahead = f.apply(&ahead);
+    *evaluations += 2;
+    if next_behind == next_ahead {
+      return Some(Collision {
+        inputs: (behind, ahead),
+        output: next_behind,
+        evaluations: *evaluations,
+      });
+    }
+    behind = next_behind;
+    ahead = next_ahead;
+  }
+}
+
+pub struct DistinguishedPointConfig {
+  // points whose first this many bits are zero
+  pub distinguished_bits: usize,
+  // walks longer than this are likely stuck in a cycle without a
+  // distinguished point, and are dropped
+  pub max_walk: usize,
+  pub threads: usize,
+}
+
+impl DistinguishedPointConfig {
+  pub fn new(distinguished_bits: usize) -> DistinguishedPointConfig {
+    // 20 times the expected walk, or as long as a walk can get when that
+    // doesn't fit
+    let max_walk = if distinguished_bits <= usize::BITS as usize - 5 {
+      20 << distinguished_bits
+    } else {
+      usize::MAX
+    };
+    DistinguishedPointConfig {
+      distinguished_bits,
+      max_walk,
+      threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    }
+  }
+}
+
+// A walk from `start` that reached a distinguished point in `length` steps.
+#[derive(Clone)]
+struct Walk {
+  start: Vec<u8>,
+  length: usize,
+}
+
+pub fn distinguished_points<F>(
+  f: &Truncated<F>,
+  config: &DistinguishedPointConfig,
+) -> Collision
+where
+  F: Fn(&[u8]) -> Vec<u8> + Sync,
+{
+  let table: Mutex<HashMap<Vec<u8>, Walk>> = Mutex::new(HashMap::new());
+  let found: Mutex<Option<Collision>> = Mutex::new(None);
+  let done = AtomicBool::new(false);
+  let evaluations = AtomicUsize::new(0);
+
+  thread::scope(|scope| {
+    for _ in 0..config.threads.max(1) {
+      scope.spawn(|| {
+        while !done.load(Ordering::Relaxed) {
+          let start = f.random_point();
+          let mut point = start.clone();
+          let mut length = 0;
+          while !is_distinguished(&point, config.distinguished_bits)
+            && length <= config.max_walk
+          {
+            point = f.apply(&point);
+            length += 1;
+          }
+          evaluations.fetch_add(length, Ordering::Relaxed);
+          if length > config.max_walk {
+            continue;
+          }
+          let walk = Walk { start, length };
+          let other = match table.lock().unwrap().insert(point, walk.clone()) {
+            Some(other) => other,
+            None => continue,
+          };
+          let mut work = 0;
+          if let Some(collision) = merge_point(f, &walk, &other, &mut work) {
+            evaluations.fetch_add(work, Ordering::Relaxed);
+            done.store(true, Ordering::Relaxed);
+            *found.lock().unwrap() = Some(collision);
+          }
+        }
+      });
+    }
+  });
+
+  let mut collision = found
+    .into_inner()
+    .unwrap()
+    .expect("workers only stop once a collision is found");
+  collision.evaluations = evaluations.into_inner();
+  collision
+}
+
+// Two walks ending on the same distinguished point: lines them up the same
+// distance from it and steps both until they meet. None if one started on
+// the other's path, so they never differ.
+fn merge_point<F: Fn(&[u8]) -> Vec<u8>>(
+  f: &Truncated<F>,
+  a: &Walk,
+  b: &Walk,
+  evaluations: &mut usize,
+) -> Option<Collision> {
+  let (longer, shorter) = if a.length >= b.length { (a, b) } else { (b, a) };
+  let mut ahead = longer.start.clone();
+  for _ in 0..longer.length - shorter.length {
+    ahead = f.apply(&ahead);
+  }
+  *evaluations += longer.length - shorter.length;
+  cycle_entry(f, shorter.start.clone(), ahead, evaluations)
+}
+
+fn is_distinguished(point: &[u8], bits: usize) -> bool {
+  let mut zeros = 0;
+  for byte in point {
+    zeros += byte.leading_zeros() as usize;
+    if *byte != 0 {
+      break;
+    }
+  }
+  zeros >= bits
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::authentication::hmac;
+  use crustopals::tools::sha2::{sha256, Sha256};
+
+  type TruncatedSha256 = Truncated<fn(&[u8]) -> Vec<u8>>;
+
+  fn check(f: &TruncatedSha256, collision: &Collision) {
+    let (a, b) = &collision.inputs;
+
+    assert_ne!(a, b);
+    assert_eq!(f.apply(a), collision.output);
+    assert_eq!(f.apply(b), collision.output);
+  }
+
+  #[test]
+  fn it_truncates_to_whole_and_partial_bytes() {
+    let f = Truncated::new(|_: &[u8]| vec![0xff; 4], 20);
+
+    assert_eq!(f.apply(b""), vec![0xff, 0xff, 0xf0]);
+    assert_eq!(f.random_point().len(), 3);
+    assert_eq!(f.random_point()[2] & 0x0f, 0);
+    assert!(is_distinguished(&[0, 0x1f], 11));
+    assert!(!is_distinguished(&[0, 0x1f], 12));
+  }
+
+  #[test]
+  fn it_finds_collisions_with_a_table_and_with_rho() {
+    let f: TruncatedSha256 = Truncated::new(sha256, 24);
+    let table = birthday(&f);
+    let floyd = floyd(&f);
+    let brent = brent(&f);
+
+    check(&f, &table);
+    check(&f, &floyd);
+    check(&f, &brent);
+    // a few times 2^12 rather than 2^24
+    for collision in [table, floyd, brent].iter() {
+      assert!(collision.evaluations < 1 << 17);
+    }
+  }
+
+  #[test]
+  fn it_finds_collisions_in_parallel_with_distinguished_points() {
+    let f: TruncatedSha256 = Truncated::new(sha256, 28);
+    let mut config = DistinguishedPointConfig::new(6);
+    config.threads = 4;
+    let collision = distinguished_points(&f, &config);
+
+    check(&f, &collision);
+    assert!(collision.evaluations < 1 << 19);
+  }
+
+  #[test]
+  fn it_caps_the_walk_length_instead_of_overflowing() {
+    assert_eq!(DistinguishedPointConfig::new(6).max_walk, 20 << 6);
+    let widest = usize::BITS as usize - 5;
+    assert_eq!(DistinguishedPointConfig::new(widest).max_walk, 20 << widest);
+    assert_eq!(
+      DistinguishedPointConfig::new(widest + 1).max_walk,
+      usize::MAX
+    );
+    assert_eq!(DistinguishedPointConfig::new(200).max_walk, usize::MAX);
+  }
+
+  // A MAC cut down to 32 bits, as some protocols do, gives forgeable
+  // collisions after ~2^16 queries.
+  #[test]
+  fn truncated_macs_collide() {
+    let key = b"a key the attacker never sees";
+    let f = Truncated::new(|m: &[u8]| hmac::<Sha256>(key, m), 32);
+    let collision = distinguished_points(&f, &DistinguishedPointConfig::new(8));
+    let (a, b) = &collision.inputs;
+
+    assert_ne!(a, b);
+    assert_eq!(
+      hmac::<Sha256>(key, a)[..4].to_vec(),
+      hmac::<Sha256>(key, b)[..4].to_vec()
+    );
+  }
+}
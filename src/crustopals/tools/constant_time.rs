@@ -0,0 +1,131 @@
+extern crate rand;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+// Byte comparison for secrets, and a way to check that something doesn't
+// leak through its running time, after dudect ("Dude, is my code constant
+// time?", Reparaz, Balasch and Verbauwhede): time an operation over two
+// classes of input, interleaved at random so drift and noise land on both,
+// and ask Welch's t-test whether the two timing distributions differ.
+
+// |t| past this is taken as a leak, as in dudect
+pub const LEAKAGE_THRESHOLD: f64 = 4.5;
+
+// Looks at every byte whatever the differences, so the time taken doesn't
+// give away where the first mismatch is. Only the lengths, which aren't
+// secret for a MAC, are compared early.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+  if a.len() != b.len() {
+    return false;
+  }
+  let difference = a
+    .iter()
+    .zip(b.iter())
+    .fold(0u8, |acc, (x, y)| acc | (x ^ y));
+  // an optimisation barrier, so the fold is left as written
+  black_box(difference) == 0
+}
+
+// Welch's t-statistic for the difference in means of two samples that
+// needn't share a variance.
+pub fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+  let (mean_a, var_a) = mean_and_variance(a);
+  let (mean_b, var_b) = mean_and_variance(b);
+  (mean_a - mean_b) / (var_a / a.len() as f64 + var_b / b.len() as f64).sqrt()
+}
+
+// Runs `operation` `runs` times, each on a randomly picked class (false or
+// true), and returns Welch's t over the two classes' times in nanoseconds.
+// The slowest tenth of the runs, interrupts and the like, is dropped first.
+// Anything the operation needs should be prepared beforehand so only the
+// part under test is timed.
+pub fn timing_t_statistic<F: FnMut(bool)>(
+  runs: usize,
+  mut operation: F,
+) -> f64 {
+  let mut measurements: Vec<(bool, f64)> = (0..runs)
+    .map(|_| {
+      let class = rand::random::<bool>();
+      let start = Instant::now();
+      operation(black_box(class));
+      (class, start.elapsed().as_nanos() as f64)
+    })
+    .collect();
+  measurements.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+  measurements.truncate(runs - runs / 10);
+  let times = |class: bool| -> Vec<f64> {
+    measurements
+      .iter()
+      .filter(|m| m.0 == class)
+      .map(|m| m.1)
+      .collect()
+  };
+  welch_t(&times(false), &times(true))
+}
+
+pub fn leaks_timing<F: FnMut(bool)>(runs: usize, operation: F) -> bool {
+  timing_t_statistic(runs, operation).abs() > LEAKAGE_THRESHOLD
+}
+
+fn mean_and_variance(sample: &[f64]) -> (f64, f64) {
+  let n = sample.len() as f64;
+  let mean = sample.iter().sum::<f64>() / n;
+  let variance =
+    sample.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+  (mean, variance)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // what `==` on slices amounts to, without the memcmp
+  fn early_exit_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x == y)
+  }
+
+  // class false compares equal buffers, class true ones differing in the
+  // first byte
+  fn inputs() -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let secret = vec![0x5a; 4096];
+    let mut wrong = secret.clone();
+    wrong[0] ^= 1;
+    (secret.clone(), secret, wrong)
+  }
+
+  #[test]
+  fn it_compares_in_constant_time() {
+    assert!(ct_eq(b"", b""));
+    assert!(ct_eq(b"a tag", b"a tag"));
+    assert!(!ct_eq(b"a tag", b"a taG"));
+    assert!(!ct_eq(b"a tag", b"a ta"));
+  }
+
+  #[test]
+  fn it_computes_welchs_t() {
+    let a = [1.0, 2.0, 3.0, 4.0];
+    let b = [2.0, 4.0, 6.0, 8.0, 10.0];
+
+    assert!((welch_t(&a, &b) + 2.2514363).abs() < 1e-6);
+    assert_eq!(welch_t(&a, &a), 0.0);
+  }
+
+  #[test]
+  fn ct_eq_doesnt_leak_where_the_mismatch_is() {
+    let (secret, equal, wrong) = inputs();
+
+    assert!(!leaks_timing(20000, |class| {
+      black_box(ct_eq(&secret, if class { &wrong } else { &equal }));
+    }));
+  }
+
+  #[test]
+  fn an_early_exit_compare_leaks() {
+    let (secret, equal, wrong) = inputs();
+
+    assert!(leaks_timing(20000, |class| {
+      black_box(early_exit_eq(&secret, if class { &wrong } else { &equal }));
+    }));
+  }
+}
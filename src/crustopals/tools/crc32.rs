@@ -0,0 +1,197 @@
+// CRC-32 in its usual reflected form: bytes go in least significant bit
+// first and the register is inverted on the way in and on the way out.
+//
+// A CRC is linear over GF(2) apart from those inversions, so for messages of
+// equal length crc(a ^ b) == crc(a) ^ crc(b) ^ crc(0...0). That makes it
+// useless as an integrity check against anyone who can flip bits: the change
+// in the CRC is known without knowing the message, and four freely chosen
+// bytes anywhere in a message can steer it to any CRC at all.
+
+use crustopals::tools::CryptoError;
+
+// x^32 + x^26 + x^23 + ... + 1, bit reversed
+pub const IEEE: u32 = 0xedb8_8320;
+// Castagnoli's polynomial (CRC-32C, used by iSCSI, SCTP and ext4), bit
+// reversed
+pub const CASTAGNOLI: u32 = 0x82f6_3b78;
+
+pub struct Crc32 {
+  table: [u32; 256],
+  // table index by the top byte of its entry, which is unique per entry
+  reverse: [u8; 256],
+}
+
+lazy_static! {
+  pub static ref IEEE_CRC: Crc32 = Crc32::new(IEEE);
+  pub static ref CASTAGNOLI_CRC: Crc32 = Crc32::new(CASTAGNOLI);
+}
+
+impl Crc32 {
+  // `polynomial` in reflected (bit reversed) form.
+  pub fn new(polynomial: u32) -> Crc32 {
+    let mut table = [0u32; 256];
+    let mut reverse = [0u8; 256];
+    for (byte, entry) in table.iter_mut().enumerate() {
+      let mut register = byte as u32;
+      for _ in 0..8 {
+        register = if register & 1 == 1 {
+          (register >> 1) ^ polynomial
+        } else {
+          register >> 1
+        };
+      }
+      *entry = register;
+      reverse[(register >> 24) as usize] = byte as u8;
+    }
+    Crc32 { table, reverse }
+  }
+
+  pub fn checksum(&self, bytes: &[u8]) -> u32 {
+    !self.update(!0, bytes)
+  }
+
+  // How the CRC of any message of this length changes when `delta` is xored
+  // into it: crc(m ^ delta) == crc(m) ^ flip_delta(delta).
+  pub fn flip_delta(&self, delta: &[u8]) -> u32 {
+    self.checksum(delta) ^ self.checksum(&vec![0u8; delta.len()])
+  }
+
+  // The four bytes to append to `message` to make its CRC `target`.
+  pub fn forge_suffix(&self, message: &[u8], target: u32) -> [u8; 4] {
+    self.bridge(self.update(!0, message), !target)
+  }
+
+  // `message` with four bytes inserted at `offset`, chosen so its CRC is
+  // `target`. The offset can be anywhere up to the end of the message.
+  pub fn forge_insert(
+    &self,
+    message: &[u8],
+    offset: usize,
+    target: u32,
+  ) -> Result<Vec<u8>, CryptoError> {
+    if offset > message.len() {
+      return Err(CryptoError::OffsetOutOfRange {
+        offset,
+        len: message.len(),
+      });
+    }
+    let (prefix, suffix) = message.split_at(offset);
+    let before = self.update(!0, prefix);
+    let after = self.unwind(!target, suffix);
+    Ok([prefix, &self.bridge(before, after)[..], suffix].concat())
+  }
+
+  fn update(&self, register: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(register, |register, byte| {
+      (register >> 8) ^ self.table[((register ^ *byte as u32) & 0xff) as usize]
+    })
+  }
+
+  // The register before `bytes` that leads to `register` after them. Each
+  // step's table entry is identified by the top byte it leaves behind.
+  fn unwind(&self, register: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().rev().fold(register, |register, byte| {
+      let index = self.reverse[(register >> 24) as usize];
+      ((register ^ self.table[index as usize]) << 8) | (index ^ *byte) as u32
+    })
+  }
+
+  // Four bytes taking the register from `from` to `to`. After four steps
+  // nothing of `from` is left, so the table indices are fixed by `to`
+  // alone, and each byte is whatever selects its index.
+  fn bridge(&self, from: u32, to: u32) -> [u8; 4] {
+    let mut indices = [0u8; 4];
+    let mut register = to;
+    for index in indices.iter_mut().rev() {
+      *index = self.reverse[(register >> 24) as usize];
+      register = (register ^ self.table[*index as usize]) << 8;
+    }
+    let mut bytes = [0u8; 4];
+    let mut register = from;
+    for (byte, index) in bytes.iter_mut().zip(indices.iter()) {
+      *byte = (register as u8) ^ index;
+      register = (register >> 8) ^ self.table[*index as usize];
+    }
+    bytes
+  }
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+  IEEE_CRC.checksum(bytes)
+}
+
+pub fn crc32c(bytes: &[u8]) -> u32 {
+  CASTAGNOLI_CRC.checksum(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::aes;
+
+  #[test]
+  fn it_computes_the_standard_check_values() {
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(
+      crc32(b"The quick brown fox jumps over the lazy dog"),
+      0x414f_a339
+    );
+    assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+    assert_eq!(crc32c(&[0u8; 32]), 0x8a91_36aa);
+  }
+
+  #[test]
+  fn it_predicts_the_crc_of_flipped_messages() {
+    for crc in [&*IEEE_CRC, &*CASTAGNOLI_CRC].iter() {
+      let message = aes::generate_rand_bytes(40);
+      let mut delta = vec![0u8; 40];
+      delta[3] = 0x80;
+      delta[39] = 0x01;
+      let flipped: Vec<u8> = message
+        .iter()
+        .zip(delta.iter())
+        .map(|(m, d)| m ^ d)
+        .collect();
+
+      assert_eq!(
+        crc.checksum(&flipped),
+        crc.checksum(&message) ^ crc.flip_delta(&delta)
+      );
+    }
+  }
+
+  #[test]
+  fn it_forges_suffixes_for_any_target() {
+    for crc in [&*IEEE_CRC, &*CASTAGNOLI_CRC].iter() {
+      let message = "pay mallory $1,000,000".as_bytes();
+      let target = crc.checksum(b"pay mallory $1");
+      let suffix = crc.forge_suffix(message, target);
+
+      assert_eq!(crc.checksum(&[message, &suffix[..]].concat()), target);
+    }
+  }
+
+  #[test]
+  fn it_forges_insertions_anywhere() {
+    let original = "amount=10;to=alice;memo=rent".as_bytes();
+    let edited = "amount=99999;to=mallory;memo=rent".as_bytes();
+    for crc in [&*IEEE_CRC, &*CASTAGNOLI_CRC].iter() {
+      let target = crc.checksum(original);
+      for offset in [0, 12, edited.len()].iter() {
+        let forged = crc.forge_insert(edited, *offset, target).unwrap();
+
+        assert_eq!(crc.checksum(&forged), target);
+        assert_eq!(&forged[..*offset], &edited[..*offset]);
+        assert_eq!(&forged[(*offset + 4)..], &edited[*offset..]);
+      }
+      assert_eq!(
+        crc.forge_insert(edited, edited.len() + 1, target),
+        Err(CryptoError::OffsetOutOfRange {
+          offset: edited.len() + 1,
+          len: edited.len()
+        })
+      );
+    }
+  }
+}
@@ -0,0 +1,371 @@
+use crustopals::tools::aes;
+use crustopals::tools::block_cipher::BlockCipher;
+use crustopals::tools::CryptoError;
+
+// All of the tables below use the FIPS 46-3 numbering where bit 1 is the most
+// significant bit of the input.
+const IP: [u8; 64] = [
+  58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4, 62, 54, 46, 38,
+  30, 22, 14, 6, 64, 56, 48, 40, 32, 24, 16, 8, 57, 49, 41, 33, 25, 17, 9, 1,
+  59, 51, 43, 35, 27, 19, 11, 3, 61, 53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39,
+  31, 23, 15, 7,
+];
+
+const FP: [u8; 64] = [
+  40, 8, 48, 16, 56, 24, 64, 32, 39, 7, 47, 15, 55, 23, 63, 31, 38, 6, 46, 14,
+  54, 22, 62, 30, 37, 5, 45, 13, 53, 21, 61, 29, 36, 4, 44, 12, 52, 20, 60, 28,
+  35, 3, 43, 11, 51, 19, 59, 27, 34, 2, 42, 10, 50, 18, 58, 26, 33, 1, 41, 9,
+  49, 17, 57, 25,
+];
+
+const E: [u8; 48] = [
+  32, 1, 2, 3, 4, 5, 4, 5, 6, 7, 8, 9, 8, 9, 10, 11, 12, 13, 12, 13, 14, 15,
+  16, 17, 16, 17, 18, 19, 20, 21, 20, 21, 22, 23, 24, 25, 24, 25, 26, 27, 28,
+  29, 28, 29, 30, 31, 32, 1,
+];
+
+const P: [u8; 32] = [
+  16, 7, 20, 21, 29, 12, 28, 17, 1, 15, 23, 26, 5, 18, 31, 10, 2, 8, 24, 14,
+  32, 27, 3, 9, 19, 13, 30, 6, 22, 11, 4, 25,
+];
+
+const PC1: [u8; 56] = [
+  57, 49, 41, 33, 25, 17, 9, 1, 58, 50, 42, 34, 26, 18, 10, 2, 59, 51, 43, 35,
+  27, 19, 11, 3, 60, 52, 44, 36, 63, 55, 47, 39, 31, 23, 15, 7, 62, 54, 46, 38,
+  30, 22, 14, 6, 61, 53, 45, 37, 29, 21, 13, 5, 28, 20, 12, 4,
+];
+
+const PC2: [u8; 48] = [
+  14, 17, 11, 24, 1, 5, 3, 28, 15, 6, 21, 10, 23, 19, 12, 4, 26, 8, 16, 7, 27,
+  20, 13, 2, 41, 52, 31, 37, 47, 55, 30, 40, 51, 45, 33, 48, 44, 49, 39, 56,
+  34, 53, 46, 42, 50, 36, 29, 32,
+];
+
+const KEY_SHIFTS: [u32; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
+
+const S_BOXES: [[u8; 64]; 8] = [
+  [
+    14, 4, 13, 1, 2, 15, 11, 8, 3, 10, 6, 12, 5, 9, 0, 7, 0, 15, 7, 4, 14, 2,
+    13, 1, 10, 6, 12, 11, 9, 5, 3, 8, 4, 1, 14, 8, 13, 6, 2, 11, 15, 12, 9, 7,
+    3, 10, 5, 0, 15, 12, 8, 2, 4, 9, 1, 7, 5, 11, 3, 14, 10, 0, 6, 13,
+  ],
+  [
+    15, 1, 8, 14, 6, 11, 3, 4, 9, 7, 2, 13, 12, 0, 5, 10, 3, 13, 4, 7, 15, 2,
+    8, 14, 12, 0, 1, 10, 6, 9, 11, 5, 0, 14, 7, 11, 10, 4, 13, 1, 5, 8, 12, 6,
+    9, 3, 2, 15, 13, 8, 10, 1, 3, 15, 4, 2, 11, 6, 7, 12, 0, 5, 14, 9,
+  ],
+  [
+    10, 0, 9, 14, 6, 3, 15, 5, 1, 13, 12, 7, 11, 4, 2, 8, 13, 7, 0, 9, 3, 4, 6,
+    10, 2, 8, 5, 14, 12, 11, 15, 1, 13, 6, 4, 9, 8, 15, 3, 0, 11, 1, 2, 12, 5,
+    10, 14, 7, 1, 10, 13, 0, 6, 9, 8, 7, 4, 15, 14, 3, 11, 5, 2, 12,
+  ],
+  [
+    7, 13, 14, 3, 0, 6, 9, 10, 1, 2, 8, 5, 11, 12, 4, 15, 13, 8, 11, 5, 6, 15,
+    0, 3, 4, 7, 2, 12, 1, 10, 14, 9, 10, 6, 9, 0, 12, 11, 7, 13, 15, 1, 3, 14,
+    5, 2, 8, 4, 3, 15, 0, 6, 10, 1, 13, 8, 9, 4, 5, 11, 12, 7, 2, 14,
+  ],
+  [
+    2, 12, 4, 1, 7, 10, 11, 6, 8, 5, 3, 15, 13, 0, 14, 9, 14, 11, 2, 12, 4, 7,
+    13, 1, 5, 0, 15, 10, 3, 9, 8, 6, 4, 2, 1, 11, 10, 13, 7, 8, 15, 9, 12, 5,
+    6, 3, 0, 14, 11, 8, 12, 7, 1, 14, 2, 13, 6, 15, 0, 9, 10, 4, 5, 3,
+  ],
+  [
+    12, 1, 10, 15, 9, 2, 6, 8, 0, 13, 3, 4, 14, 7, 5, 11, 10, 15, 4, 2, 7, 12,
+    9, 5, 6, 1, 13, 14, 0, 11, 3, 8, 9, 14, 15, 5, 2, 8, 12, 3, 7, 0, 4, 10, 1,
+    13, 11, 6, 4, 3, 2, 12, 9, 5, 15, 10, 11, 14, 1, 7, 6, 0, 8, 13,
+  ],
+  [
+    4, 11, 2, 14, 15, 0, 8, 13, 3, 12, 9, 7, 5, 10, 6, 1, 13, 0, 11, 7, 4, 9,
+    1, 10, 14, 3, 5, 12, 2, 15, 8, 6, 1, 4, 11, 13, 12, 3, 7, 14, 10, 15, 6, 8,
+    0, 5, 9, 2, 6, 11, 13, 8, 1, 4, 10, 7, 9, 5, 0, 15, 14, 2, 3, 12,
+  ],
+  [
+    13, 2, 8, 4, 6, 15, 11, 1, 10, 9, 3, 14, 5, 0, 12, 7, 1, 15, 13, 8, 10, 3,
+    7, 4, 12, 5, 6, 11, 0, 14, 9, 2, 7, 11, 4, 1, 9, 12, 14, 2, 0, 6, 10, 13,
+    15, 3, 5, 8, 2, 1, 14, 7, 4, 10, 8, 13, 15, 12, 9, 0, 3, 5, 6, 11,
+  ],
+];
+
+pub fn generate_key() -> Vec<u8> {
+  aes::generate_rand_bytes(8)
+}
+
+pub fn generate_iv() -> Vec<u8> {
+  aes::generate_rand_bytes(8)
+}
+
+pub struct Des {
+  subkeys: [u64; 16],
+}
+
+impl Des {
+  // Takes an 8 byte key. The parity bits (the low bit of each byte) are
+  // ignored, as in every other implementation.
+  pub fn new(key: &[u8]) -> Result<Des, CryptoError> {
+    if key.len() != 8 {
+      return Err(CryptoError::InvalidKeyLength {
+        expected: 8,
+        actual: key.len(),
+      });
+    }
+    Ok(Des {
+      subkeys: key_schedule(bytes_to_u64(key)),
+    })
+  }
+
+  fn encrypt_u64(&self, block: u64) -> u64 {
+    feistel(block, self.subkeys.iter())
+  }
+
+  fn decrypt_u64(&self, block: u64) -> u64 {
+    feistel(block, self.subkeys.iter().rev())
+  }
+}
+
+impl BlockCipher for Des {
+  fn block_size(&self) -> usize {
+    8
+  }
+
+  fn encrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    u64_to_bytes(self.encrypt_u64(bytes_to_u64(block)))
+  }
+
+  fn decrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    u64_to_bytes(self.decrypt_u64(bytes_to_u64(block)))
+  }
+}
+
+// Triple DES in encrypt-decrypt-encrypt form. A 16 byte key is the two key
+// variant (K3 = K1), a 24 byte key is the three key variant.
+pub struct TripleDes {
+  des1: Des,
+  des2: Des,
+  des3: Des,
+}
+
+impl TripleDes {
+  pub fn new(key: &[u8]) -> Result<TripleDes, CryptoError> {
+    match key.len() {
+      16 => Ok(TripleDes {
+        des1: Des::new(&key[0..8])?,
+        des2: Des::new(&key[8..16])?,
+        des3: Des::new(&key[0..8])?,
+      }),
+      24 => Ok(TripleDes {
+        des1: Des::new(&key[0..8])?,
+        des2: Des::new(&key[8..16])?,
+        des3: Des::new(&key[16..24])?,
+      }),
+      _ => Err(CryptoError::UnsupportedKeyLength {
+        supported: &[16, 24],
+        actual: key.len(),
+      }),
+    }
+  }
+}
+
+impl BlockCipher for TripleDes {
+  fn block_size(&self) -> usize {
+    8
+  }
+
+  fn encrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    let block = self.des1.encrypt_u64(bytes_to_u64(block));
+    let block = self.des2.decrypt_u64(block);
+    u64_to_bytes(self.des3.encrypt_u64(block))
+  }
+
+  fn decrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    let block = self.des3.decrypt_u64(bytes_to_u64(block));
+    let block = self.des2.encrypt_u64(block);
+    u64_to_bytes(self.des1.decrypt_u64(block))
+  }
+}
+
+fn feistel<'a, I: Iterator<Item = &'a u64>>(block: u64, subkeys: I) -> u64 {
+  let permuted = permute(block, 64, &IP);
+  let mut left = (permuted >> 32) as u32;
+  let mut right = permuted as u32;
+  for subkey in subkeys {
+    let next_right = left ^ round_function(right, *subkey);
+    left = right;
+    right = next_right;
+  }
+  // the halves are swapped one last time before the final permutation
+  let preoutput = ((right as u64) << 32) | left as u64;
+  permute(preoutput, 64, &FP)
+}
+
+fn round_function(half_block: u32, subkey: u64) -> u32 {
+  let expanded = permute(half_block as u64, 32, &E) ^ subkey;
+  let mut substituted: u32 = 0;
+  for (i, s_box) in S_BOXES.iter().enumerate() {
+    let six_bits = ((expanded >> (42 - 6 * i)) & 0x3f) as usize;
+    // outer bits select the row, inner four bits select the column
+    let row = ((six_bits & 0x20) >> 4) | (six_bits & 1);
+    let col = (six_bits >> 1) & 0xf;
+    substituted = (substituted << 4) | s_box[row * 16 + col] as u32;
+  }
+  permute(substituted as u64, 32, &P) as u32
+}
+
+fn key_schedule(key: u64) -> [u64; 16] {
+  let permuted_key = permute(key, 64, &PC1);
+  let mut c = (permuted_key >> 28) as u32;
+  let mut d = (permuted_key & 0x0fff_ffff) as u32;
+  let mut subkeys = [0u64; 16];
+  for (round, shift) in KEY_SHIFTS.iter().enumerate() {
+    c = rotate_28(c, *shift);
+    d = rotate_28(d, *shift);
+    let cd = ((c as u64) << 28) | d as u64;
+    subkeys[round] = permute(cd, 56, &PC2);
+  }
+  subkeys
+}
+
+fn rotate_28(half: u32, shift: u32) -> u32 {
+  ((half << shift) | (half >> (28 - shift))) & 0x0fff_ffff
+}
+
+fn permute(input: u64, input_bits: u32, table: &[u8]) -> u64 {
+  let mut output: u64 = 0;
+  for position in table.iter() {
+    let bit = (input >> (input_bits - *position as u32)) & 1;
+    output = (output << 1) | bit;
+  }
+  output
+}
+
+fn bytes_to_u64(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn u64_to_bytes(val: u64) -> Vec<u8> {
+  (0..8).map(|i| (val >> (56 - 8 * i)) as u8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+  use crustopals::tools::block_cipher;
+
+  #[test]
+  fn encrypts_the_fips_46_worked_example() {
+    let des = Des::new(&hex::decode("133457799bbcdff1").unwrap()).unwrap();
+    let plaintext = hex::decode("0123456789abcdef").unwrap();
+    let ciphertext = des.encrypt_block(&plaintext);
+
+    assert_eq!(hex::encode(&ciphertext), "85e813540f0ab405");
+    assert_eq!(des.decrypt_block(&ciphertext), plaintext);
+  }
+
+  #[test]
+  fn passes_nist_variable_plaintext_known_answer_tests() {
+    // NIST SP 800-17, table A.1 (first few rows)
+    let key = hex::decode("0101010101010101").unwrap();
+    let des = Des::new(&key).unwrap();
+    let vectors = [
+      ("8000000000000000", "95f8a5e5dd31d900"),
+      ("4000000000000000", "dd7f121ca5015619"),
+      ("2000000000000000", "2e8653104f3834ea"),
+      ("1000000000000000", "4bd388ff6cd81d4f"),
+    ];
+
+    for (pt, ct) in vectors.iter() {
+      let encrypted = des.encrypt_block(&hex::decode(pt).unwrap());
+      assert_eq!(hex::encode(encrypted), *ct);
+    }
+  }
+
+  #[test]
+  fn encrypts_in_cbc_mode_per_fips_81() {
+    let key = hex::decode("0123456789abcdef").unwrap();
+    let iv = hex::decode("1234567890abcdef").unwrap();
+    let des = Des::new(&key).unwrap();
+    let plaintext = "Now is the time for all ".as_bytes();
+    let ciphertext = block_cipher::encrypt_cbc(&des, plaintext, &iv).unwrap();
+
+    // FIPS 81 doesn't pad, the trailing block is our pkcs7 padding
+    assert_eq!(
+      hex::encode(&ciphertext[..24]),
+      "e5c7cdde872bf27c43e934008c389c0f683788499a7c05f6"
+    );
+    assert_eq!(
+      block_cipher::decrypt_cbc(&des, &ciphertext, &iv).unwrap(),
+      plaintext
+    );
+  }
+
+  #[test]
+  fn encrypts_with_three_key_triple_des() {
+    // NIST SP 800-67 example
+    let key =
+      hex::decode("0123456789abcdef23456789abcdef01456789abcdef0123").unwrap();
+    let tdes = TripleDes::new(&key).unwrap();
+    let plaintext = "The qufck brown fox jump".as_bytes();
+    let ciphertext = block_cipher::encrypt_ecb(&tdes, plaintext);
+
+    assert_eq!(
+      hex::encode(&ciphertext[..24]),
+      "a826fd8ce53b855fcce21c8112256fe668d5c05dd9b6b900"
+    );
+    assert_eq!(
+      block_cipher::decrypt_ecb(&tdes, &ciphertext).unwrap(),
+      plaintext
+    );
+  }
+
+  #[test]
+  fn encrypts_with_two_key_triple_des() {
+    let key = hex::decode("0123456789abcdef23456789abcdef01").unwrap();
+    let tdes = TripleDes::new(&key).unwrap();
+    let plaintext = "Now is the time for all ".as_bytes();
+    let ciphertext = block_cipher::encrypt_ecb(&tdes, plaintext);
+
+    assert_eq!(
+      hex::encode(&ciphertext[..24]),
+      "b7835779ee26acb75d2731a8d9b401623dd3fc69a08cc6d9"
+    );
+  }
+
+  #[test]
+  fn triple_des_with_repeated_keys_is_single_des() {
+    let key = generate_key();
+    let des = Des::new(&key).unwrap();
+    let tdes =
+      TripleDes::new(&[&key[..], &key[..], &key[..]].concat()).unwrap();
+    let block = aes::generate_rand_bytes(8);
+
+    assert_eq!(des.encrypt_block(&block), tdes.encrypt_block(&block));
+  }
+
+  #[test]
+  fn round_trips_in_ctr_mode() {
+    let des = Des::new(&generate_key()).unwrap();
+    let nonce = aes::generate_rand_bytes(4);
+    let plaintext = "an odd length message for the keystream".as_bytes();
+    let ciphertext =
+      block_cipher::encrypt_ctr(&des, plaintext, &nonce).unwrap();
+
+    assert_eq!(ciphertext.len(), plaintext.len());
+    assert_eq!(
+      block_cipher::decrypt_ctr(&des, &ciphertext, &nonce).unwrap(),
+      plaintext
+    );
+  }
+
+  #[test]
+  fn rejects_wrong_size_keys() {
+    assert!(Des::new(&[0u8; 7]).is_err());
+    assert_eq!(
+      TripleDes::new(&[0u8; 8]).err(),
+      Some(CryptoError::UnsupportedKeyLength {
+        supported: &[16, 24],
+        actual: 8
+      })
+    );
+  }
+}
@@ -0,0 +1,161 @@
+extern crate base64;
+extern crate hex;
+
+use std::error;
+use std::fmt;
+use std::num;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CryptoError {
+  InvalidPadding,
+  InvalidKeyLength { expected: usize, actual: usize },
+  // for ciphers that take keys of more than one size
+  UnsupportedKeyLength { supported: &'static [usize], actual: usize },
+  InvalidIvLength { expected: usize, actual: usize },
+  // ciphertext that can't be split evenly into blocks
+  InvalidBlockLength { block_size: usize, actual: usize },
+  LengthMismatch { left: usize, right: usize },
+  // a position past the end of the bytes it indexes into
+  OffsetOutOfRange { offset: usize, len: usize },
+  Decode(String),
+  Parse(String),
+  AuthenticationFailed,
+  // more output than a key derivation function or keystream can produce
+  OutputTooLong { max: usize, actual: usize },
+  InvalidIterationCount,
+  // a toy hash's chaining state, in bytes
+  InvalidStateSize { max: usize, actual: usize },
+  // a truncated digest, in bytes, longer than the hash's state
+  InvalidDigestSize { max: usize, actual: usize },
+  // a GF(2^8) reduction polynomial that isn't irreducible of degree 8
+  InvalidPolynomial(u16),
+}
+
+impl fmt::Display for CryptoError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CryptoError::InvalidPadding => write!(f, "Invalid padding"),
+      CryptoError::InvalidKeyLength { expected, actual } => write!(
+        f,
+        "Wrong size key. Must be {} bytes, got {}.",
+        expected, actual
+      ),
+      CryptoError::UnsupportedKeyLength { supported, actual } => {
+        let sizes: Vec<String> =
+          supported.iter().map(|size| size.to_string()).collect();
+        write!(
+          f,
+          "Wrong size key. Must be {} bytes, got {}.",
+          sizes.join(" or "),
+          actual
+        )
+      }
+      CryptoError::InvalidIvLength { expected, actual } => write!(
+        f,
+        "Wrong size IV/nonce. Must be {} bytes, got {}.",
+        expected, actual
+      ),
+      CryptoError::InvalidBlockLength { block_size, actual } => write!(
+        f,
+        "Input of {} bytes is not a multiple of the {} byte block size",
+        actual, block_size
+      ),
+      CryptoError::LengthMismatch { left, right } => write!(
+        f,
+        "Byte arrays not the same length! ({} vs {})",
+        left, right
+      ),
+      CryptoError::OffsetOutOfRange { offset, len } => {
+        write!(f, "Offset {} is past the end of {} bytes", offset, len)
+      }
+      CryptoError::Decode(msg) => write!(f, "Decode error: {}", msg),
+      CryptoError::Parse(msg) => write!(f, "Parse error: {}", msg),
+      CryptoError::AuthenticationFailed => write!(f, "Authentication failed"),
+      CryptoError::OutputTooLong { max, actual } => write!(
+        f,
+        "Can't derive {} bytes, at most {}",
+        actual, max
+      ),
+      CryptoError::InvalidIterationCount => {
+        write!(f, "Iteration count must be at least 1")
+      }
+      CryptoError::InvalidStateSize { max, actual } => write!(
+        f,
+        "State of {} bytes, must be between 1 and {}",
+        actual, max
+      ),
+      CryptoError::InvalidDigestSize { max, actual } => write!(
+        f,
+        "Digest of {} bytes, must be between 1 and {}",
+        actual, max
+      ),
+      CryptoError::InvalidPolynomial(polynomial) => write!(
+        f,
+        "Reduction polynomial {:#x} is not irreducible of degree 8",
+        polynomial
+      ),
+    }
+  }
+}
+
+impl error::Error for CryptoError {}
+
+impl From<hex::FromHexError> for CryptoError {
+  fn from(err: hex::FromHexError) -> CryptoError {
+    CryptoError::Decode(err.to_string())
+  }
+}
+
+impl From<base64::DecodeError> for CryptoError {
+  fn from(err: base64::DecodeError) -> CryptoError {
+    CryptoError::Decode(err.to_string())
+  }
+}
+
+impl From<num::ParseIntError> for CryptoError {
+  fn from(err: num::ParseIntError) -> CryptoError {
+    CryptoError::Parse(err.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_converts_hex_errors_into_decode_errors() {
+    let err: CryptoError = hex::decode("zz").unwrap_err().into();
+
+    match err {
+      CryptoError::Decode(_) => (),
+      _ => panic!("expected a decode error, got {:?}", err),
+    }
+  }
+
+  #[test]
+  fn it_formats_errors_for_display() {
+    let err = CryptoError::InvalidKeyLength {
+      expected: 16,
+      actual: 11,
+    };
+
+    assert_eq!(err.to_string(), "Wrong size key. Must be 16 bytes, got 11.");
+    assert_eq!(CryptoError::InvalidPadding.to_string(), "Invalid padding");
+    assert_eq!(
+      CryptoError::UnsupportedKeyLength {
+        supported: &[16, 24],
+        actual: 10
+      }
+      .to_string(),
+      "Wrong size key. Must be 16 or 24 bytes, got 10."
+    );
+    assert_eq!(
+      CryptoError::InvalidDigestSize {
+        max: 32,
+        actual: 40
+      }
+      .to_string(),
+      "Digest of 40 bytes, must be between 1 and 32"
+    );
+  }
+}
@@ -0,0 +1,309 @@
+use crustopals::tools::CryptoError;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+// x^8 + x^4 + x^3 + x + 1, the polynomial AES reduces by
+pub const AES_POLYNOMIAL: u16 = 0x11b;
+
+// An element of GF(2^8): a byte read as a polynomial over GF(2), reduced
+// modulo `polynomial`. Elements only combine with elements of the same field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gf256 {
+  value: u8,
+  polynomial: u16,
+}
+
+impl Gf256 {
+  pub fn new(value: u8) -> Gf256 {
+    Gf256 {
+      value,
+      polynomial: AES_POLYNOMIAL,
+    }
+  }
+
+  // `polynomial` is the full degree 8 polynomial, e.g. 0x11d for the field
+  // Reed-Solomon codes usually use
+  pub fn with_polynomial(
+    value: u8,
+    polynomial: u16,
+  ) -> Result<Gf256, CryptoError> {
+    if polynomial >> 8 != 1 {
+      return Err(CryptoError::InvalidPolynomial(polynomial));
+    }
+    Ok(Gf256 { value, polynomial })
+  }
+
+  pub fn zero() -> Gf256 {
+    Gf256::new(0)
+  }
+
+  pub fn one() -> Gf256 {
+    Gf256::new(1)
+  }
+
+  pub fn value(self) -> u8 {
+    self.value
+  }
+
+  pub fn polynomial(self) -> u16 {
+    self.polynomial
+  }
+
+  pub fn pow(self, mut exponent: u32) -> Gf256 {
+    let mut base = self;
+    let mut result = self.with_value(1);
+    while exponent > 0 {
+      if exponent & 1 == 1 {
+        result = result * base;
+      }
+      base = base * base;
+      exponent >>= 1;
+    }
+    result
+  }
+
+  // a^254 == a^-1 when the polynomial is irreducible. Zero has no inverse,
+  // and neither do zero divisors of a reducible polynomial.
+  pub fn inverse(self) -> Option<Gf256> {
+    let candidate = self.pow(254);
+    if (self * candidate).value == 1 {
+      Some(candidate)
+    } else {
+      None
+    }
+  }
+
+  fn with_value(self, value: u8) -> Gf256 {
+    Gf256 {
+      value,
+      polynomial: self.polynomial,
+    }
+  }
+
+  fn xor(self, other: Gf256) -> Gf256 {
+    self.check_field(other);
+    self.with_value(self.value ^ other.value)
+  }
+
+  fn check_field(self, other: Gf256) {
+    assert_eq!(
+      self.polynomial, other.polynomial,
+      "elements of different fields"
+    );
+  }
+}
+
+// addition and subtraction are both xor in characteristic 2
+impl Add for Gf256 {
+  type Output = Gf256;
+
+  fn add(self, other: Gf256) -> Gf256 {
+    self.xor(other)
+  }
+}
+
+impl Sub for Gf256 {
+  type Output = Gf256;
+
+  fn sub(self, other: Gf256) -> Gf256 {
+    self.xor(other)
+  }
+}
+
+impl Mul for Gf256 {
+  type Output = Gf256;
+
+  // Russian peasant multiplication, reducing whenever x^8 shows up
+  fn mul(self, other: Gf256) -> Gf256 {
+    self.check_field(other);
+    let reduction = self.polynomial as u8;
+    let (mut a, mut b) = (self.value, other.value);
+    let mut product = 0u8;
+    while b != 0 {
+      if b & 1 == 1 {
+        product ^= a;
+      }
+      b >>= 1;
+      let carry = a & 0x80 != 0;
+      a <<= 1;
+      if carry {
+        a ^= reduction;
+      }
+    }
+    self.with_value(product)
+  }
+}
+
+impl fmt::Display for Gf256 {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:02x}", self.value)
+  }
+}
+
+// Discrete log tables over a generator of the field's multiplicative group,
+// turning a multiplication into two lookups and an addition mod 255.
+pub struct LogTables {
+  generator: Gf256,
+  log: [u8; 256],
+  antilog: [u8; 255],
+}
+
+impl LogTables {
+  // Uses the smallest element that generates all 255 non-zero elements.
+  // There isn't one if the polynomial is reducible.
+  pub fn new(polynomial: u16) -> Result<LogTables, CryptoError> {
+    let one = Gf256::with_polynomial(1, polynomial)?;
+    (2..=255u8)
+      .map(|candidate| one.with_value(candidate))
+      .filter_map(LogTables::with_generator)
+      .next()
+      .ok_or(CryptoError::InvalidPolynomial(polynomial))
+  }
+
+  pub fn aes() -> LogTables {
+    LogTables::new(AES_POLYNOMIAL).expect("the AES polynomial is irreducible")
+  }
+
+  fn with_generator(generator: Gf256) -> Option<LogTables> {
+    let mut log = [0u8; 256];
+    let mut antilog = [0u8; 255];
+    let mut seen = [false; 256];
+    let mut power = generator.with_value(1);
+    for (exponent, entry) in antilog.iter_mut().enumerate() {
+      // repeating early means a smaller subgroup (or, for a reducible
+      // polynomial, no group at all)
+      if seen[power.value as usize] || power.value == 0 {
+        return None;
+      }
+      seen[power.value as usize] = true;
+      *entry = power.value;
+      log[power.value as usize] = exponent as u8;
+      power = power * generator;
+    }
+    Some(LogTables {
+      generator,
+      log,
+      antilog,
+    })
+  }
+
+  pub fn generator(&self) -> Gf256 {
+    self.generator
+  }
+
+  pub fn log(&self, element: Gf256) -> Option<u8> {
+    if element.value == 0 {
+      None
+    } else {
+      Some(self.log[element.value as usize])
+    }
+  }
+
+  pub fn antilog(&self, exponent: u8) -> Gf256 {
+    self
+      .generator
+      .with_value(self.antilog[exponent as usize % 255])
+  }
+
+  pub fn mul(&self, a: Gf256, b: Gf256) -> Gf256 {
+    match (self.log(a), self.log(b)) {
+      (Some(log_a), Some(log_b)) => {
+        self.antilog(((log_a as usize + log_b as usize) % 255) as u8)
+      }
+      _ => self.generator.with_value(0),
+    }
+  }
+
+  pub fn inverse(&self, element: Gf256) -> Option<Gf256> {
+    self
+      .log(element)
+      .map(|log| self.antilog(((255 - log as usize) % 255) as u8))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_multiplies_like_fips_197() {
+    // the worked examples in FIPS 197 section 4.2
+    assert_eq!(Gf256::new(0x57) * Gf256::new(0x83), Gf256::new(0xc1));
+    assert_eq!(Gf256::new(0x57) * Gf256::new(0x13), Gf256::new(0xfe));
+    assert_eq!(Gf256::new(0x57) + Gf256::new(0x83), Gf256::new(0xd4));
+    assert_eq!(Gf256::new(0x57) - Gf256::new(0x83), Gf256::new(0xd4));
+  }
+
+  #[test]
+  fn every_non_zero_element_has_an_inverse() {
+    for value in 1..=255u8 {
+      let element = Gf256::new(value);
+      assert_eq!(element * element.inverse().unwrap(), Gf256::one());
+    }
+    assert_eq!(Gf256::zero().inverse(), None);
+    assert_eq!(Gf256::new(0x53).inverse(), Some(Gf256::new(0xca)));
+  }
+
+  #[test]
+  fn it_raises_to_powers() {
+    let element = Gf256::new(0x03);
+
+    assert_eq!(element.pow(0), Gf256::one());
+    assert_eq!(element.pow(2), element * element);
+    // the multiplicative group has order 255
+    assert_eq!(element.pow(255), Gf256::one());
+  }
+
+  #[test]
+  fn log_tables_agree_with_direct_multiplication() {
+    let tables = LogTables::aes();
+
+    assert_eq!(tables.generator(), Gf256::new(0x03));
+    for a in 0..=255u8 {
+      for b in 0..=255u8 {
+        let (a, b) = (Gf256::new(a), Gf256::new(b));
+        assert_eq!(tables.mul(a, b), a * b);
+      }
+      assert_eq!(tables.inverse(Gf256::new(a)), Gf256::new(a).inverse());
+    }
+  }
+
+  #[test]
+  fn it_supports_other_reduction_polynomials() {
+    let tables = LogTables::new(0x11d).unwrap();
+    let two = Gf256::with_polynomial(2, 0x11d).unwrap();
+
+    // 2 generates the field for 0x11d but not for AES's polynomial
+    assert_eq!(tables.generator(), two);
+    assert_eq!(two.pow(8), Gf256::with_polynomial(0x1d, 0x11d).unwrap());
+    assert_eq!(Gf256::new(2).pow(51), Gf256::one());
+  }
+
+  #[test]
+  fn reducible_polynomials_have_no_log_tables() {
+    // x^8 + 1 == (x + 1)^8
+    assert_eq!(
+      LogTables::new(0x101).err(),
+      Some(CryptoError::InvalidPolynomial(0x101))
+    );
+    assert_eq!(Gf256::with_polynomial(0x03, 0x101).unwrap().inverse(), None);
+  }
+
+  #[test]
+  fn it_rejects_polynomials_not_of_degree_8() {
+    assert_eq!(
+      Gf256::with_polynomial(1, 0x1b),
+      Err(CryptoError::InvalidPolynomial(0x1b))
+    );
+    assert_eq!(
+      LogTables::new(0x21b).err(),
+      Some(CryptoError::InvalidPolynomial(0x21b))
+    );
+  }
+
+  #[test]
+  #[should_panic]
+  fn elements_of_different_fields_do_not_mix() {
+    let _ = Gf256::new(2) * Gf256::with_polynomial(2, 0x11d).unwrap();
+  }
+}
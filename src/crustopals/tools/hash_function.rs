@@ -0,0 +1,181 @@
+use crustopals::tools::md4::{self, Md4};
+use crustopals::tools::md5::{self, Md5};
+use crustopals::tools::sha1::{self, Sha1};
+use crustopals::tools::sha2::{self, Sha256, Sha512};
+use crustopals::tools::sha3::Sha3;
+
+// An incremental hash, for code that works over any of them (HMAC, the
+// generic attacks). Constructed by `new` it is the hash's standard variant,
+// so SHA-256 and SHA-512 rather than their truncated siblings.
+pub trait HashFunction: Clone {
+  // bytes per compression function call, which HMAC pads keys to
+  const BLOCK_SIZE: usize;
+  const DIGEST_SIZE: usize;
+
+  fn new() -> Self;
+  fn update(&mut self, bytes: &[u8]);
+  fn digest(&self) -> Vec<u8>;
+
+  fn hash(bytes: &[u8]) -> Vec<u8> {
+    let mut hasher = Self::new();
+    hasher.update(bytes);
+    hasher.digest()
+  }
+}
+
+impl HashFunction for Md4 {
+  const BLOCK_SIZE: usize = md4::BLOCK_SIZE;
+  const DIGEST_SIZE: usize = md4::DIGEST_SIZE;
+
+  fn new() -> Md4 {
+    Md4::new()
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    Md4::update(self, bytes)
+  }
+
+  fn digest(&self) -> Vec<u8> {
+    Md4::digest(self)
+  }
+}
+
+impl HashFunction for Md5 {
+  const BLOCK_SIZE: usize = md5::BLOCK_SIZE;
+  const DIGEST_SIZE: usize = md5::DIGEST_SIZE;
+
+  fn new() -> Md5 {
+    Md5::new()
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    Md5::update(self, bytes)
+  }
+
+  fn digest(&self) -> Vec<u8> {
+    Md5::digest(self)
+  }
+}
+
+impl HashFunction for Sha1 {
+  const BLOCK_SIZE: usize = sha1::BLOCK_SIZE;
+  const DIGEST_SIZE: usize = sha1::DIGEST_SIZE;
+
+  fn new() -> Sha1 {
+    Sha1::new()
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    Sha1::update(self, bytes)
+  }
+
+  fn digest(&self) -> Vec<u8> {
+    Sha1::digest(self)
+  }
+}
+
+impl HashFunction for Sha256 {
+  const BLOCK_SIZE: usize = sha2::SHA256_BLOCK_SIZE;
+  const DIGEST_SIZE: usize = 32;
+
+  fn new() -> Sha256 {
+    Sha256::new()
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    Sha256::update(self, bytes)
+  }
+
+  fn digest(&self) -> Vec<u8> {
+    Sha256::digest(self)
+  }
+}
+
+impl HashFunction for Sha512 {
+  const BLOCK_SIZE: usize = sha2::SHA512_BLOCK_SIZE;
+  const DIGEST_SIZE: usize = 64;
+
+  fn new() -> Sha512 {
+    Sha512::new()
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    Sha512::update(self, bytes)
+  }
+
+  fn digest(&self) -> Vec<u8> {
+    Sha512::digest(self)
+  }
+}
+
+// HMAC pads keys to the rate, as the sponge takes its input a rate at a
+// time.
+impl HashFunction for Sha3 {
+  const BLOCK_SIZE: usize = 136;
+  const DIGEST_SIZE: usize = 32;
+
+  fn new() -> Sha3 {
+    Sha3::new()
+  }
+
+  fn update(&mut self, bytes: &[u8]) {
+    Sha3::update(self, bytes)
+  }
+
+  fn digest(&self) -> Vec<u8> {
+    Sha3::digest(self)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::CryptoError;
+
+  fn check_incremental<H: HashFunction>() {
+    let message: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let mut hasher = H::new();
+    for chunk in message.chunks(37) {
+      hasher.update(chunk);
+    }
+
+    assert_eq!(hasher.digest(), H::hash(&message));
+    // digesting doesn't consume the hasher
+    hasher.update(b"more");
+    assert_eq!(hasher.digest(), H::hash(&[&message[..], b"more"].concat()));
+  }
+
+  fn check_state_round_trip<H: HashFunction, S>(
+    state: fn(&H) -> S,
+    from_state: fn(S) -> Result<H, CryptoError>,
+  ) {
+    let mut hasher = H::new();
+    hasher.update(&[7u8; 150]);
+    let mut resumed = from_state(state(&hasher)).unwrap();
+    resumed.update(b"the rest");
+
+    assert_eq!(
+      resumed.digest(),
+      H::hash(&[&[7u8; 150][..], b"the rest"].concat())
+    );
+  }
+
+  #[test]
+  fn it_hashes_incrementally() {
+    check_incremental::<Md4>();
+    check_incremental::<Md5>();
+    check_incremental::<Sha1>();
+    check_incremental::<Sha256>();
+    check_incremental::<Sha512>();
+    check_incremental::<Sha3>();
+  }
+
+  #[test]
+  fn it_exports_and_imports_state() {
+    check_state_round_trip(Md4::state, Md4::from_state);
+    check_state_round_trip(Md5::state, Md5::from_state);
+    check_state_round_trip(Sha1::state, Sha1::from_state);
+    check_state_round_trip(Sha256::state, Sha256::from_state);
+    check_state_round_trip(Sha512::state, Sha512::from_state);
+  }
+}
@@ -0,0 +1,225 @@
+use crustopals::byteorder::{BigEndian, ByteOrder};
+use crustopals::tools::authentication::Hmac;
+use crustopals::tools::hash_function::HashFunction;
+use crustopals::tools::CryptoError;
+
+// Key derivation over `authentication::Hmac`. HKDF (RFC 5869) is for
+// secrets that already have plenty of entropy, a DH shared secret say, and
+// PBKDF2 (RFC 8018) for passwords, where the iteration count is there to
+// make every guess expensive.
+
+// HKDF's first step: condenses the input keying material into a
+// pseudorandom key of one digest. No salt means a digest of zeros.
+pub fn hkdf_extract<H: HashFunction>(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+  let zeros = vec![0u8; H::DIGEST_SIZE];
+  let salt = if salt.is_empty() { &zeros[..] } else { salt };
+  let mut hmac = Hmac::<H>::new(salt);
+  hmac.update(ikm);
+  hmac.digest()
+}
+
+// HKDF's second step: T(i) = HMAC(prk, T(i - 1) || info || i), concatenated
+// until there are `length` bytes. The one byte counter caps the output at
+// 255 digests.
+pub fn hkdf_expand<H: HashFunction>(
+  prk: &[u8],
+  info: &[u8],
+  length: usize,
+) -> Result<Vec<u8>, CryptoError> {
+  let max = 255 * H::DIGEST_SIZE;
+  if length > max {
+    return Err(CryptoError::OutputTooLong {
+      max,
+      actual: length,
+    });
+  }
+  let keyed = Hmac::<H>::new(prk);
+  let mut okm = vec![];
+  let mut block = vec![];
+  let mut counter = 1u8;
+  while okm.len() < length {
+    let mut hmac = keyed.clone();
+    hmac.update(&block);
+    hmac.update(info);
+    hmac.update(&[counter]);
+    block = hmac.digest();
+    okm.extend_from_slice(&block);
+    counter = counter.wrapping_add(1);
+  }
+  okm.truncate(length);
+  Ok(okm)
+}
+
+pub fn hkdf<H: HashFunction>(
+  salt: &[u8],
+  ikm: &[u8],
+  info: &[u8],
+  length: usize,
+) -> Result<Vec<u8>, CryptoError> {
+  hkdf_expand::<H>(&hkdf_extract::<H>(salt, ikm), info, length)
+}
+
+// Each digest sized block of output is U1 ^ U2 ^ ... ^ Uc, where
+// U1 = HMAC(password, salt || block number) and every later U is the HMAC
+// of the one before.
+pub fn pbkdf2<H: HashFunction>(
+  password: &[u8],
+  salt: &[u8],
+  iterations: u32,
+  length: usize,
+) -> Result<Vec<u8>, CryptoError> {
+  if iterations == 0 {
+    return Err(CryptoError::InvalidIterationCount);
+  }
+  let keyed = Hmac::<H>::new(password);
+  let mut derived = vec![];
+  let mut block_number = 1u32;
+  while derived.len() < length {
+    let mut block_index = [0u8; 4];
+    BigEndian::write_u32(&mut block_index, block_number);
+    let mut hmac = keyed.clone();
+    hmac.update(salt);
+    hmac.update(&block_index);
+    let mut u = hmac.digest();
+    let mut block = u.clone();
+    for _ in 1..iterations {
+      let mut hmac = keyed.clone();
+      hmac.update(&u);
+      u = hmac.digest();
+      for (b, x) in block.iter_mut().zip(u.iter()) {
+        *b ^= x;
+      }
+    }
+    derived.extend(block);
+    block_number += 1;
+  }
+  derived.truncate(length);
+  Ok(derived)
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+  use crustopals::tools::sha1::Sha1;
+  use crustopals::tools::sha2::Sha256;
+
+  #[test]
+  fn it_derives_the_rfc_5869_keys() {
+    let salt: Vec<u8> = (0..13).collect();
+    let info: Vec<u8> = (0xf0..0xfa).collect();
+    let ikm = [0x0b; 22];
+
+    assert_eq!(
+      hex::encode(hkdf_extract::<Sha256>(&salt, &ikm)),
+      "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+    );
+    assert_eq!(
+      hex::encode(hkdf::<Sha256>(&salt, &ikm, &info, 42).unwrap()),
+      "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+       34007208d5b887185865"
+    );
+
+    let long_salt: Vec<u8> = (0x60..0xb0).collect();
+    let long_info: Vec<u8> = (0xb0..=0xff).collect();
+    let long_ikm: Vec<u8> = (0..0x50).collect();
+
+    assert_eq!(
+      hex::encode(
+        hkdf::<Sha256>(&long_salt, &long_ikm, &long_info, 82).unwrap()
+      ),
+      "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c\
+       59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71\
+       cc30c58179ec3e87c14c01d5c1f3434f1d87"
+    );
+
+    assert_eq!(
+      hex::encode(hkdf_extract::<Sha256>(b"", &ikm)),
+      "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04"
+    );
+    assert_eq!(
+      hex::encode(hkdf::<Sha256>(b"", &ikm, b"", 42).unwrap()),
+      "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d\
+       9d201395faa4b61a96c8"
+    );
+
+    assert_eq!(
+      hex::encode(hkdf::<Sha1>(&salt, &ikm[..11], &info, 42).unwrap()),
+      "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2\
+       c22e422478d305f3f896"
+    );
+  }
+
+  #[test]
+  fn hkdf_output_is_capped_at_255_digests() {
+    let prk = hkdf_extract::<Sha1>(b"salt", b"secret");
+
+    assert_eq!(
+      hkdf_expand::<Sha1>(&prk, b"", 255 * 20).unwrap().len(),
+      5100
+    );
+    assert_eq!(
+      hkdf_expand::<Sha1>(&prk, b"", 5101),
+      Err(CryptoError::OutputTooLong {
+        max: 5100,
+        actual: 5101
+      })
+    );
+  }
+
+  #[test]
+  fn it_derives_the_rfc_6070_keys() {
+    let examples: [(&[u8], &[u8], u32, &str); 5] = [
+      (
+        b"password",
+        b"salt",
+        1,
+        "0c60c80f961f0e71f3a9b524af6012062fe037a6",
+      ),
+      (
+        b"password",
+        b"salt",
+        2,
+        "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957",
+      ),
+      (
+        b"password",
+        b"salt",
+        4096,
+        "4b007901b765489abead49d926f721d065a429c1",
+      ),
+      (
+        b"passwordPASSWORDpassword",
+        b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+        4096,
+        "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038",
+      ),
+      (
+        b"pass\0word",
+        b"sa\0lt",
+        4096,
+        "56fa6aa75548099dcc37d7f03425e0c3",
+      ),
+    ];
+    for (password, salt, iterations, key) in examples.iter() {
+      let derived =
+        pbkdf2::<Sha1>(password, salt, *iterations, key.len() / 2).unwrap();
+
+      assert_eq!(hex::encode(derived), *key);
+    }
+  }
+
+  #[test]
+  fn it_derives_the_rfc_7914_pbkdf2_sha256_key() {
+    assert_eq!(
+      hex::encode(pbkdf2::<Sha256>(b"passwd", b"salt", 1, 64).unwrap()),
+      "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+       49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+    );
+    assert_eq!(
+      pbkdf2::<Sha256>(b"passwd", b"salt", 0, 64),
+      Err(CryptoError::InvalidIterationCount)
+    );
+  }
+}
@@ -0,0 +1,168 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::merkle_damgard::{BlockBuffer, MerkleDamgard};
+use crustopals::tools::CryptoError;
+
+// MD4 (RFC 1320), with the same state export/import as `sha1::Sha1`. It is
+// the same Merkle-Damgard construction as SHA-1, only little endian, with
+// three rounds of sixteen steps over four words.
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 16;
+
+// The round internals are public for the collision attack in `problem55`,
+// which steers individual steps.
+pub const INITIAL_WORDS: [u32; 4] =
+  [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+// message word order, rotations and additive constant for each round
+pub const ORDER: [[usize; 16]; 3] = [
+  [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+  [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+  [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+];
+pub const SHIFTS: [[u32; 4]; 3] =
+  [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+pub const ROUND_CONSTANTS: [u32; 3] = [0, 0x5a827999, 0x6ed9eba1];
+
+pub type Md4State = BlockBuffer<[u32; 4]>;
+
+#[derive(Clone)]
+pub struct Md4 {
+  state: Md4State,
+}
+
+impl Md4 {
+  pub fn new() -> Md4 {
+    Md4 {
+      state: BlockBuffer::new(INITIAL_WORDS),
+    }
+  }
+
+  pub fn from_state(state: Md4State) -> Result<Md4, CryptoError> {
+    state.check::<Md4>()?;
+    Ok(Md4 { state })
+  }
+
+  pub fn state(&self) -> Md4State {
+    self.state.clone()
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    self.state.update::<Md4>(bytes, compress);
+  }
+
+  pub fn digest(&self) -> Vec<u8> {
+    let mut digest = vec![0u8; DIGEST_SIZE];
+    LittleEndian::write_u32_into(
+      &self.state.finish::<Md4>(compress),
+      &mut digest,
+    );
+    digest
+  }
+}
+
+impl Default for Md4 {
+  fn default() -> Md4 {
+    Md4::new()
+  }
+}
+
+pub fn md4(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Md4::new();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+// As `sha1::padding`, but with the bit length little endian. MD5 pads the
+// same way.
+pub fn padding(message_len: u64) -> Vec<u8> {
+  <Md4 as MerkleDamgard>::padding(message_len)
+}
+
+pub fn state_from_digest(
+  digest: &[u8],
+  length: u64,
+) -> Result<Md4State, CryptoError> {
+  if digest.len() != DIGEST_SIZE {
+    return Err(CryptoError::LengthMismatch {
+      left: DIGEST_SIZE,
+      right: digest.len(),
+    });
+  }
+  let mut words = [0u32; 4];
+  LittleEndian::read_u32_into(digest, &mut words);
+  Ok(Md4State {
+    words,
+    length,
+    buffer: vec![],
+  })
+}
+
+// the three rounds' boolean functions: a bitwise choice, majority and
+// parity
+pub fn f(x: u32, y: u32, z: u32) -> u32 {
+  (x & y) | (!x & z)
+}
+
+pub fn g(x: u32, y: u32, z: u32) -> u32 {
+  (x & y) | (x & z) | (y & z)
+}
+
+pub fn h(x: u32, y: u32, z: u32) -> u32 {
+  x ^ y ^ z
+}
+
+pub fn compress(words: &mut [u32; 4], block: &[u8]) {
+  let mut x = [0u32; 16];
+  LittleEndian::read_u32_into(block, &mut x);
+
+  // [a, b, c, d], rotated after every step so the word being updated is
+  // always first
+  let mut state = *words;
+  for round in 0..3 {
+    let function = [f, g, h][round];
+    for step in 0..16 {
+      let updated = state[0]
+        .wrapping_add(function(state[1], state[2], state[3]))
+        .wrapping_add(x[ORDER[round][step]])
+        .wrapping_add(ROUND_CONSTANTS[round])
+        .rotate_left(SHIFTS[round][step % 4]);
+      state = [state[3], updated, state[1], state[2]];
+    }
+  }
+  for (word, new) in words.iter_mut().zip(state.iter()) {
+    *word = word.wrapping_add(*new);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  #[test]
+  fn it_hashes_the_rfc_1320_examples() {
+    let examples = [
+      ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+      ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+      ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+      ("message digest", "d9130a8164549fe818874806e1c7014b"),
+      (
+        "abcdefghijklmnopqrstuvwxyz",
+        "d79e1c308aa5bbcdeea8ed63df412da9",
+      ),
+      (
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+        "043f8582f241db351ce627e153e7f0e4",
+      ),
+      (
+        "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+        "e33b4ddc9c38f2199c3e7b164fcc0536",
+      ),
+    ];
+    for (message, digest) in examples.iter() {
+      assert_eq!(hex::encode(md4(message.as_bytes())), *digest);
+    }
+  }
+}
@@ -0,0 +1,146 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::merkle_damgard::BlockBuffer;
+use crustopals::tools::CryptoError;
+
+// MD4's padding and state, so a digest resumes the same way.
+pub use crustopals::tools::md4::{
+  padding, state_from_digest, Md4State as Md5State,
+};
+
+// MD5 (RFC 1321), with the same state export/import as `md4::Md4`: four
+// rounds of sixteen steps, each with its own additive constant, and the
+// previous step's result fed into every step.
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 16;
+
+const INITIAL_WORDS: [u32; 4] =
+  [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+// floor(2^32 * |sin(i + 1)|)
+const K: [u32; 64] = [
+  0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a,
+  0xa8304613, 0xfd469501, 0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be,
+  0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340,
+  0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+  0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8,
+  0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c,
+  0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa,
+  0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+  0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92,
+  0xffeff47d, 0x85845dd1, 0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1,
+  0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+const SHIFTS: [[u32; 4]; 4] = [
+  [7, 12, 17, 22],
+  [5, 9, 14, 20],
+  [4, 11, 16, 23],
+  [6, 10, 15, 21],
+];
+
+#[derive(Clone)]
+pub struct Md5 {
+  state: Md5State,
+}
+
+impl Md5 {
+  pub fn new() -> Md5 {
+    Md5 {
+      state: BlockBuffer::new(INITIAL_WORDS),
+    }
+  }
+
+  pub fn from_state(state: Md5State) -> Result<Md5, CryptoError> {
+    state.check::<Md5>()?;
+    Ok(Md5 { state })
+  }
+
+  pub fn state(&self) -> Md5State {
+    self.state.clone()
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    self.state.update::<Md5>(bytes, compress);
+  }
+
+  pub fn digest(&self) -> Vec<u8> {
+    let mut digest = vec![0u8; DIGEST_SIZE];
+    LittleEndian::write_u32_into(
+      &self.state.finish::<Md5>(compress),
+      &mut digest,
+    );
+    digest
+  }
+}
+
+impl Default for Md5 {
+  fn default() -> Md5 {
+    Md5::new()
+  }
+}
+
+pub fn md5(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Md5::new();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+fn compress(words: &mut [u32; 4], block: &[u8]) {
+  let mut x = [0u32; 16];
+  LittleEndian::read_u32_into(block, &mut x);
+
+  // [a, b, c, d], rotated as in `md4::compress`
+  let mut h = *words;
+  for i in 0..64 {
+    let (b, c, d) = (h[1], h[2], h[3]);
+    let (f, g) = match i / 16 {
+      0 => ((b & c) | (!b & d), i),
+      1 => ((b & d) | (c & !d), (5 * i + 1) % 16),
+      2 => (b ^ c ^ d, (3 * i + 5) % 16),
+      _ => (c ^ (b | !d), (7 * i) % 16),
+    };
+    let updated = b.wrapping_add(
+      h[0]
+        .wrapping_add(f)
+        .wrapping_add(K[i])
+        .wrapping_add(x[g])
+        .rotate_left(SHIFTS[i / 16][i % 4]),
+    );
+    h = [h[3], updated, h[1], h[2]];
+  }
+  for (word, new) in words.iter_mut().zip(h.iter()) {
+    *word = word.wrapping_add(*new);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  #[test]
+  fn it_hashes_the_rfc_1321_examples() {
+    let examples = [
+      ("", "d41d8cd98f00b204e9800998ecf8427e"),
+      ("a", "0cc175b9c0f1b6a831c399e269772661"),
+      ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+      ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+      (
+        "abcdefghijklmnopqrstuvwxyz",
+        "c3fcd3d76192e4007dfb496cca67e13b",
+      ),
+      (
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+        "d174ab98d277d9f5a5611c2c9f419d9f",
+      ),
+      (
+        "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+        "57edf4a22be3c955ac49da2e2107b67a",
+      ),
+    ];
+    for (message, digest) in examples.iter() {
+      assert_eq!(hex::encode(md5(message.as_bytes())), *digest);
+    }
+  }
+}
@@ -0,0 +1,370 @@
+use crustopals::byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crustopals::tools::hash_function::HashFunction;
+use crustopals::tools::md4::{self, Md4};
+use crustopals::tools::md5::{self, Md5};
+use crustopals::tools::sha1::{self, Sha1};
+use crustopals::tools::sha2::{self, Sha256, Sha512};
+use crustopals::tools::CryptoError;
+
+// A Merkle-Damgard hash: blocks fed through a compression function, after
+// padding with a 1 bit, zeros and the message length in bits. When the
+// digest is the whole chaining state, anyone holding H(m) can carry on
+// hashing from it without knowing m, only its length.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endianness {
+  Big,
+  Little,
+}
+
+pub trait MerkleDamgard: HashFunction {
+  // bytes taken by the bit length at the end of the padding
+  const LENGTH_SIZE: usize;
+  const ENDIANNESS: Endianness;
+
+  // A hasher that has processed `length` bytes and ended up at `digest`.
+  fn resume(digest: &[u8], length: u64) -> Result<Self, CryptoError>;
+
+  fn padding(message_len: u64) -> Vec<u8> {
+    strengthening(
+      message_len,
+      Self::BLOCK_SIZE,
+      Self::LENGTH_SIZE,
+      Self::ENDIANNESS,
+    )
+  }
+}
+
+// The padding itself, for hashes that aren't a `MerkleDamgard` but pad the
+// same way: a 1 bit, zeros, then the message length in bits in the last
+// `length_size` bytes of a block.
+pub fn strengthening(
+  message_len: u64,
+  block_size: usize,
+  length_size: usize,
+  endianness: Endianness,
+) -> Vec<u8> {
+  let zeros =
+    (block_size * 2 - 1 - length_size - message_len as usize % block_size)
+      % block_size;
+  let mut padding = vec![0x80];
+  padding.extend(vec![0u8; zeros]);
+  // lengths past 2^64 bits don't fit a u64 of bytes anyway
+  let mut bit_length = vec![0u8; length_size];
+  let high = message_len >> 61;
+  let low = message_len.wrapping_mul(8);
+  match endianness {
+    Endianness::Big => {
+      BigEndian::write_u64(&mut bit_length[length_size - 8..], low);
+      if length_size >= 16 {
+        BigEndian::write_u64(&mut bit_length[length_size - 16..], high);
+      }
+    }
+    Endianness::Little => {
+      LittleEndian::write_u64(&mut bit_length[..8], low);
+      if length_size >= 16 {
+        LittleEndian::write_u64(&mut bit_length[8..16], high);
+      }
+    }
+  }
+  padding.extend(bit_length);
+  padding
+}
+
+// What each of these hashes keeps between updates: the chaining words, how
+// many bytes went through the compression function and any bytes still
+// waiting for a full block. It is also the state they export and import.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockBuffer<W> {
+  pub words: W,
+  pub length: u64,
+  pub buffer: Vec<u8>,
+}
+
+impl<W: Copy> BlockBuffer<W> {
+  pub fn new(words: W) -> BlockBuffer<W> {
+    BlockBuffer {
+      words,
+      length: 0,
+      buffer: vec![],
+    }
+  }
+
+  // Only whole blocks go through the compression function, so anything
+  // else isn't a state `H` can be in.
+  pub fn check<H: MerkleDamgard>(&self) -> Result<(), CryptoError> {
+    if !self.length.is_multiple_of(H::BLOCK_SIZE as u64)
+      || self.buffer.len() >= H::BLOCK_SIZE
+    {
+      return Err(CryptoError::InvalidBlockLength {
+        block_size: H::BLOCK_SIZE,
+        actual: self.length as usize + self.buffer.len(),
+      });
+    }
+    Ok(())
+  }
+
+  pub fn update<H: MerkleDamgard>(
+    &mut self,
+    bytes: &[u8],
+    compress: fn(&mut W, &[u8]),
+  ) {
+    self.buffer.extend_from_slice(bytes);
+    let full_blocks = self.buffer.len() / H::BLOCK_SIZE * H::BLOCK_SIZE;
+    for block in self.buffer[..full_blocks].chunks(H::BLOCK_SIZE) {
+      compress(&mut self.words, block);
+    }
+    self.length += full_blocks as u64;
+    self.buffer.drain(..full_blocks);
+  }
+
+  // The words after the buffer and `H`'s padding, leaving the buffer as it
+  // is so hashing can carry on.
+  pub fn finish<H: MerkleDamgard>(&self, compress: fn(&mut W, &[u8])) -> W {
+    let mut words = self.words;
+    let total = self.length + self.buffer.len() as u64;
+    let tail = [&self.buffer[..], &H::padding(total)[..]].concat();
+    for block in tail.chunks(H::BLOCK_SIZE) {
+      compress(&mut words, block);
+    }
+    words
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension {
+  // what the hash appended to the original message, which has to go
+  // between it and the suffix
+  pub glue_padding: Vec<u8>,
+  // H(original || glue_padding || suffix)
+  pub digest: Vec<u8>,
+}
+
+// Extends a digest of an `original_len` byte message by `suffix`.
+pub fn length_extend<H: MerkleDamgard>(
+  digest: &[u8],
+  original_len: u64,
+  suffix: &[u8],
+) -> Result<Extension, CryptoError> {
+  let glue_padding = H::padding(original_len);
+  let mut hasher = H::resume(digest, original_len + glue_padding.len() as u64)?;
+  hasher.update(suffix);
+  Ok(Extension {
+    glue_padding,
+    digest: hasher.digest(),
+  })
+}
+
+// From the MAC H(secret || msg) under a `secret_len` byte secret, a valid
+// MAC for msg || glue padding || suffix, and that message.
+pub fn extend_secret_prefix_mac<H: MerkleDamgard>(
+  mac: &[u8],
+  secret_len: usize,
+  msg: &[u8],
+  suffix: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+  let extension =
+    length_extend::<H>(mac, (secret_len + msg.len()) as u64, suffix)?;
+  let forged_msg = [msg, &extension.glue_padding[..], suffix].concat();
+  Ok((extension.digest, forged_msg))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forgery {
+  pub message: Vec<u8>,
+  pub mac: Vec<u8>,
+  pub secret_len: usize,
+  // oracle calls made, the successful one included
+  pub queries: usize,
+}
+
+// `extend_secret_prefix_mac` when the secret's length isn't known: each
+// length in `secret_lens` gives a different glue padding, so the forgery
+// for each is put to `oracle` (message, MAC -> valid?) until one is
+// accepted. None when no length works; a MAC that isn't a digest of `H` is
+// an error.
+pub fn forge_with_oracle<H, I, F>(
+  mac: &[u8],
+  msg: &[u8],
+  suffix: &[u8],
+  secret_lens: I,
+  mut oracle: F,
+) -> Result<Option<Forgery>, CryptoError>
+where
+  H: MerkleDamgard,
+  I: IntoIterator<Item = usize>,
+  F: FnMut(&[u8], &[u8]) -> bool,
+{
+  for (tried, secret_len) in secret_lens.into_iter().enumerate() {
+    let (forged_mac, forged_msg) =
+      extend_secret_prefix_mac::<H>(mac, secret_len, msg, suffix)?;
+    if oracle(&forged_msg, &forged_mac) {
+      return Ok(Some(Forgery {
+        message: forged_msg,
+        mac: forged_mac,
+        secret_len,
+        queries: tried + 1,
+      }));
+    }
+  }
+  Ok(None)
+}
+
+impl MerkleDamgard for Md4 {
+  const LENGTH_SIZE: usize = 8;
+  const ENDIANNESS: Endianness = Endianness::Little;
+
+  fn resume(digest: &[u8], length: u64) -> Result<Md4, CryptoError> {
+    Md4::from_state(md4::state_from_digest(digest, length)?)
+  }
+}
+
+impl MerkleDamgard for Md5 {
+  const LENGTH_SIZE: usize = 8;
+  const ENDIANNESS: Endianness = Endianness::Little;
+
+  fn resume(digest: &[u8], length: u64) -> Result<Md5, CryptoError> {
+    Md5::from_state(md5::state_from_digest(digest, length)?)
+  }
+}
+
+impl MerkleDamgard for Sha1 {
+  const LENGTH_SIZE: usize = 8;
+  const ENDIANNESS: Endianness = Endianness::Big;
+
+  fn resume(digest: &[u8], length: u64) -> Result<Sha1, CryptoError> {
+    Sha1::from_state(sha1::state_from_digest(digest, length)?)
+  }
+}
+
+// Resuming only works from full width digests, so SHA-224 can't be
+// extended through this.
+impl MerkleDamgard for Sha256 {
+  const LENGTH_SIZE: usize = 8;
+  const ENDIANNESS: Endianness = Endianness::Big;
+
+  fn resume(digest: &[u8], length: u64) -> Result<Sha256, CryptoError> {
+    Sha256::from_state(sha2::sha256_state_from_digest(digest, length)?)
+  }
+}
+
+// Likewise only SHA-512 itself, not SHA-384 or SHA-512/256.
+impl MerkleDamgard for Sha512 {
+  const LENGTH_SIZE: usize = 16;
+  const ENDIANNESS: Endianness = Endianness::Big;
+
+  fn resume(digest: &[u8], length: u64) -> Result<Sha512, CryptoError> {
+    Sha512::from_state(sha2::sha512_state_from_digest(digest, length)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::aes;
+  use crustopals::tools::md5::md5;
+  use crustopals::tools::sha1::sha1;
+  use crustopals::tools::sha2::sha384;
+
+  fn check_extension<H: MerkleDamgard>() {
+    for len in [0, 13, 55, 56, 64, 111, 112, 200].iter() {
+      let original = aes::generate_rand_bytes(*len);
+      let extension =
+        length_extend::<H>(&H::hash(&original), *len as u64, b";admin=true")
+          .unwrap();
+      let forged =
+        [&original[..], &extension.glue_padding[..], b";admin=true"].concat();
+
+      assert_eq!(extension.digest, H::hash(&forged));
+    }
+    assert!(H::resume(&vec![0u8; H::DIGEST_SIZE + 1], 64).is_err());
+  }
+
+  #[test]
+  fn it_extends_every_full_width_hash() {
+    check_extension::<Md4>();
+    check_extension::<Md5>();
+    check_extension::<Sha1>();
+    check_extension::<Sha256>();
+    check_extension::<Sha512>();
+  }
+
+  #[test]
+  fn it_buffers_whatever_is_short_of_a_block() {
+    let mut buffered = BlockBuffer::new([0u32; 5]);
+    buffered.update::<Sha1>(&[7u8; 150], |words, _| words[0] += 1);
+
+    assert_eq!(buffered.words[0], 2);
+    assert_eq!(buffered.length, 128);
+    assert_eq!(buffered.buffer.len(), 22);
+    assert!(buffered.check::<Sha1>().is_ok());
+  }
+
+  #[test]
+  fn it_pads_with_the_length_in_each_hashs_byte_order() {
+    let mut big = vec![0x80];
+    big.extend(vec![0u8; 12]);
+    big.extend(&[0, 0, 0, 0, 0, 0, 1, 0x58]);
+    let mut little = vec![0x80];
+    little.extend(vec![0u8; 12]);
+    little.extend(&[0x58, 1, 0, 0, 0, 0, 0, 0]);
+    let mut wide = vec![0x80];
+    wide.extend(vec![0u8; 68]);
+    wide.extend(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0x58]);
+
+    assert_eq!(Sha1::padding(43), big);
+    assert_eq!(Sha256::padding(43), big);
+    assert_eq!(Md4::padding(43), little);
+    assert_eq!(Md5::padding(43), little);
+    assert_eq!(Sha512::padding(43), wide);
+    for len in 0..300u64 {
+      assert_eq!((len + Sha1::padding(len).len() as u64) % 64, 0);
+      assert_eq!((len + Sha512::padding(len).len() as u64) % 128, 0);
+    }
+  }
+
+  #[test]
+  fn it_extends_secret_prefix_macs() {
+    let secret = aes::generate_rand_bytes(21);
+    let msg = b"user=alice;role=guest";
+    let mac = sha1(&[&secret[..], &msg[..]].concat());
+    let (forged_mac, forged_msg) =
+      extend_secret_prefix_mac::<Sha1>(&mac, 21, msg, b";role=admin").unwrap();
+
+    assert!(forged_msg.starts_with(msg));
+    assert!(forged_msg.ends_with(b";role=admin"));
+    assert_eq!(forged_mac, sha1(&[&secret[..], &forged_msg[..]].concat()));
+  }
+
+  #[test]
+  fn it_finds_the_secret_length_with_an_oracle() {
+    let secret = aes::generate_rand_bytes(37);
+    let msg = b"user=alice;role=guest";
+    let mac = md5(&[&secret[..], &msg[..]].concat());
+    let valid =
+      |m: &[u8], t: &[u8]| md5(&[&secret[..], m].concat()) == t.to_vec();
+    let forgery =
+      forge_with_oracle::<Md5, _, _>(&mac, msg, b";role=admin", 0..64, valid)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(forgery.secret_len, 37);
+    assert_eq!(forgery.queries, 38);
+    assert!(valid(&forgery.message, &forgery.mac));
+    assert_eq!(
+      forge_with_oracle::<Md5, _, _>(&mac, msg, b"x", 0..37, valid),
+      Ok(None)
+    );
+    assert!(
+      forge_with_oracle::<Md5, _, _>(&mac[..8], msg, b"x", 0..64, valid)
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn truncated_digests_cant_be_extended() {
+    let digest = sha384(b"message");
+
+    assert!(length_extend::<Sha512>(&digest, 7, b"suffix").is_err());
+  }
+}
@@ -3,8 +3,36 @@ extern crate hex;
 
 pub mod aes;
 pub mod authentication;
+pub mod block_cipher;
+pub mod chacha20;
+pub mod chacha20_poly1305;
+pub mod collision_search;
+pub mod constant_time;
+pub mod crc32;
+pub mod des;
+pub mod error;
 pub mod freq_analysis;
+pub mod gf256;
+pub mod hash_function;
+pub mod kdf;
+pub mod md4;
+pub mod md5;
+pub mod merkle_damgard;
 pub mod mt_prng;
+pub mod poly1305;
+pub mod rc4;
+pub mod salsa20;
+pub mod sbox;
+pub mod sha1;
+pub mod sha2;
+pub mod sha3;
+pub mod speck;
+pub mod spn;
+pub mod stream_cipher;
+pub mod toy_hash;
+
+pub use self::error::CryptoError;
+pub use self::gf256::Gf256;
 
 use std::iter;
 use std::time::SystemTime;
@@ -18,21 +46,9 @@ pub fn system_time_as_u32() -> u32 {
     .as_secs() as u32
 }
 
-pub fn mult_bytes(mut a: u8, mut b: u8) -> u8 {
-  let mut p: u8 = 0;
-  let modulo: u8 = 27; // 11B field representation modulo
-  for _i in 0..8 {
-    if b & 1 > 0 {
-      p = p ^ a;
-    }
-    b = b >> 1;
-    let carry = a & 0x80;
-    a = a << 1;
-    if carry > 0 {
-      a = a ^ modulo;
-    }
-  }
-  p
+// multiplication in AES's GF(2^8)
+pub fn mult_bytes(a: u8, b: u8) -> u8 {
+  (Gf256::new(a) * Gf256::new(b)).value()
 }
 
 pub fn word_to_bytes(word: u32) -> [u8; 4] {
@@ -55,9 +71,9 @@ pub fn bytes_to_word(bytes: &[u8]) -> u32 {
   word
 }
 
-pub fn hex_to_b64(hex: &str) -> String {
-  let decoded_hex = hex::decode(hex).expect("Invalid Hex!");
-  base64::encode(&decoded_hex)
+pub fn hex_to_b64(hex: &str) -> Result<String, CryptoError> {
+  let decoded_hex = hex::decode(hex)?;
+  Ok(base64::encode(&decoded_hex))
 }
 
 pub fn bytes_to_string(bytes: Vec<u8>) -> String {
@@ -65,40 +81,54 @@ pub fn bytes_to_string(bytes: Vec<u8>) -> String {
 }
 
 pub fn xor_string(msg: &str, key: &str) -> String {
-  let key = resize_key(key, msg.len());
-  let xord_bytes = xor_bytes(msg.as_bytes(), key.as_bytes());
+  let key = expand_bytes(key.as_bytes(), msg.len());
+  let xord_bytes = msg
+    .as_bytes()
+    .iter()
+    .zip(key.iter())
+    .map(|(m, k)| m ^ k)
+    .collect();
   bytes_to_string(xord_bytes)
 }
 
-pub fn xor_hex(hex_msg: &str, hex_key: &str) -> String {
-  let hex_key = resize_key(hex_key, hex_msg.len());
-  let message_bytes = hex::decode(hex_msg).expect("Message is invalid hex");
-  let key_bytes = hex::decode(hex_key).expect("Key is invalid hex");
-  let xord_bytes = xor_bytes(&message_bytes, &key_bytes);
-  hex::encode(xord_bytes)
+pub fn xor_hex(hex_msg: &str, hex_key: &str) -> Result<String, CryptoError> {
+  let message_bytes = hex::decode(hex_msg)?;
+  let key_bytes = hex::decode(hex_key)?;
+  let key_bytes = expand_bytes(&key_bytes, message_bytes.len());
+  let xord_bytes = xor_bytes(&message_bytes, &key_bytes)?;
+  Ok(hex::encode(xord_bytes))
 }
 
-pub fn hamming_distance(bytes1: &[u8], bytes2: &[u8]) -> u32 {
+pub fn hamming_distance(
+  bytes1: &[u8],
+  bytes2: &[u8],
+) -> Result<u32, CryptoError> {
   let mut distance: u32 = 0;
-  for byte in xor_bytes(bytes1, bytes2) {
+  for byte in xor_bytes(bytes1, bytes2)? {
     distance += (byte as u64).count_ones();
   }
-  distance
+  Ok(distance)
 }
 
-pub fn xor_bytes(a: &[u8], b: &[u8]) -> Vec<u8> {
+pub fn xor_bytes(a: &[u8], b: &[u8]) -> Result<Vec<u8>, CryptoError> {
   if a.len() != b.len() {
-    panic!("Byte arrays not the same length!")
+    return Err(CryptoError::LengthMismatch {
+      left: a.len(),
+      right: b.len(),
+    });
   };
   let mut xord_bytes: Vec<u8> = vec![];
   for (i, byte) in a.iter().enumerate() {
     xord_bytes.push(byte ^ b[i]);
   }
-  xord_bytes
+  Ok(xord_bytes)
 }
 
 pub fn expand_bytes(bytes: &[u8], size: usize) -> Vec<u8> {
   let mut expanded_bytes: Vec<u8> = vec![];
+  if bytes.is_empty() {
+    return expanded_bytes;
+  }
   while { expanded_bytes.len() < size } {
     let bytes_needed = size - expanded_bytes.len();
     if bytes.len() < bytes_needed {
@@ -126,35 +156,32 @@ pub fn build_byte_vec(byte: u8, size: usize) -> Vec<u8> {
 }
 
 pub fn strip_pkcs7_padding(
+  decrypted_bytes: Vec<u8>,
+) -> Result<Vec<u8>, CryptoError> {
+  unpad_bytes(decrypted_bytes, 16)
+}
+
+pub fn unpad_bytes(
   mut decrypted_bytes: Vec<u8>,
-) -> Result<Vec<u8>, String> {
-  let padding_byte = decrypted_bytes.last().unwrap().clone();
-  if padding_byte > 0 && padding_byte < 17 {
+  block_size: usize,
+) -> Result<Vec<u8>, CryptoError> {
+  let padding_byte = match decrypted_bytes.last() {
+    Some(byte) => *byte,
+    None => return Err(CryptoError::InvalidPadding),
+  };
+  let padding_len = padding_byte as usize;
+  if padding_len > 0
+    && padding_len <= block_size
+    && padding_len <= decrypted_bytes.len()
+  {
     for _i in 0..padding_byte {
-      if decrypted_bytes.pop().unwrap() != padding_byte {
-        return Err("Invalid padding".to_string());
+      if decrypted_bytes.pop() != Some(padding_byte) {
+        return Err(CryptoError::InvalidPadding);
       }
     }
     return Ok(decrypted_bytes);
   }
-  Err("Invalid padding".to_string())
-}
-
-fn resize_key(key: &str, size: usize) -> String {
-  if key.len() == size {
-    return String::from(key);
-  }
-
-  let mut resized_key = String::new();
-  while { resized_key.len() < size } {
-    let bytes_needed = size - resized_key.len();
-    if key.len() < bytes_needed {
-      resized_key.push_str(&key[0..]);
-    } else {
-      resized_key.push_str(&key[0..bytes_needed]);
-    }
-  }
-  resized_key
+  Err(CryptoError::InvalidPadding)
 }
 
 #[cfg(test)]
@@ -166,7 +193,7 @@ mod tests {
     let hex = "ff00"; //     111111 110000 0000-- ------
                       //     |      |      |      |
     let base64 = "/wA="; //  /      w      A      =
-    let result = hex_to_b64(&hex);
+    let result = hex_to_b64(hex).unwrap();
 
     assert_eq!(result, base64);
   }
@@ -178,18 +205,26 @@ mod tests {
       6f7573206d757368726f6f6d";
     let base64 =
       "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t";
-    let result = hex_to_b64(&hex);
+    let result = hex_to_b64(hex).unwrap();
 
     assert_eq!(result, base64);
   }
 
+  #[test]
+  fn hex_to_base64_rejects_invalid_hex() {
+    match hex_to_b64("not hex") {
+      Err(CryptoError::Decode(_)) => (),
+      result => panic!("expected a decode error, got {:?}", result),
+    }
+  }
+
   #[test]
   fn simple_hex_string_xor() {
     let hex1 = "ff"; // 11111111
     let hex2 = "11"; // 00010001
     let xord = "ee"; // 11101110
 
-    let result = xor_hex(&hex1, &hex2);
+    let result = xor_hex(hex1, hex2).unwrap();
 
     assert_eq!(xord, result);
   }
@@ -225,7 +260,7 @@ mod tests {
     let key = "f1"; //       1111000111110001
     let xord = "6987"; //    0110100110000111
 
-    let result = xor_hex(&message, &key);
+    let result = xor_hex(message, key).unwrap();
 
     assert_eq!(xord, result);
   }
@@ -236,7 +271,7 @@ mod tests {
     let key = "f123"; //   1111000100100011
     let xord = "69"; //    01101001
 
-    let result = xor_hex(&message, &key);
+    let result = xor_hex(message, key).unwrap();
 
     assert_eq!(xord, result);
   }
@@ -247,7 +282,7 @@ mod tests {
     let hex2 = "686974207468652062756c6c277320657965";
     let xord = "746865206b696420646f6e277420706c6179";
 
-    let result = xor_hex(&hex1, &hex2);
+    let result = xor_hex(hex1, hex2).unwrap();
 
     assert_eq!(result, xord);
   }
@@ -257,7 +292,7 @@ mod tests {
     let bytes1 = b"a"; // 01100001
     let bytes2 = b"b"; // 01100010
 
-    let result = hamming_distance(bytes1, bytes2);
+    let result = hamming_distance(bytes1, bytes2).unwrap();
 
     assert_eq!(result, 2);
   }
@@ -267,11 +302,18 @@ mod tests {
     let bytes1 = b"this is a test";
     let bytes2 = b"wokka wokka!!!";
 
-    let result = hamming_distance(bytes1, bytes2);
+    let result = hamming_distance(bytes1, bytes2).unwrap();
 
     assert_eq!(result, 37);
   }
 
+  #[test]
+  fn xor_of_mismatched_lengths_is_an_error() {
+    let result = xor_bytes(b"abc", b"ab");
+
+    assert_eq!(result, Err(CryptoError::LengthMismatch { left: 3, right: 2 }));
+  }
+
   #[test]
   fn it_expands_bytes() {
     let bytes = b"abc";
@@ -295,17 +337,40 @@ mod tests {
     let invalid_padding0 = "ICE ICE BABY\x03\x03\x03\x00".as_bytes().to_vec();
     let result0 = strip_pkcs7_padding(invalid_padding0);
 
-    assert_eq!(Err("Invalid padding".to_string()), result0);
+    assert_eq!(Err(CryptoError::InvalidPadding), result0);
 
     let invalid_padding1 = "ICE ICE BABY\x01\x02\x03\x04".as_bytes().to_vec();
     let result1 = strip_pkcs7_padding(invalid_padding1);
 
-    assert_eq!(Err("Invalid padding".to_string()), result1);
+    assert_eq!(Err(CryptoError::InvalidPadding), result1);
 
     let invalid_padding2 = "ICE ICE BABY\x01\x05\x05\x05".as_bytes().to_vec();
     let result2 = strip_pkcs7_padding(invalid_padding2);
 
-    assert_eq!(Err("Invalid padding".to_string()), result2);
+    assert_eq!(Err(CryptoError::InvalidPadding), result2);
+
+    let result3 = strip_pkcs7_padding(vec![]);
+
+    assert_eq!(Err(CryptoError::InvalidPadding), result3);
+
+    let result4 = strip_pkcs7_padding(vec![4, 4, 4]);
+
+    assert_eq!(Err(CryptoError::InvalidPadding), result4);
+  }
+
+  #[test]
+  fn it_strips_padding_for_smaller_block_sizes() {
+    let valid_padding = b"ICE ICE\x01".to_vec();
+
+    assert_eq!(Ok(b"ICE ICE".to_vec()), unpad_bytes(valid_padding, 8));
+
+    let too_long_padding = build_byte_vec(9, 16);
+
+    assert_eq!(
+      Err(CryptoError::InvalidPadding),
+      unpad_bytes(too_long_padding.clone(), 8)
+    );
+    assert_eq!(Ok(build_byte_vec(9, 7)), unpad_bytes(too_long_padding, 16));
   }
 
   #[test]
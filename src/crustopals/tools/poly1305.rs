@@ -0,0 +1,336 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::constant_time::ct_eq;
+use crustopals::tools::CryptoError;
+
+const MASK_26: u64 = (1 << 26) - 1;
+// 2p as five 26 bit limbs, each at least as big as any carried limb, so
+// 2p - x needs no borrows
+const TWO_P: [u64; 5] = [
+  (MASK_26 - 4) * 2,
+  MASK_26 * 2,
+  MASK_26 * 2,
+  MASK_26 * 2,
+  MASK_26 * 2,
+];
+// p - 2 as little endian bytes, the exponent for inverting
+const P_MINUS_2: [u8; 17] = [
+  0xf9, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+  0xff, 0xff, 0xff, 0x03,
+];
+
+// An integer modulo p = 2^130 - 5, kept as five 26 bit limbs so limb
+// products fit comfortably in a u64. Values are only guaranteed to be below
+// 2^130 between operations; `to_bytes` gives the canonical one.
+#[derive(Debug, Clone, Copy)]
+pub struct Fe1305 {
+  limbs: [u64; 5],
+}
+
+impl Fe1305 {
+  pub fn zero() -> Fe1305 {
+    Fe1305 { limbs: [0; 5] }
+  }
+
+  pub fn one() -> Fe1305 {
+    Fe1305 {
+      limbs: [1, 0, 0, 0, 0],
+    }
+  }
+
+  // A little endian integer of up to 17 bytes, reduced mod p.
+  pub fn from_bytes(bytes: &[u8]) -> Fe1305 {
+    assert!(bytes.len() <= 17, "more than 17 bytes for a field element");
+    let mut padded = [0u8; 17];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    let low = LittleEndian::read_u128(&padded[..16]);
+    let high = padded[16] as u64;
+    let mut limbs = [
+      low as u64 & MASK_26,
+      (low >> 26) as u64 & MASK_26,
+      (low >> 52) as u64 & MASK_26,
+      (low >> 78) as u64 & MASK_26,
+      (low >> 104) as u64 | (high << 24),
+    ];
+    // bits past 2^130 wrap around times 5
+    limbs[0] += (limbs[4] >> 26) * 5;
+    limbs[4] &= MASK_26;
+    Fe1305 { limbs }.carried()
+  }
+
+  // The canonical value mod p as 17 little endian bytes; only the low two
+  // bits of the last byte are ever set.
+  pub fn to_bytes(&self) -> [u8; 17] {
+    let h = self.reduced().limbs;
+    let low = (h[0] as u128)
+      | ((h[1] as u128) << 26)
+      | ((h[2] as u128) << 52)
+      | ((h[3] as u128) << 78)
+      | ((h[4] as u128) << 104);
+    let mut bytes = [0u8; 17];
+    LittleEndian::write_u128(&mut bytes[..16], low);
+    bytes[16] = (h[4] >> 24) as u8;
+    bytes
+  }
+
+  pub fn add(&self, other: &Fe1305) -> Fe1305 {
+    let mut limbs = [0u64; 5];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+      *limb = self.limbs[i] + other.limbs[i];
+    }
+    Fe1305 { limbs }.carried()
+  }
+
+  pub fn sub(&self, other: &Fe1305) -> Fe1305 {
+    let mut limbs = [0u64; 5];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+      *limb = self.limbs[i] + TWO_P[i] - other.limbs[i];
+    }
+    Fe1305 { limbs }.carried()
+  }
+
+  pub fn mul(&self, other: &Fe1305) -> Fe1305 {
+    let a = self.limbs;
+    let b = other.limbs;
+    // 2^130 == 5, so limb products that land past the top fold back in x5
+    let s: Vec<u64> = b.iter().map(|limb| limb * 5).collect();
+    let limbs = [
+      a[0] * b[0] + a[1] * s[4] + a[2] * s[3] + a[3] * s[2] + a[4] * s[1],
+      a[0] * b[1] + a[1] * b[0] + a[2] * s[4] + a[3] * s[3] + a[4] * s[2],
+      a[0] * b[2] + a[1] * b[1] + a[2] * b[0] + a[3] * s[4] + a[4] * s[3],
+      a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0] + a[4] * s[4],
+      a[0] * b[4] + a[1] * b[3] + a[2] * b[2] + a[3] * b[1] + a[4] * b[0],
+    ];
+    Fe1305 { limbs }.carried()
+  }
+
+  // Square and multiply with a little endian exponent, which may be as wide
+  // as p itself.
+  pub fn pow(&self, exponent: &[u8]) -> Fe1305 {
+    let mut result = Fe1305::one();
+    for byte in exponent.iter().rev() {
+      for bit in (0..8).rev() {
+        result = result.mul(&result);
+        if (byte >> bit) & 1 == 1 {
+          result = result.mul(self);
+        }
+      }
+    }
+    result
+  }
+
+  // x^(p-2), by Fermat's little theorem.
+  pub fn inverse(&self) -> Option<Fe1305> {
+    if self.is_zero() {
+      None
+    } else {
+      Some(self.pow(&P_MINUS_2))
+    }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    *self == Fe1305::zero()
+  }
+
+  // Carries until every limb fits in 26 bits, i.e. the value is below 2^130.
+  fn carried(mut self) -> Fe1305 {
+    loop {
+      for i in 0..4 {
+        self.limbs[i + 1] += self.limbs[i] >> 26;
+        self.limbs[i] &= MASK_26;
+      }
+      let overflow = self.limbs[4] >> 26;
+      if overflow == 0 {
+        return self;
+      }
+      self.limbs[4] &= MASK_26;
+      self.limbs[0] += overflow * 5;
+    }
+  }
+
+  // After carrying the value is below 2^130, i.e. below 2p; subtract p once
+  // if needed.
+  fn reduced(&self) -> Fe1305 {
+    let h = self.carried();
+    let mut g = h;
+    g.limbs[0] += 5;
+    for i in 0..4 {
+      g.limbs[i + 1] += g.limbs[i] >> 26;
+      g.limbs[i] &= MASK_26;
+    }
+    // h + 5 >= 2^130 exactly when h >= p
+    if g.limbs[4] >> 26 != 0 {
+      g.limbs[4] &= MASK_26;
+      g
+    } else {
+      h
+    }
+  }
+}
+
+impl PartialEq for Fe1305 {
+  fn eq(&self, other: &Fe1305) -> bool {
+    self.to_bytes() == other.to_bytes()
+  }
+}
+
+// The "r" half of a Poly1305 key, with the bits the spec requires cleared.
+pub fn clamp(r: &[u8]) -> Result<Fe1305, CryptoError> {
+  if r.len() != 16 {
+    return Err(CryptoError::InvalidKeyLength {
+      expected: 16,
+      actual: r.len(),
+    });
+  }
+  let mut clamped = [0u8; 16];
+  clamped.copy_from_slice(r);
+  for idx in [3, 7, 11, 15].iter() {
+    clamped[*idx] &= 0x0f;
+  }
+  for idx in [4, 8, 12].iter() {
+    clamped[*idx] &= 0xfc;
+  }
+  Ok(Fe1305::from_bytes(&clamped))
+}
+
+// Each 16 byte chunk of the message (the last one possibly shorter) with a
+// 1 byte appended, as the coefficients Poly1305 evaluates.
+pub fn message_blocks(message: &[u8]) -> Vec<Fe1305> {
+  message
+    .chunks(16)
+    .map(|chunk| {
+      let mut block = chunk.to_vec();
+      block.push(1);
+      Fe1305::from_bytes(&block)
+    })
+    .collect()
+}
+
+// Poly1305 (RFC 8439 section 2.5) under a one time 32 byte key r || s:
+// evaluate the message polynomial at r mod 2^130 - 5, then add s mod 2^128.
+pub fn poly1305(key: &[u8], message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+  if key.len() != 32 {
+    return Err(CryptoError::InvalidKeyLength {
+      expected: 32,
+      actual: key.len(),
+    });
+  }
+  let r = clamp(&key[..16])?;
+  let accumulator = message_blocks(message)
+    .iter()
+    .fold(Fe1305::zero(), |acc, block| acc.add(block).mul(&r));
+  let h = LittleEndian::read_u128(&accumulator.to_bytes()[..16]);
+  let s = LittleEndian::read_u128(&key[16..]);
+  let mut tag = vec![0u8; 16];
+  LittleEndian::write_u128(&mut tag, h.wrapping_add(s));
+  Ok(tag)
+}
+
+// Compares `tag` against the message's in constant time.
+pub fn verify(
+  key: &[u8],
+  message: &[u8],
+  tag: &[u8],
+) -> Result<bool, CryptoError> {
+  Ok(ct_eq(&poly1305(key, message)?, tag))
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  #[test]
+  fn it_computes_the_rfc_8439_tag() {
+    // section 2.5.2
+    let key = hex::decode(
+      "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b",
+    )
+    .unwrap();
+    let tag = poly1305(&key, "Cryptographic Forum Research Group".as_bytes());
+
+    assert_eq!(
+      hex::encode(tag.unwrap()),
+      "a8061dc1305136c6c22b8baf0c0127a9"
+    );
+  }
+
+  #[test]
+  fn it_verifies_tags() {
+    let key = [0x5a; 32];
+    let tag = poly1305(&key, b"message").unwrap();
+    let mut wrong = tag.clone();
+    wrong[15] ^= 1;
+
+    assert_eq!(verify(&key, b"message", &tag), Ok(true));
+    assert_eq!(verify(&key, b"message", &wrong), Ok(false));
+    assert_eq!(verify(&key, b"message", &tag[..15]), Ok(false));
+    assert!(verify(&key[..16], b"message", &tag).is_err());
+  }
+
+  #[test]
+  fn it_handles_the_wrap_around_edge_cases() {
+    // RFC 8439 appendix A.3 test vectors 6 and 7, where h lands right at or
+    // just past p
+    let mut key = vec![0u8; 32];
+    key[0] = 2;
+    let mut message = vec![0xffu8; 16];
+    assert_eq!(
+      hex::encode(poly1305(&key, &message).unwrap()),
+      "03000000000000000000000000000000"
+    );
+
+    let mut key = vec![0u8; 32];
+    key[0] = 2;
+    key[16..].copy_from_slice(&[0xffu8; 16]);
+    message = vec![0u8; 16];
+    message[0] = 2;
+    assert_eq!(
+      hex::encode(poly1305(&key, &message).unwrap()),
+      "03000000000000000000000000000000"
+    );
+  }
+
+  #[test]
+  fn field_elements_reduce_mod_p() {
+    // p itself is 0 and p + 1 is 1
+    let mut p = [0xffu8; 17];
+    p[0] = 0xfb;
+    p[16] = 0x03;
+    assert_eq!(Fe1305::from_bytes(&p), Fe1305::zero());
+    p[0] = 0xfc;
+    assert_eq!(Fe1305::from_bytes(&p), Fe1305::one());
+    // (p - 1)^2 == 1
+    p[0] = 0xfa;
+    let minus_one = Fe1305::from_bytes(&p);
+    assert_eq!(minus_one.mul(&minus_one), Fe1305::one());
+    assert_eq!(minus_one.add(&Fe1305::one()), Fe1305::zero());
+  }
+
+  #[test]
+  fn it_subtracts_and_inverts() {
+    let a = Fe1305::from_bytes(&[0x12; 16]);
+    let b = Fe1305::from_bytes(&[0xfe; 17]);
+
+    assert_eq!(a.sub(&b).add(&b), a);
+    assert_eq!(
+      Fe1305::zero().sub(&Fe1305::one()).add(&Fe1305::one()),
+      Fe1305::zero()
+    );
+    assert_eq!(a.mul(&a.inverse().unwrap()), Fe1305::one());
+    assert_eq!(a.pow(&[3]), a.mul(&a).mul(&a));
+    assert_eq!(Fe1305::zero().inverse(), None);
+  }
+
+  #[test]
+  fn it_rejects_short_keys() {
+    assert!(poly1305(&[0u8; 16], b"message").is_err());
+    assert_eq!(
+      clamp(&[0u8; 15]),
+      Err(CryptoError::InvalidKeyLength {
+        expected: 16,
+        actual: 15
+      })
+    );
+  }
+}
@@ -0,0 +1,118 @@
+use crustopals::tools::stream_cipher::StreamCipher;
+use crustopals::tools::CryptoError;
+
+// RC4, optionally discarding the first `drop` keystream bytes (RC4-drop[n]),
+// which is where its best known biases live.
+pub struct Rc4 {
+  key: Vec<u8>,
+  drop: usize,
+}
+
+impl Rc4 {
+  pub fn new(key: &[u8]) -> Result<Rc4, CryptoError> {
+    Rc4::with_drop(key, 0)
+  }
+
+  pub fn with_drop(key: &[u8], drop: usize) -> Result<Rc4, CryptoError> {
+    // anything from 1 to 256 bytes works, 16 is the usual choice
+    if key.is_empty() || key.len() > 256 {
+      return Err(CryptoError::InvalidKeyLength {
+        expected: 16,
+        actual: key.len(),
+      });
+    }
+    Ok(Rc4 {
+      key: key.to_vec(),
+      drop,
+    })
+  }
+}
+
+impl StreamCipher for Rc4 {
+  fn keystream(&self, len: usize) -> Vec<u8> {
+    // key scheduling
+    let mut s = [0u8; 256];
+    for (i, byte) in s.iter_mut().enumerate() {
+      *byte = i as u8;
+    }
+    let mut j = 0u8;
+    for i in 0..256 {
+      j = j
+        .wrapping_add(s[i])
+        .wrapping_add(self.key[i % self.key.len()]);
+      s.swap(i, j as usize);
+    }
+
+    // output generation
+    let mut i = 0u8;
+    let mut j = 0u8;
+    let mut stream = Vec::with_capacity(len);
+    for n in 0..(self.drop + len) {
+      i = i.wrapping_add(1);
+      j = j.wrapping_add(s[i as usize]);
+      s.swap(i as usize, j as usize);
+      if n >= self.drop {
+        stream.push(s[s[i as usize].wrapping_add(s[j as usize]) as usize]);
+      }
+    }
+    stream
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+  use crustopals::tools::aes;
+
+  #[test]
+  fn it_encrypts_the_classic_test_vectors() {
+    let rc4 = Rc4::new("Key".as_bytes()).unwrap();
+    assert_eq!(
+      hex::encode(rc4.apply_keystream("Plaintext".as_bytes())),
+      "bbf316e8d940af0ad3"
+    );
+
+    let rc4 = Rc4::new("Secret".as_bytes()).unwrap();
+    assert_eq!(
+      hex::encode(rc4.apply_keystream("Attack at dawn".as_bytes())),
+      "45a01f645fc35b383552544b9bf5"
+    );
+  }
+
+  #[test]
+  fn it_matches_the_rfc_6229_keystream() {
+    // 40 bit key, offsets 0 and 768
+    let key = hex::decode("0102030405").unwrap();
+    let rc4 = Rc4::new(&key).unwrap();
+    let drop768 = Rc4::with_drop(&key, 768).unwrap();
+
+    assert_eq!(
+      hex::encode(rc4.keystream(16)),
+      "b2396305f03dc027ccc3524a0a1118a8"
+    );
+    assert_eq!(
+      hex::encode(drop768.keystream(16)),
+      "eb62638d4f0ba1fe9fca20e05bf8ff2b"
+    );
+  }
+
+  #[test]
+  fn dropping_skips_the_start_of_the_keystream() {
+    let key = aes::generate_key();
+    let full = Rc4::new(&key).unwrap().keystream(300);
+
+    assert_eq!(
+      Rc4::with_drop(&key, 256).unwrap().keystream(44),
+      &full[256..]
+    );
+  }
+
+  #[test]
+  fn it_rejects_empty_and_oversized_keys() {
+    assert!(Rc4::new(&[]).is_err());
+    assert!(Rc4::new(&[0u8; 257]).is_err());
+    assert!(Rc4::new(&[0u8; 256]).is_ok());
+  }
+}
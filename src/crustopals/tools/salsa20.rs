@@ -0,0 +1,280 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::stream_cipher::StreamCipher;
+use crustopals::tools::CryptoError;
+
+// "expand 32-byte k", which Salsa20 spreads along the diagonal
+const CONSTANTS: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+// Salsa20/20 with a 256 bit key, a 64 bit nonce and a 64 bit block counter.
+pub struct Salsa20 {
+  key: [u32; 8],
+  nonce: [u32; 2],
+}
+
+// XSalsa20 stretches the nonce to 192 bits: HSalsa20 turns the key and the
+// first 128 bits of nonce into a subkey for plain Salsa20 with the rest.
+pub struct XSalsa20 {
+  inner: Salsa20,
+}
+
+impl Salsa20 {
+  pub fn new(key: &[u8], nonce: &[u8]) -> Result<Salsa20, CryptoError> {
+    check_key(key)?;
+    if nonce.len() != 8 {
+      return Err(CryptoError::InvalidIvLength {
+        expected: 8,
+        actual: nonce.len(),
+      });
+    }
+    let mut nonce_words = [0u32; 2];
+    LittleEndian::read_u32_into(nonce, &mut nonce_words);
+    Ok(Salsa20 {
+      key: key_words(key),
+      nonce: nonce_words,
+    })
+  }
+
+  pub fn block(&self, counter: u64) -> [u8; 64] {
+    let input = initial_state(
+      &self.key,
+      [
+        self.nonce[0],
+        self.nonce[1],
+        counter as u32,
+        (counter >> 32) as u32,
+      ],
+    );
+    let mut working = input;
+    double_rounds(&mut working);
+    for (word, initial) in working.iter_mut().zip(input.iter()) {
+      *word = word.wrapping_add(*initial);
+    }
+    let mut block = [0u8; 64];
+    LittleEndian::write_u32_into(&working, &mut block);
+    block
+  }
+}
+
+impl StreamCipher for Salsa20 {
+  fn keystream(&self, len: usize) -> Vec<u8> {
+    let mut stream: Vec<u8> = vec![];
+    let mut counter = 0u64;
+    while stream.len() < len {
+      stream.extend(self.block(counter).iter());
+      counter += 1;
+    }
+    stream.truncate(len);
+    stream
+  }
+}
+
+impl XSalsa20 {
+  pub fn new(key: &[u8], nonce: &[u8]) -> Result<XSalsa20, CryptoError> {
+    check_key(key)?;
+    if nonce.len() != 24 {
+      return Err(CryptoError::InvalidIvLength {
+        expected: 24,
+        actual: nonce.len(),
+      });
+    }
+    let subkey = hsalsa20(key, &nonce[..16])?;
+    Ok(XSalsa20 {
+      inner: Salsa20::new(&subkey, &nonce[16..])?,
+    })
+  }
+}
+
+impl StreamCipher for XSalsa20 {
+  fn keystream(&self, len: usize) -> Vec<u8> {
+    self.inner.keystream(len)
+  }
+}
+
+// The Salsa20 core without the final feed-forward, keeping the words that
+// line up with the constants and the input: a 256 bit subkey.
+pub fn hsalsa20(key: &[u8], input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+  check_key(key)?;
+  if input.len() != 16 {
+    return Err(CryptoError::InvalidIvLength {
+      expected: 16,
+      actual: input.len(),
+    });
+  }
+  let mut input_words = [0u32; 4];
+  LittleEndian::read_u32_into(input, &mut input_words);
+  let mut state = initial_state(&key_words(key), input_words);
+  double_rounds(&mut state);
+  let subkey_words: Vec<u32> = [0, 5, 10, 15, 6, 7, 8, 9]
+    .iter()
+    .map(|idx| state[*idx])
+    .collect();
+  let mut subkey = vec![0u8; 32];
+  LittleEndian::write_u32_into(&subkey_words, &mut subkey);
+  Ok(subkey)
+}
+
+fn check_key(key: &[u8]) -> Result<(), CryptoError> {
+  if key.len() != 32 {
+    return Err(CryptoError::InvalidKeyLength {
+      expected: 32,
+      actual: key.len(),
+    });
+  }
+  Ok(())
+}
+
+fn key_words(key: &[u8]) -> [u32; 8] {
+  let mut words = [0u32; 8];
+  LittleEndian::read_u32_into(key, &mut words);
+  words
+}
+
+fn initial_state(key: &[u32; 8], input: [u32; 4]) -> [u32; 16] {
+  [
+    CONSTANTS[0],
+    key[0],
+    key[1],
+    key[2],
+    key[3],
+    CONSTANTS[1],
+    input[0],
+    input[1],
+    input[2],
+    input[3],
+    CONSTANTS[2],
+    key[4],
+    key[5],
+    key[6],
+    key[7],
+    CONSTANTS[3],
+  ]
+}
+
+fn double_rounds(state: &mut [u32; 16]) {
+  for _ in 0..10 {
+    // columns, then rows
+    quarter_round(state, 0, 4, 8, 12);
+    quarter_round(state, 5, 9, 13, 1);
+    quarter_round(state, 10, 14, 2, 6);
+    quarter_round(state, 15, 3, 7, 11);
+    quarter_round(state, 0, 1, 2, 3);
+    quarter_round(state, 5, 6, 7, 4);
+    quarter_round(state, 10, 11, 8, 9);
+    quarter_round(state, 15, 12, 13, 14);
+  }
+}
+
+fn quarter_round(
+  state: &mut [u32; 16],
+  a: usize,
+  b: usize,
+  c: usize,
+  d: usize,
+) {
+  state[b] ^= state[a].wrapping_add(state[d]).rotate_left(7);
+  state[c] ^= state[b].wrapping_add(state[a]).rotate_left(9);
+  state[d] ^= state[c].wrapping_add(state[b]).rotate_left(13);
+  state[a] ^= state[d].wrapping_add(state[c]).rotate_left(18);
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  #[test]
+  fn it_expands_the_salsa20_spec_example() {
+    // the Salsa20_k(n) example from the spec, where n is the nonce followed
+    // by the little endian block counter
+    let key: Vec<u8> = (1..17).chain(201..217).collect();
+    let nonce: Vec<u8> = (101..109).collect();
+    let counter = LittleEndian::read_u64(&(109..117).collect::<Vec<u8>>());
+    let salsa = Salsa20::new(&key, &nonce).unwrap();
+
+    assert_eq!(
+      salsa.block(counter)[..],
+      [
+        69, 37, 68, 39, 41, 15, 107, 193, 255, 139, 122, 6, 170, 233, 217, 98,
+        89, 144, 182, 106, 21, 51, 200, 65, 239, 49, 222, 34, 215, 114, 40,
+        126, 104, 197, 7, 225, 197, 153, 31, 2, 102, 78, 76, 176, 84, 245, 246,
+        184, 177, 160, 133, 130, 6, 72, 149, 119, 192, 195, 132, 236, 234, 103,
+        246, 74,
+      ][..]
+    );
+  }
+
+  #[test]
+  fn it_streams_successive_blocks() {
+    let key: Vec<u8> = (1..17).chain(201..217).collect();
+    let nonce: Vec<u8> = (101..109).collect();
+    let salsa = Salsa20::new(&key, &nonce).unwrap();
+    let stream = salsa.keystream(80);
+
+    assert_eq!(&stream[..64], &salsa.block(0)[..]);
+    assert_eq!(&stream[64..], &salsa.block(1)[..16]);
+    assert_eq!(
+      hex::encode(&stream[64..]),
+      "ff01db5e8f99184ae1dcc6da4d5e5c99"
+    );
+  }
+
+  #[test]
+  fn it_derives_the_nacl_secondkey_with_hsalsa20() {
+    // "firstkey" and "nonceprefix" from the NaCl test suite
+    let key = hex::decode(
+      "1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389",
+    )
+    .unwrap();
+    let input = hex::decode("69696ee955b62b73cd62bda875fc73d6").unwrap();
+
+    assert_eq!(
+      hex::encode(hsalsa20(&key, &input).unwrap()),
+      "dc908dda0b9344a953629b733820778880f3ceb421bb61b91cbd4c3e66256ce4"
+    );
+  }
+
+  #[test]
+  fn xsalsa20_is_salsa20_under_the_hsalsa20_subkey() {
+    let key = hex::decode(
+      "1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389",
+    )
+    .unwrap();
+    let nonce =
+      hex::decode("69696ee955b62b73cd62bda875fc73d6e4dbd1a3cdefe1d5").unwrap();
+    let xsalsa = XSalsa20::new(&key, &nonce).unwrap();
+    let subkey = hsalsa20(&key, &nonce[..16]).unwrap();
+    let salsa = Salsa20::new(&subkey, &nonce[16..]).unwrap();
+
+    assert_eq!(xsalsa.keystream(100), salsa.keystream(100));
+    assert_eq!(
+      hex::encode(xsalsa.keystream(32)),
+      "5bb6e6dd2e5d38647351e14931fc0f68989405dfaf99cf8d636f41bd686b5699"
+    );
+  }
+
+  #[test]
+  fn it_matches_the_nacl_xsalsa20_stream() {
+    // NaCl's tests/stream3.c: crypto_stream_xsalsa20 under "firstkey" and
+    // "nonce"
+    let key = hex::decode(
+      "1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389",
+    )
+    .unwrap();
+    let nonce =
+      hex::decode("69696ee955b62b73cd62bda875fc73d68219e0036b7a0b37").unwrap();
+    let xsalsa = XSalsa20::new(&key, &nonce).unwrap();
+
+    assert_eq!(
+      hex::encode(xsalsa.keystream(32)),
+      "eea6a7251c1e72916d11c2cb214d3c252539121d8e234e652d651fa4c8cff880"
+    );
+  }
+
+  #[test]
+  fn it_rejects_bad_nonce_sizes() {
+    assert!(Salsa20::new(&[0u8; 32], &[0u8; 12]).is_err());
+    assert!(XSalsa20::new(&[0u8; 32], &[0u8; 8]).is_err());
+    assert!(XSalsa20::new(&[0u8; 16], &[0u8; 24]).is_err());
+  }
+}
@@ -0,0 +1,301 @@
+use crustopals::tools::aes::byte_operations;
+use crustopals::tools::{CryptoError, Gf256};
+
+// An n bit to n bit substitution table (n <= 8) along with the usual
+// measures of how well it resists differential and linear cryptanalysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SBox {
+  table: Vec<u8>,
+  bits: usize,
+}
+
+impl SBox {
+  pub fn new(table: &[u8]) -> Result<SBox, CryptoError> {
+    let size = table.len();
+    if !(2..=256).contains(&size) || !size.is_power_of_two() {
+      return Err(CryptoError::Parse(format!(
+        "an S-box needs 2^n entries for n in 1..=8, got {}",
+        size
+      )));
+    }
+    if let Some(entry) = table.iter().find(|entry| **entry as usize >= size) {
+      return Err(CryptoError::Parse(format!(
+        "S-box entry {} is out of range for {} entries",
+        entry, size
+      )));
+    }
+    Ok(SBox {
+      table: table.to_vec(),
+      bits: size.trailing_zeros() as usize,
+    })
+  }
+
+  // the lookup table AES ships with
+  pub fn aes() -> SBox {
+    let table: Vec<u8> = (0..=255u8).map(byte_operations::s_box).collect();
+    SBox::new(&table).unwrap()
+  }
+
+  // AES's S-box rebuilt from its definition: the multiplicative inverse in
+  // GF(2^8) (with 0 mapped to 0) followed by an affine map over GF(2).
+  pub fn aes_from_field() -> SBox {
+    let table: Vec<u8> = (0..=255u8)
+      .map(|byte| {
+        let inverse = Gf256::new(byte).inverse().unwrap_or(Gf256::zero());
+        aes_affine(inverse.value())
+      })
+      .collect();
+    SBox::new(&table).unwrap()
+  }
+
+  pub fn bits(&self) -> usize {
+    self.bits
+  }
+
+  pub fn table(&self) -> &[u8] {
+    &self.table
+  }
+
+  pub fn apply(&self, input: u8) -> u8 {
+    self.table[input as usize]
+  }
+
+  pub fn is_bijective(&self) -> bool {
+    let mut seen = vec![false; self.table.len()];
+    for entry in self.table.iter() {
+      seen[*entry as usize] = true;
+    }
+    seen.iter().all(|hit| *hit)
+  }
+
+  pub fn inverse(&self) -> Option<SBox> {
+    if !self.is_bijective() {
+      return None;
+    }
+    let mut inverse = vec![0u8; self.table.len()];
+    for (input, output) in self.table.iter().enumerate() {
+      inverse[*output as usize] = input as u8;
+    }
+    Some(SBox {
+      table: inverse,
+      bits: self.bits,
+    })
+  }
+
+  // ddt[a][b]: how many inputs x have S(x) ^ S(x ^ a) == b
+  pub fn difference_distribution_table(&self) -> Vec<Vec<u32>> {
+    let size = self.table.len();
+    let mut table = vec![vec![0u32; size]; size];
+    for (input_diff, row) in table.iter_mut().enumerate() {
+      for x in 0..size {
+        let output_diff = self.table[x] ^ self.table[x ^ input_diff];
+        row[output_diff as usize] += 1;
+      }
+    }
+    table
+  }
+
+  // lat[a][b]: how many inputs x have a·x == b·S(x), minus half of them. The
+  // columns come from a Walsh-Hadamard transform of each component function
+  // rather than 2^3n parity checks.
+  pub fn linear_approximation_table(&self) -> Vec<Vec<i32>> {
+    let size = self.table.len();
+    let mut table = vec![vec![0i32; size]; size];
+    for output_mask in 0..size {
+      let mut spectrum: Vec<i32> = self
+        .table
+        .iter()
+        .map(|y| {
+          if parity(*y as usize & output_mask) {
+            -1
+          } else {
+            1
+          }
+        })
+        .collect();
+      walsh_hadamard(&mut spectrum);
+      for (input_mask, row) in table.iter_mut().enumerate() {
+        row[output_mask] = spectrum[input_mask] / 2;
+      }
+    }
+    table
+  }
+
+  // The largest DDT entry over non-zero input differences; 2 is the best
+  // possible (APN), AES manages 4.
+  pub fn differential_uniformity(&self) -> u32 {
+    self.difference_distribution_table()[1..]
+      .iter()
+      .flat_map(|row| row.iter().cloned())
+      .max()
+      .unwrap_or(0)
+  }
+
+  // Distance from the nearest affine function, minimised over every non-zero
+  // combination of output bits: 2^(n-1) - max |lat[a][b]| for b != 0.
+  pub fn nonlinearity(&self) -> u32 {
+    let max_bias = self
+      .linear_approximation_table()
+      .iter()
+      .flat_map(|row| row[1..].iter().map(|entry| entry.abs()))
+      .max()
+      .unwrap_or(0);
+    (self.table.len() as u32 / 2) - max_bias as u32
+  }
+
+  // The highest degree monomial in the algebraic normal form of any output
+  // bit, found with the Moebius transform.
+  pub fn algebraic_degree(&self) -> u32 {
+    (0..self.bits)
+      .map(|bit| {
+        let mut anf: Vec<u8> =
+          self.table.iter().map(|y| (y >> bit) & 1).collect();
+        moebius(&mut anf);
+        anf
+          .iter()
+          .enumerate()
+          .filter(|(_, coefficient)| **coefficient == 1)
+          .map(|(monomial, _)| monomial.count_ones())
+          .max()
+          .unwrap_or(0)
+      })
+      .max()
+      .unwrap_or(0)
+  }
+
+  pub fn fixed_points(&self) -> Vec<u8> {
+    self
+      .table
+      .iter()
+      .enumerate()
+      .filter(|(input, output)| *input == **output as usize)
+      .map(|(input, _)| input as u8)
+      .collect()
+  }
+
+  // inputs with S(x) == !x, which AES's affine constant also avoids
+  pub fn opposite_fixed_points(&self) -> Vec<u8> {
+    let mask = (self.table.len() - 1) as u8;
+    self
+      .table
+      .iter()
+      .enumerate()
+      .filter(|(input, output)| *input as u8 ^ mask == **output)
+      .map(|(input, _)| input as u8)
+      .collect()
+  }
+}
+
+fn aes_affine(byte: u8) -> u8 {
+  byte
+    ^ byte.rotate_left(1)
+    ^ byte.rotate_left(2)
+    ^ byte.rotate_left(3)
+    ^ byte.rotate_left(4)
+    ^ 0x63
+}
+
+fn parity(bits: usize) -> bool {
+  bits.count_ones() % 2 == 1
+}
+
+fn walsh_hadamard(values: &mut [i32]) {
+  let mut half = 1;
+  while half < values.len() {
+    for start in (0..values.len()).step_by(2 * half) {
+      for i in start..(start + half) {
+        let (a, b) = (values[i], values[i + half]);
+        values[i] = a + b;
+        values[i + half] = a - b;
+      }
+    }
+    half *= 2;
+  }
+}
+
+fn moebius(values: &mut [u8]) {
+  let mut half = 1;
+  while half < values.len() {
+    for start in (0..values.len()).step_by(2 * half) {
+      for i in start..(start + half) {
+        values[i + half] ^= values[i];
+      }
+    }
+    half *= 2;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::spn::HEYS_SBOX;
+
+  #[test]
+  fn it_rebuilds_the_aes_sbox_from_the_field() {
+    let derived = SBox::aes_from_field();
+
+    assert_eq!(derived, SBox::aes());
+    let inverse = derived.inverse().unwrap();
+    for byte in 0..=255u8 {
+      assert_eq!(inverse.apply(byte), byte_operations::inv_s_box(byte));
+    }
+  }
+
+  #[test]
+  fn it_measures_the_aes_sbox() {
+    let aes = SBox::aes();
+
+    assert!(aes.is_bijective());
+    assert_eq!(aes.differential_uniformity(), 4);
+    assert_eq!(aes.nonlinearity(), 112);
+    assert_eq!(aes.algebraic_degree(), 7);
+    assert!(aes.fixed_points().is_empty());
+    assert!(aes.opposite_fixed_points().is_empty());
+  }
+
+  #[test]
+  fn it_measures_the_heys_sbox() {
+    let heys = SBox::new(&HEYS_SBOX).unwrap();
+    let ddt = heys.difference_distribution_table();
+    let lat = heys.linear_approximation_table();
+
+    assert_eq!(ddt[0xb][0x2], 8);
+    assert_eq!(lat[0xb][0x4], 4);
+    assert_eq!(lat[0x4][0x5], -4);
+    assert_eq!(lat[0][0], 8);
+    for (ddt_row, lat_row) in ddt.iter().zip(lat[1..].iter()) {
+      assert_eq!(ddt_row.iter().sum::<u32>(), 16);
+      assert_eq!(lat_row[0], 0);
+    }
+    assert_eq!(heys.differential_uniformity(), 8);
+    assert_eq!(heys.algebraic_degree(), 3);
+    assert!(heys.fixed_points().is_empty());
+  }
+
+  #[test]
+  fn affine_sboxes_have_no_nonlinearity() {
+    // x -> x ^ 5 over 3 bits is affine
+    let table: Vec<u8> = (0..8).map(|x| x ^ 5).collect();
+    let affine = SBox::new(&table).unwrap();
+
+    assert_eq!(affine.nonlinearity(), 0);
+    assert_eq!(affine.algebraic_degree(), 1);
+    assert_eq!(affine.differential_uniformity(), 8);
+  }
+
+  #[test]
+  fn it_finds_fixed_points_and_non_bijections() {
+    let sbox = SBox::new(&[0, 0, 3, 1]).unwrap();
+
+    assert_eq!(sbox.fixed_points(), vec![0]);
+    assert!(!sbox.is_bijective());
+    assert!(sbox.inverse().is_none());
+  }
+
+  #[test]
+  fn it_rejects_malformed_tables() {
+    assert!(SBox::new(&[0, 1, 2]).is_err());
+    assert!(SBox::new(&[0, 4, 1, 2]).is_err());
+    assert!(SBox::new(&[]).is_err());
+  }
+}
@@ -0,0 +1,169 @@
+use crustopals::byteorder::{BigEndian, ByteOrder};
+use crustopals::tools::merkle_damgard::{BlockBuffer, MerkleDamgard};
+use crustopals::tools::CryptoError;
+
+// SHA-1 (FIPS 180-4) with its internal state out in the open, so a digest
+// can be turned back into a hasher that carries on where it left off.
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 20;
+
+const INITIAL_WORDS: [u32; 5] =
+  [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+pub type Sha1State = BlockBuffer<[u32; 5]>;
+
+#[derive(Clone)]
+pub struct Sha1 {
+  state: Sha1State,
+}
+
+impl Sha1 {
+  pub fn new() -> Sha1 {
+    Sha1 {
+      state: BlockBuffer::new(INITIAL_WORDS),
+    }
+  }
+
+  pub fn from_state(state: Sha1State) -> Result<Sha1, CryptoError> {
+    state.check::<Sha1>()?;
+    Ok(Sha1 { state })
+  }
+
+  pub fn state(&self) -> Sha1State {
+    self.state.clone()
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    self.state.update::<Sha1>(bytes, compress);
+  }
+
+  pub fn digest(&self) -> Vec<u8> {
+    let mut digest = vec![0u8; DIGEST_SIZE];
+    BigEndian::write_u32_into(
+      &self.state.finish::<Sha1>(compress),
+      &mut digest,
+    );
+    digest
+  }
+}
+
+impl Default for Sha1 {
+  fn default() -> Sha1 {
+    Sha1::new()
+  }
+}
+
+pub fn sha1(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Sha1::new();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+// The padding appended to a `message_len` byte message: a 1 bit, zeros up
+// to 8 bytes short of a block boundary, then the length in bits.
+pub fn padding(message_len: u64) -> Vec<u8> {
+  <Sha1 as MerkleDamgard>::padding(message_len)
+}
+
+// The state right after the final block of a `length` byte message
+// (padding included), recovered from its digest.
+pub fn state_from_digest(
+  digest: &[u8],
+  length: u64,
+) -> Result<Sha1State, CryptoError> {
+  if digest.len() != DIGEST_SIZE {
+    return Err(CryptoError::LengthMismatch {
+      left: DIGEST_SIZE,
+      right: digest.len(),
+    });
+  }
+  let mut words = [0u32; 5];
+  BigEndian::read_u32_into(digest, &mut words);
+  Ok(Sha1State {
+    words,
+    length,
+    buffer: vec![],
+  })
+}
+
+fn compress(words: &mut [u32; 5], block: &[u8]) {
+  let mut schedule = [0u32; 80];
+  BigEndian::read_u32_into(block, &mut schedule[..16]);
+  for t in 16..80 {
+    schedule[t] =
+      (schedule[t - 3] ^ schedule[t - 8] ^ schedule[t - 14] ^ schedule[t - 16])
+        .rotate_left(1);
+  }
+
+  let [mut a, mut b, mut c, mut d, mut e] = *words;
+  for (t, w) in schedule.iter().enumerate() {
+    let (f, k) = match t {
+      0..=19 => ((b & c) | (!b & d), 0x5a827999),
+      20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+      40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+      _ => (b ^ c ^ d, 0xca62c1d6),
+    };
+    let temp = a
+      .rotate_left(5)
+      .wrapping_add(f)
+      .wrapping_add(e)
+      .wrapping_add(k)
+      .wrapping_add(*w);
+    e = d;
+    d = c;
+    c = b.rotate_left(30);
+    b = a;
+    a = temp;
+  }
+  for (word, new) in words.iter_mut().zip([a, b, c, d, e].iter()) {
+    *word = word.wrapping_add(*new);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  #[test]
+  fn it_hashes_the_fips_180_examples() {
+    assert_eq!(
+      hex::encode(sha1(b"abc")),
+      "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    assert_eq!(
+      hex::encode(sha1(b"")),
+      "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+    );
+    assert_eq!(
+      hex::encode(sha1(
+        b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+      )),
+      "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+    assert_eq!(
+      hex::encode(sha1(&vec![b'a'; 1_000_000])),
+      "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+  }
+
+  #[test]
+  fn it_rejects_states_off_a_block_boundary() {
+    let mut state = Sha1::new().state();
+    state.length = 10;
+
+    assert!(Sha1::from_state(state).is_err());
+    assert!(state_from_digest(&[0u8; 16], 64).is_err());
+  }
+
+  #[test]
+  fn padding_ends_on_a_block_boundary() {
+    for len in 0..200u64 {
+      assert_eq!((len as usize + padding(len).len()) % BLOCK_SIZE, 0);
+    }
+    assert_eq!(padding(55).len(), 9);
+    assert_eq!(padding(56).len(), 72);
+  }
+}
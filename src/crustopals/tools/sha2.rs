@@ -0,0 +1,623 @@
+use crustopals::byteorder::{BigEndian, ByteOrder};
+use crustopals::tools::merkle_damgard::{BlockBuffer, MerkleDamgard};
+use crustopals::tools::CryptoError;
+
+// The SHA-2 family (FIPS 180-4) with the same state export/import as
+// `sha1::Sha1`. SHA-224 is SHA-256 with another IV and a truncated digest,
+// and SHA-384 and SHA-512/256 are the same for SHA-512.
+//
+// The full width digests are the whole final state, so secret-prefix MACs
+// built on them extend exactly like SHA-1's. The truncated ones leave 32 to
+// 256 bits of the state out of the digest, which have to be guessed first.
+
+pub const SHA256_BLOCK_SIZE: usize = 64;
+pub const SHA512_BLOCK_SIZE: usize = 128;
+
+const SHA224_IV: [u32; 8] = [
+  0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511,
+  0x64f98fa7, 0xbefa4fa4,
+];
+const SHA256_IV: [u32; 8] = [
+  0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c,
+  0x1f83d9ab, 0x5be0cd19,
+];
+const SHA384_IV: [u64; 8] = [
+  0xcbbb9d5dc1059ed8,
+  0x629a292a367cd507,
+  0x9159015a3070dd17,
+  0x152fecd8f70e5939,
+  0x67332667ffc00b31,
+  0x8eb44a8768581511,
+  0xdb0c2e0d64f98fa7,
+  0x47b5481dbefa4fa4,
+];
+const SHA512_IV: [u64; 8] = [
+  0x6a09e667f3bcc908,
+  0xbb67ae8584caa73b,
+  0x3c6ef372fe94f82b,
+  0xa54ff53a5f1d36f1,
+  0x510e527fade682d1,
+  0x9b05688c2b3e6c1f,
+  0x1f83d9abfb41bd6b,
+  0x5be0cd19137e2179,
+];
+const SHA512_256_IV: [u64; 8] = [
+  0x22312194fc2bf72c,
+  0x9f555fa3c84c64c2,
+  0x2393b86b6f53b151,
+  0x963877195940eabd,
+  0x96283ee2a88effe3,
+  0xbe5e1e2553863992,
+  0x2b0199fc2c85b8aa,
+  0x0eb72ddc81c52ca2,
+];
+
+// first 32 bits of the fractional parts of the cube roots of the first 64
+// primes
+const K256: [u32; 64] = [
+  0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+  0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+  0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+  0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+  0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+  0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+  0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+  0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+  0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+  0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+  0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// the same to 64 bits, for the first 80 primes
+const K512: [u64; 80] = [
+  0x428a2f98d728ae22,
+  0x7137449123ef65cd,
+  0xb5c0fbcfec4d3b2f,
+  0xe9b5dba58189dbbc,
+  0x3956c25bf348b538,
+  0x59f111f1b605d019,
+  0x923f82a4af194f9b,
+  0xab1c5ed5da6d8118,
+  0xd807aa98a3030242,
+  0x12835b0145706fbe,
+  0x243185be4ee4b28c,
+  0x550c7dc3d5ffb4e2,
+  0x72be5d74f27b896f,
+  0x80deb1fe3b1696b1,
+  0x9bdc06a725c71235,
+  0xc19bf174cf692694,
+  0xe49b69c19ef14ad2,
+  0xefbe4786384f25e3,
+  0x0fc19dc68b8cd5b5,
+  0x240ca1cc77ac9c65,
+  0x2de92c6f592b0275,
+  0x4a7484aa6ea6e483,
+  0x5cb0a9dcbd41fbd4,
+  0x76f988da831153b5,
+  0x983e5152ee66dfab,
+  0xa831c66d2db43210,
+  0xb00327c898fb213f,
+  0xbf597fc7beef0ee4,
+  0xc6e00bf33da88fc2,
+  0xd5a79147930aa725,
+  0x06ca6351e003826f,
+  0x142929670a0e6e70,
+  0x27b70a8546d22ffc,
+  0x2e1b21385c26c926,
+  0x4d2c6dfc5ac42aed,
+  0x53380d139d95b3df,
+  0x650a73548baf63de,
+  0x766a0abb3c77b2a8,
+  0x81c2c92e47edaee6,
+  0x92722c851482353b,
+  0xa2bfe8a14cf10364,
+  0xa81a664bbc423001,
+  0xc24b8b70d0f89791,
+  0xc76c51a30654be30,
+  0xd192e819d6ef5218,
+  0xd69906245565a910,
+  0xf40e35855771202a,
+  0x106aa07032bbd1b8,
+  0x19a4c116b8d2d0c8,
+  0x1e376c085141ab53,
+  0x2748774cdf8eeb99,
+  0x34b0bcb5e19b48a8,
+  0x391c0cb3c5c95a63,
+  0x4ed8aa4ae3418acb,
+  0x5b9cca4f7763e373,
+  0x682e6ff3d6b2b8a3,
+  0x748f82ee5defb2fc,
+  0x78a5636f43172f60,
+  0x84c87814a1f0ab72,
+  0x8cc702081a6439ec,
+  0x90befffa23631e28,
+  0xa4506cebde82bde9,
+  0xbef9a3f7b2c67915,
+  0xc67178f2e372532b,
+  0xca273eceea26619c,
+  0xd186b8c721c0c207,
+  0xeada7dd6cde0eb1e,
+  0xf57d4f7fee6ed178,
+  0x06f067aa72176fba,
+  0x0a637dc5a2c898a6,
+  0x113f9804bef90dae,
+  0x1b710b35131c471b,
+  0x28db77f523047d84,
+  0x32caab7b40c72493,
+  0x3c9ebe0a15c9bebc,
+  0x431d67c49c100d4c,
+  0x4cc5d4becb3e42b6,
+  0x597f299cfc657e2a,
+  0x5fcb6fab3ad6faec,
+  0x6c44198c4a475817,
+];
+
+// `digest_size` is how much of the final state makes it into the digest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sha256State {
+  pub words: [u32; 8],
+  pub length: u64,
+  pub buffer: Vec<u8>,
+  pub digest_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sha512State {
+  pub words: [u64; 8],
+  pub length: u64,
+  pub buffer: Vec<u8>,
+  pub digest_size: usize,
+}
+
+#[derive(Clone)]
+pub struct Sha256 {
+  state: BlockBuffer<[u32; 8]>,
+  digest_size: usize,
+}
+
+#[derive(Clone)]
+pub struct Sha512 {
+  state: BlockBuffer<[u64; 8]>,
+  digest_size: usize,
+}
+
+impl Sha256 {
+  pub fn new() -> Sha256 {
+    Sha256 {
+      state: BlockBuffer::new(SHA256_IV),
+      digest_size: 32,
+    }
+  }
+
+  pub fn new_224() -> Sha256 {
+    Sha256 {
+      state: BlockBuffer::new(SHA224_IV),
+      digest_size: 28,
+    }
+  }
+
+  pub fn from_state(state: Sha256State) -> Result<Sha256, CryptoError> {
+    let buffered = BlockBuffer {
+      words: state.words,
+      length: state.length,
+      buffer: state.buffer,
+    };
+    buffered.check::<Sha256>()?;
+    check_digest_size(state.digest_size, 32)?;
+    Ok(Sha256 {
+      state: buffered,
+      digest_size: state.digest_size,
+    })
+  }
+
+  pub fn state(&self) -> Sha256State {
+    Sha256State {
+      words: self.state.words,
+      length: self.state.length,
+      buffer: self.state.buffer.clone(),
+      digest_size: self.digest_size,
+    }
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    self.state.update::<Sha256>(bytes, compress_256);
+  }
+
+  pub fn digest(&self) -> Vec<u8> {
+    let mut digest = vec![0u8; 32];
+    BigEndian::write_u32_into(
+      &self.state.finish::<Sha256>(compress_256),
+      &mut digest,
+    );
+    digest.truncate(self.digest_size);
+    digest
+  }
+}
+
+impl Default for Sha256 {
+  fn default() -> Sha256 {
+    Sha256::new()
+  }
+}
+
+impl Sha512 {
+  pub fn new() -> Sha512 {
+    Sha512 {
+      state: BlockBuffer::new(SHA512_IV),
+      digest_size: 64,
+    }
+  }
+
+  pub fn new_384() -> Sha512 {
+    Sha512 {
+      state: BlockBuffer::new(SHA384_IV),
+      digest_size: 48,
+    }
+  }
+
+  pub fn new_512_256() -> Sha512 {
+    Sha512 {
+      state: BlockBuffer::new(SHA512_256_IV),
+      digest_size: 32,
+    }
+  }
+
+  pub fn from_state(state: Sha512State) -> Result<Sha512, CryptoError> {
+    let buffered = BlockBuffer {
+      words: state.words,
+      length: state.length,
+      buffer: state.buffer,
+    };
+    buffered.check::<Sha512>()?;
+    check_digest_size(state.digest_size, 64)?;
+    Ok(Sha512 {
+      state: buffered,
+      digest_size: state.digest_size,
+    })
+  }
+
+  pub fn state(&self) -> Sha512State {
+    Sha512State {
+      words: self.state.words,
+      length: self.state.length,
+      buffer: self.state.buffer.clone(),
+      digest_size: self.digest_size,
+    }
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    self.state.update::<Sha512>(bytes, compress_512);
+  }
+
+  pub fn digest(&self) -> Vec<u8> {
+    let mut digest = vec![0u8; 64];
+    BigEndian::write_u64_into(
+      &self.state.finish::<Sha512>(compress_512),
+      &mut digest,
+    );
+    digest.truncate(self.digest_size);
+    digest
+  }
+}
+
+impl Default for Sha512 {
+  fn default() -> Sha512 {
+    Sha512::new()
+  }
+}
+
+pub fn sha224(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Sha256::new_224();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+pub fn sha256(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Sha256::new();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+pub fn sha384(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Sha512::new_384();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+pub fn sha512(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Sha512::new();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+pub fn sha512_256(bytes: &[u8]) -> Vec<u8> {
+  let mut hasher = Sha512::new_512_256();
+  hasher.update(bytes);
+  hasher.digest()
+}
+
+// Same as SHA-1's padding.
+pub fn sha256_padding(message_len: u64) -> Vec<u8> {
+  <Sha256 as MerkleDamgard>::padding(message_len)
+}
+
+// 128 byte blocks and a 128 bit length.
+pub fn sha512_padding(message_len: u64) -> Vec<u8> {
+  <Sha512 as MerkleDamgard>::padding(message_len)
+}
+
+// Only a full SHA-256 digest is the whole state.
+pub fn sha256_state_from_digest(
+  digest: &[u8],
+  length: u64,
+) -> Result<Sha256State, CryptoError> {
+  if digest.len() != 32 {
+    return Err(CryptoError::LengthMismatch {
+      left: 32,
+      right: digest.len(),
+    });
+  }
+  let mut words = [0u32; 8];
+  BigEndian::read_u32_into(digest, &mut words);
+  Ok(Sha256State {
+    words,
+    length,
+    buffer: vec![],
+    digest_size: 32,
+  })
+}
+
+// Only a full SHA-512 digest is the whole state.
+pub fn sha512_state_from_digest(
+  digest: &[u8],
+  length: u64,
+) -> Result<Sha512State, CryptoError> {
+  if digest.len() != 64 {
+    return Err(CryptoError::LengthMismatch {
+      left: 64,
+      right: digest.len(),
+    });
+  }
+  let mut words = [0u64; 8];
+  BigEndian::read_u64_into(digest, &mut words);
+  Ok(Sha512State {
+    words,
+    length,
+    buffer: vec![],
+    digest_size: 64,
+  })
+}
+
+fn check_digest_size(
+  digest_size: usize,
+  state_size: usize,
+) -> Result<(), CryptoError> {
+  if digest_size == 0 || digest_size > state_size {
+    return Err(CryptoError::InvalidDigestSize {
+      max: state_size,
+      actual: digest_size,
+    });
+  }
+  Ok(())
+}
+
+fn compress_256(words: &mut [u32; 8], block: &[u8]) {
+  let mut schedule = [0u32; 64];
+  BigEndian::read_u32_into(block, &mut schedule[..16]);
+  for t in 16..64 {
+    let s0 = schedule[t - 15].rotate_right(7)
+      ^ schedule[t - 15].rotate_right(18)
+      ^ (schedule[t - 15] >> 3);
+    let s1 = schedule[t - 2].rotate_right(17)
+      ^ schedule[t - 2].rotate_right(19)
+      ^ (schedule[t - 2] >> 10);
+    schedule[t] = schedule[t - 16]
+      .wrapping_add(s0)
+      .wrapping_add(schedule[t - 7])
+      .wrapping_add(s1);
+  }
+
+  let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *words;
+  for (k, w) in K256.iter().zip(schedule.iter()) {
+    let sum1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+    let choose = (e & f) ^ (!e & g);
+    let temp1 = h
+      .wrapping_add(sum1)
+      .wrapping_add(choose)
+      .wrapping_add(*k)
+      .wrapping_add(*w);
+    let sum0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+    let majority = (a & b) ^ (a & c) ^ (b & c);
+    let temp2 = sum0.wrapping_add(majority);
+    h = g;
+    g = f;
+    f = e;
+    e = d.wrapping_add(temp1);
+    d = c;
+    c = b;
+    b = a;
+    a = temp1.wrapping_add(temp2);
+  }
+  for (word, new) in words.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+    *word = word.wrapping_add(*new);
+  }
+}
+
+fn compress_512(words: &mut [u64; 8], block: &[u8]) {
+  let mut schedule = [0u64; 80];
+  BigEndian::read_u64_into(block, &mut schedule[..16]);
+  for t in 16..80 {
+    let s0 = schedule[t - 15].rotate_right(1)
+      ^ schedule[t - 15].rotate_right(8)
+      ^ (schedule[t - 15] >> 7);
+    let s1 = schedule[t - 2].rotate_right(19)
+      ^ schedule[t - 2].rotate_right(61)
+      ^ (schedule[t - 2] >> 6);
+    schedule[t] = schedule[t - 16]
+      .wrapping_add(s0)
+      .wrapping_add(schedule[t - 7])
+      .wrapping_add(s1);
+  }
+
+  let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *words;
+  for (k, w) in K512.iter().zip(schedule.iter()) {
+    let sum1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+    let choose = (e & f) ^ (!e & g);
+    let temp1 = h
+      .wrapping_add(sum1)
+      .wrapping_add(choose)
+      .wrapping_add(*k)
+      .wrapping_add(*w);
+    let sum0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+    let majority = (a & b) ^ (a & c) ^ (b & c);
+    let temp2 = sum0.wrapping_add(majority);
+    h = g;
+    g = f;
+    f = e;
+    e = d.wrapping_add(temp1);
+    d = c;
+    c = b;
+    b = a;
+    a = temp1.wrapping_add(temp2);
+  }
+  for (word, new) in words.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+    *word = word.wrapping_add(*new);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  extern crate hex;
+
+  use super::*;
+
+  const TWO_BLOCK: &str =
+    "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+  const FOUR_BLOCK: &str = "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+  #[test]
+  fn it_hashes_the_fips_180_examples_with_sha256() {
+    assert_eq!(
+      hex::encode(sha256(b"abc")),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+      hex::encode(sha256(b"")),
+      "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+      hex::encode(sha256(TWO_BLOCK.as_bytes())),
+      "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    assert_eq!(
+      hex::encode(sha224(b"abc")),
+      "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+    );
+    assert_eq!(
+      hex::encode(sha224(TWO_BLOCK.as_bytes())),
+      "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525"
+    );
+  }
+
+  #[test]
+  fn it_hashes_the_fips_180_examples_with_sha512() {
+    assert_eq!(
+      hex::encode(sha512(b"abc")),
+      "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+       2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+    );
+    assert_eq!(
+      hex::encode(sha512(FOUR_BLOCK.as_bytes())),
+      "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+       501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+    );
+    assert_eq!(
+      hex::encode(sha384(b"abc")),
+      "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded163\
+       1a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"
+    );
+    assert_eq!(
+      hex::encode(sha384(FOUR_BLOCK.as_bytes())),
+      "09330c33f71147e83d192fc782cd1b4753111b173b3b05d2\
+       2fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039"
+    );
+    assert_eq!(
+      hex::encode(sha512_256(b"abc")),
+      "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23"
+    );
+    assert_eq!(
+      hex::encode(sha512_256(b"")),
+      "c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a"
+    );
+  }
+
+  #[test]
+  fn truncated_digests_dont_resume() {
+    let message = b"a message of some length";
+    let digest = sha384(message);
+    assert!(sha512_state_from_digest(&digest, 128).is_err());
+    assert!(sha256_state_from_digest(&sha224(message), 64).is_err());
+
+    // filling in the missing 128 bits with a guess gives a different state
+    let padded =
+      [&message[..], &sha512_padding(message.len() as u64)[..]].concat();
+    let mut words = [0u64; 8];
+    BigEndian::read_u64_into(&digest, &mut words[..6]);
+    let mut resumed = Sha512::from_state(Sha512State {
+      words,
+      length: padded.len() as u64,
+      buffer: vec![],
+      digest_size: 48,
+    })
+    .unwrap();
+    resumed.update(b";admin=true");
+
+    assert_ne!(
+      resumed.digest(),
+      sha384(&[&padded[..], b";admin=true"].concat())
+    );
+
+    // SHA-512/256 leaves out half the state, so the same goes for it
+    let digest = sha512_256(message);
+    assert!(sha512_state_from_digest(&digest, 128).is_err());
+    let mut words = [0u64; 8];
+    BigEndian::read_u64_into(&digest, &mut words[..4]);
+    let mut resumed = Sha512::from_state(Sha512State {
+      words,
+      length: padded.len() as u64,
+      buffer: vec![],
+      digest_size: 32,
+    })
+    .unwrap();
+    resumed.update(b";admin=true");
+
+    assert_ne!(
+      resumed.digest(),
+      sha512_256(&[&padded[..], b";admin=true"].concat())
+    );
+  }
+
+  #[test]
+  fn it_rejects_digests_wider_than_the_state() {
+    let mut state = Sha256::new().state();
+    state.digest_size = 40;
+
+    assert_eq!(
+      Sha256::from_state(state).err(),
+      Some(CryptoError::InvalidDigestSize {
+        max: 32,
+        actual: 40
+      })
+    );
+  }
+
+  #[test]
+  fn padding_ends_on_a_block_boundary() {
+    for len in 0..300u64 {
+      assert_eq!((len as usize + sha256_padding(len).len()) % 64, 0);
+      assert_eq!((len as usize + sha512_padding(len).len()) % 128, 0);
+    }
+    assert_eq!(sha512_padding(111).len(), 17);
+    assert_eq!(sha512_padding(112).len(), 144);
+  }
+}
@@ -0,0 +1,213 @@
+use crustopals::tools::block_cipher::BlockCipher;
+use crustopals::tools::CryptoError;
+
+// The toy substitution-permutation network from Howard Heys' "A Tutorial on
+// Linear and Differential Cryptanalysis": a 16 bit block split into four
+// 4 bit S-boxes, a bit transposition between rounds and an independent 16 bit
+// subkey mixed in before every round plus one after the last.
+// S-boxes and bits are numbered from the most significant end, as in the
+// tutorial, so S-box 0 is the top nibble.
+pub const HEYS_SBOX: [u8; 16] = [
+  0xe, 0x4, 0xd, 0x1, 0x2, 0xf, 0xb, 0x8, 0x3, 0xa, 0x6, 0xc, 0x5, 0x9, 0x0,
+  0x7,
+];
+
+pub const HEYS_ROUNDS: usize = 4;
+
+// bit i of the block moves to bit PERMUTATION[i]
+const PERMUTATION: [usize; 16] =
+  [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15];
+
+pub struct Spn {
+  subkeys: Vec<u16>,
+}
+
+impl Spn {
+  // the tutorial's 4 round cipher, keyed with five 16 bit subkeys
+  pub fn new(key: &[u8]) -> Result<Spn, CryptoError> {
+    Spn::with_rounds(key, HEYS_ROUNDS)
+  }
+
+  // `rounds` rounds need rounds + 1 subkeys, read big endian from the key
+  pub fn with_rounds(key: &[u8], rounds: usize) -> Result<Spn, CryptoError> {
+    let expected = 2 * (rounds + 1);
+    if rounds == 0 || key.len() != expected {
+      return Err(CryptoError::InvalidKeyLength {
+        expected,
+        actual: key.len(),
+      });
+    }
+    let subkeys = key
+      .chunks(2)
+      .map(|pair| ((pair[0] as u16) << 8) | pair[1] as u16)
+      .collect();
+    Ok(Spn { subkeys })
+  }
+
+  pub fn rounds(&self) -> usize {
+    self.subkeys.len() - 1
+  }
+
+  pub fn encrypt(&self, block: u16) -> u16 {
+    let rounds = self.rounds();
+    let mut state = block;
+    for round in 0..rounds {
+      state = substitute(&HEYS_SBOX, state ^ self.subkeys[round]);
+      // the last round swaps its permutation for the final key mix
+      if round != rounds - 1 {
+        state = permute(state);
+      }
+    }
+    state ^ self.subkeys[rounds]
+  }
+
+  pub fn decrypt(&self, block: u16) -> u16 {
+    let rounds = self.rounds();
+    let inv_sbox = invert_sbox(&HEYS_SBOX);
+    let mut state = block ^ self.subkeys[rounds];
+    for round in (0..rounds).rev() {
+      if round != rounds - 1 {
+        // the transposition is its own inverse
+        state = permute(state);
+      }
+      state = substitute(&inv_sbox, state) ^ self.subkeys[round];
+    }
+    state
+  }
+}
+
+impl BlockCipher for Spn {
+  fn block_size(&self) -> usize {
+    2
+  }
+
+  fn encrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    word_to_block(self.encrypt(block_to_word(block)))
+  }
+
+  fn decrypt_block(&self, block: &[u8]) -> Vec<u8> {
+    word_to_block(self.decrypt(block_to_word(block)))
+  }
+}
+
+// Runs each nibble of the block through the S-box.
+pub fn substitute(sbox: &[u8; 16], block: u16) -> u16 {
+  (0..4).fold(0, |acc, idx| {
+    acc | ((sbox[nibble(block, idx) as usize] as u16) << nibble_shift(idx))
+  })
+}
+
+pub fn permute(block: u16) -> u16 {
+  (0..16).fold(0, |acc, bit| {
+    if block & (0x8000 >> bit) != 0 {
+      acc | (0x8000 >> PERMUTATION[bit])
+    } else {
+      acc
+    }
+  })
+}
+
+pub fn invert_sbox(sbox: &[u8; 16]) -> [u8; 16] {
+  let mut inverse = [0u8; 16];
+  for (input, output) in sbox.iter().enumerate() {
+    inverse[*output as usize] = input as u8;
+  }
+  inverse
+}
+
+// The 4 bits feeding S-box `idx`.
+pub fn nibble(block: u16, idx: usize) -> u8 {
+  ((block >> nibble_shift(idx)) & 0xf) as u8
+}
+
+fn nibble_shift(idx: usize) -> usize {
+  12 - 4 * idx
+}
+
+fn block_to_word(block: &[u8]) -> u16 {
+  ((block[0] as u16) << 8) | block[1] as u16
+}
+
+fn word_to_block(word: u16) -> Vec<u8> {
+  vec![(word >> 8) as u8, word as u8]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::{aes, block_cipher};
+
+  #[test]
+  fn permutes_bits_as_a_transpose() {
+    assert_eq!(permute(0x8000), 0x8000);
+    assert_eq!(permute(0x4000), 0x0800);
+    assert_eq!(permute(0x0b00), 0x0040 | 0x4000 | 0x0004);
+    assert_eq!(permute(permute(0x1234)), 0x1234);
+  }
+
+  #[test]
+  fn substitutes_every_nibble() {
+    assert_eq!(substitute(&HEYS_SBOX, 0x0000), 0xeeee);
+    assert_eq!(substitute(&HEYS_SBOX, 0x0123), 0xe4d1);
+    let inverse = invert_sbox(&HEYS_SBOX);
+    assert_eq!(substitute(&inverse, 0xe4d1), 0x0123);
+  }
+
+  #[test]
+  fn it_round_trips_blocks() {
+    for rounds in 1..6 {
+      let key = aes::generate_rand_bytes(2 * (rounds + 1));
+      let spn = Spn::with_rounds(&key, rounds).unwrap();
+      for block in [0x0000, 0x1234, 0xbeef, 0xffff].iter() {
+        assert_eq!(spn.decrypt(spn.encrypt(*block)), *block);
+      }
+    }
+  }
+
+  #[test]
+  fn a_single_round_is_key_sbox_key() {
+    let spn = Spn::with_rounds(&[0x12, 0x34, 0xab, 0xcd], 1).unwrap();
+
+    assert_eq!(spn.encrypt(0), substitute(&HEYS_SBOX, 0x1234) ^ 0xabcd);
+  }
+
+  #[test]
+  fn round_trips_in_cbc_mode() {
+    let spn = Spn::new(&aes::generate_rand_bytes(10)).unwrap();
+    let iv = aes::generate_rand_bytes(2);
+    let message = "sixteen bit blocks".as_bytes();
+    let ciphertext = block_cipher::encrypt_cbc(&spn, message, &iv).unwrap();
+
+    assert_eq!(
+      block_cipher::decrypt_cbc(&spn, &ciphertext, &iv).unwrap(),
+      message
+    );
+  }
+
+  #[test]
+  fn runs_out_of_ctr_counter_after_256_blocks() {
+    let spn = Spn::new(&aes::generate_rand_bytes(10)).unwrap();
+    let stream = block_cipher::generate_ctr_stream(&spn, &[0], 512).unwrap();
+
+    assert_eq!(stream.len(), 512);
+    assert_eq!(
+      block_cipher::generate_ctr_stream(&spn, &[0], 513).err(),
+      Some(CryptoError::OutputTooLong {
+        max: 512,
+        actual: 513,
+      })
+    );
+  }
+
+  #[test]
+  fn rejects_keys_that_do_not_match_the_rounds() {
+    assert_eq!(
+      Spn::new(&[0u8; 8]).err(),
+      Some(CryptoError::InvalidKeyLength {
+        expected: 10,
+        actual: 8,
+      })
+    );
+    assert!(Spn::with_rounds(&[0u8; 2], 0).is_err());
+  }
+}
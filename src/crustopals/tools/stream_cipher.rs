@@ -0,0 +1,83 @@
+use crustopals::tools::block_cipher;
+use crustopals::tools::block_cipher::BlockCipher;
+use crustopals::tools::CryptoError;
+
+// A keystream generator already bound to its key and nonce. Encryption and
+// decryption are the same xor, so a fixed nonce means a reused keystream no
+// matter which cipher produces it.
+pub trait StreamCipher {
+  fn keystream(&self, len: usize) -> Vec<u8>;
+
+  fn apply_keystream(&self, bytes: &[u8]) -> Vec<u8> {
+    self
+      .keystream(bytes.len())
+      .iter()
+      .zip(bytes.iter())
+      .map(|(k, b)| k ^ b)
+      .collect()
+  }
+}
+
+// A block cipher in CTR mode, see `block_cipher::generate_ctr_stream` for
+// the counter layout.
+pub struct Ctr<C: BlockCipher> {
+  cipher: C,
+  nonce: Vec<u8>,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+  pub fn new(cipher: C, nonce: &[u8]) -> Result<Ctr<C>, CryptoError> {
+    let expected = cipher.block_size() - cipher.block_size() / 2;
+    if nonce.len() != expected {
+      return Err(CryptoError::InvalidIvLength {
+        expected,
+        actual: nonce.len(),
+      });
+    }
+    Ok(Ctr {
+      cipher,
+      nonce: nonce.to_vec(),
+    })
+  }
+}
+
+impl<C: BlockCipher> StreamCipher for Ctr<C> {
+  fn keystream(&self, len: usize) -> Vec<u8> {
+    block_cipher::generate_ctr_stream(&self.cipher, &self.nonce, len)
+      .expect("the nonce length is checked in Ctr::new")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::tools::aes;
+  use crustopals::tools::aes::Aes128;
+
+  #[test]
+  fn ctr_matches_the_aes_ctr_functions() {
+    let key = "YELLOW SUBMARINE".as_bytes();
+    let nonce = [7u8; 8];
+    let message = "a message that spans a couple of blocks".as_bytes();
+    let ctr = Ctr::new(Aes128::new(key).unwrap(), &nonce).unwrap();
+
+    assert_eq!(
+      ctr.apply_keystream(message),
+      aes::encrypt_ctr(message, key, &nonce).unwrap()
+    );
+    assert_eq!(ctr.apply_keystream(&ctr.apply_keystream(message)), message);
+  }
+
+  #[test]
+  fn ctr_rejects_the_wrong_nonce_size() {
+    let aes = Aes128::new(&aes::generate_key()).unwrap();
+
+    assert_eq!(
+      Ctr::new(aes, &[0u8; 16]).err(),
+      Some(CryptoError::InvalidIvLength {
+        expected: 8,
+        actual: 16,
+      })
+    );
+  }
+}
@@ -0,0 +1,164 @@
+use crustopals::tools::aes::Aes128;
+use crustopals::tools::block_cipher::BlockCipher;
+use crustopals::tools::merkle_damgard::{strengthening, Endianness};
+use crustopals::tools::CryptoError;
+
+// A Merkle-Damgard hash small enough to attack: the compression function
+// encrypts the chaining state (zero padded to a block) under the message
+// block as the AES key and keeps the first `state_size` bytes. With a 16 to
+// 32 bit state a birthday collision is 2^8 to 2^16 compressions, which is
+// what the generic attacks on iterated hashes (multicollisions, second
+// preimages, herding) need to run in a test.
+
+pub const BLOCK_SIZE: usize = 16;
+
+const IV: [u8; 16] = [
+  0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76,
+  0x54, 0x32, 0x10,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToyHash {
+  iv: Vec<u8>,
+}
+
+impl ToyHash {
+  pub fn new(state_size: usize) -> Result<ToyHash, CryptoError> {
+    if state_size == 0 || state_size > BLOCK_SIZE {
+      return Err(CryptoError::InvalidStateSize {
+        max: BLOCK_SIZE,
+        actual: state_size,
+      });
+    }
+    Ok(ToyHash {
+      iv: IV[..state_size].to_vec(),
+    })
+  }
+
+  // Another hash of the same state size, starting from `iv`.
+  pub fn with_iv(iv: &[u8]) -> Result<ToyHash, CryptoError> {
+    let mut hash = ToyHash::new(iv.len())?;
+    hash.iv = iv.to_vec();
+    Ok(hash)
+  }
+
+  pub fn state_size(&self) -> usize {
+    self.iv.len()
+  }
+
+  pub fn iv(&self) -> &[u8] {
+    &self.iv
+  }
+
+  // Fails on anything but a 16 byte block, which is the AES key.
+  pub fn compress(
+    &self,
+    state: &[u8],
+    block: &[u8],
+  ) -> Result<Vec<u8>, CryptoError> {
+    if block.len() != BLOCK_SIZE {
+      return Err(CryptoError::InvalidBlockLength {
+        block_size: BLOCK_SIZE,
+        actual: block.len(),
+      });
+    }
+    let mut padded_state = state.to_vec();
+    padded_state.resize(BLOCK_SIZE, 0);
+    let mut next = Aes128::new(block)?.encrypt_block(&padded_state);
+    next.truncate(self.state_size());
+    Ok(next)
+  }
+
+  // Runs the compression function over `blocks` (a whole number of them)
+  // from `state`, with no padding.
+  pub fn iterate(
+    &self,
+    state: &[u8],
+    blocks: &[u8],
+  ) -> Result<Vec<u8>, CryptoError> {
+    if !blocks.len().is_multiple_of(BLOCK_SIZE) {
+      return Err(CryptoError::InvalidBlockLength {
+        block_size: BLOCK_SIZE,
+        actual: blocks.len(),
+      });
+    }
+    blocks
+      .chunks(BLOCK_SIZE)
+      .try_fold(state.to_vec(), |state, block| self.compress(&state, block))
+  }
+
+  pub fn hash(&self, message: &[u8]) -> Vec<u8> {
+    let padded = [message, &padding(message.len() as u64)[..]].concat();
+    self
+      .iterate(&self.iv, &padded)
+      .expect("padding fills out the last block")
+  }
+}
+
+// The usual strengthening: a 1 bit, zeros and the message length in bits
+// as a big endian u64, out to a whole block.
+pub fn padding(message_len: u64) -> Vec<u8> {
+  strengthening(message_len, BLOCK_SIZE, 8, Endianness::Big)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crustopals::byteorder::{BigEndian, ByteOrder};
+  use crustopals::tools::aes;
+
+  #[test]
+  fn it_truncates_aes_to_the_state_size() {
+    let hash = ToyHash::new(3).unwrap();
+    let block = b"YELLOW SUBMARINE";
+    let full = Aes128::new(block)
+      .unwrap()
+      .encrypt_block(&[&IV[..3], &[0u8; 13][..]].concat());
+
+    assert_eq!(hash.compress(hash.iv(), block), Ok(full[..3].to_vec()));
+    assert_eq!(hash.hash(b"").len(), 3);
+    assert_eq!(
+      ToyHash::new(17),
+      Err(CryptoError::InvalidStateSize {
+        max: 16,
+        actual: 17
+      })
+    );
+  }
+
+  #[test]
+  fn it_pads_to_whole_blocks() {
+    for len in 0..50u64 {
+      let padding = padding(len);
+
+      assert_eq!((len + padding.len() as u64) % 16, 0);
+      assert_eq!(BigEndian::read_u64(&padding[padding.len() - 8..]), len * 8);
+    }
+  }
+
+  #[test]
+  fn it_iterates_the_compression_function() {
+    let hash = ToyHash::with_iv(&[1, 2]).unwrap();
+    let message = aes::generate_rand_bytes(40);
+    let padded = [&message[..], &padding(40)[..]].concat();
+    let state = hash.iterate(&[1, 2], &padded[..48]).unwrap();
+
+    assert_eq!(
+      Ok(hash.hash(&message)),
+      hash.compress(&state, &padded[48..])
+    );
+  }
+
+  #[test]
+  fn it_rejects_partial_blocks() {
+    let hash = ToyHash::new(2).unwrap();
+    let partial = CryptoError::InvalidBlockLength {
+      block_size: 16,
+      actual: 15,
+    };
+
+    assert_eq!(hash.compress(hash.iv(), &[0u8; 15]), Err(partial.clone()));
+    assert_eq!(hash.iterate(hash.iv(), &[0u8; 15]), Err(partial));
+    assert!(hash.iterate(hash.iv(), &[0u8; 40]).is_err());
+  }
+}
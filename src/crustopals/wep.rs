@@ -0,0 +1,351 @@
+use crustopals::byteorder::{ByteOrder, LittleEndian};
+use crustopals::tools::aes;
+use crustopals::tools::crc32::{crc32, IEEE_CRC};
+use crustopals::tools::rc4::Rc4;
+use crustopals::tools::stream_cipher::StreamCipher;
+use crustopals::tools::CryptoError;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// WEP encrypts every frame with RC4 under IV || root key, where the 24 bit
+// IV travels in the clear, and protects integrity with a CRC-32 "ICV"
+// inside the encryption. Both choices are fatal:
+// - the per packet keys are related, and Klein's RC4 correlation turns the
+//   first keystream bytes of each frame into a weak vote on sums of root
+//   key bytes. PTW (Tews, Weinmann, Pyshkin) tallies those votes over every
+//   captured frame, rather than waiting for FMS's few weak IVs, and needs
+//   tens of thousands of ARP frames, whose first 16 bytes are fixed.
+// - CRC-32 is affine, so bits flipped in the ciphertext can be matched by
+//   flipping the right bits in the encrypted ICV.
+
+pub const IV_SIZE: usize = 3;
+pub const ICV_SIZE: usize = 4;
+
+// LLC/SNAP header for ARP followed by the fixed start of an ARP request:
+// Ethernet, IPv4, 6 byte MACs, 4 byte addresses, opcode 1.
+pub const ARP_HEADER: [u8; 16] = [
+  0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x08, 0x06, 0x00, 0x01, 0x08, 0x00, 0x06,
+  0x04, 0x00, 0x01,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WepFrame {
+  pub iv: [u8; IV_SIZE],
+  // RC4(IV || root key) xor (payload || ICV)
+  pub body: Vec<u8>,
+}
+
+pub struct AccessPoint {
+  root_key: Vec<u8>,
+}
+
+impl AccessPoint {
+  // WEP-40 or WEP-104, i.e. a 5 or 13 byte root key.
+  pub fn new(root_key: &[u8]) -> Result<AccessPoint, CryptoError> {
+    if root_key.len() != 5 && root_key.len() != 13 {
+      return Err(CryptoError::UnsupportedKeyLength {
+        supported: &[5, 13],
+        actual: root_key.len(),
+      });
+    }
+    Ok(AccessPoint {
+      root_key: root_key.to_vec(),
+    })
+  }
+
+  pub fn encrypt(&self, iv: [u8; IV_SIZE], payload: &[u8]) -> WepFrame {
+    let mut icv = [0u8; ICV_SIZE];
+    LittleEndian::write_u32(&mut icv, crc32(payload));
+    WepFrame {
+      iv,
+      body: per_packet_cipher(&iv, &self.root_key)
+        .apply_keystream(&[payload, &icv[..]].concat()),
+    }
+  }
+
+  // The payload, if the ICV checks out.
+  pub fn decrypt(&self, frame: &WepFrame) -> Result<Vec<u8>, CryptoError> {
+    decrypt_frame(&self.root_key, frame)
+  }
+
+  // What an eavesdropper collects from a busy network: ARP requests, each
+  // under a random IV.
+  pub fn capture_arp_traffic(&self, count: usize) -> Vec<WepFrame> {
+    (0..count)
+      .map(|_| {
+        let mut iv = [0u8; IV_SIZE];
+        iv.copy_from_slice(&aes::generate_rand_bytes(IV_SIZE));
+        self.encrypt(iv, &arp_request())
+      })
+      .collect()
+  }
+}
+
+pub fn decrypt_frame(
+  root_key: &[u8],
+  frame: &WepFrame,
+) -> Result<Vec<u8>, CryptoError> {
+  if frame.body.len() < ICV_SIZE {
+    return Err(CryptoError::AuthenticationFailed);
+  }
+  let plaintext =
+    per_packet_cipher(&frame.iv, root_key).apply_keystream(&frame.body);
+  let (payload, icv) = plaintext.split_at(plaintext.len() - ICV_SIZE);
+  if LittleEndian::read_u32(icv) != crc32(payload) {
+    return Err(CryptoError::AuthenticationFailed);
+  }
+  Ok(payload.to_vec())
+}
+
+// An ARP request from a random host: the fixed header, then the sender's
+// MAC and IP, an empty target MAC and the target IP.
+pub fn arp_request() -> Vec<u8> {
+  let mut request = ARP_HEADER.to_vec();
+  request.extend(aes::generate_rand_bytes(6));
+  request.extend(&[192, 168, 1]);
+  request.extend(aes::generate_rand_bytes(1));
+  request.extend(&[0u8; 6]);
+  request.extend(&[192, 168, 1, 1]);
+  request
+}
+
+// PTW key recovery from captured ARP frames. For sigma_i, the sum of root
+// key bytes 0..=i, each frame votes
+//   S3^-1[(3 + i) - X[2 + i]] - (j3 + S3[3] + ... + S3[3 + i])
+// where S3 and j3 are the RC4 state after the three key schedule steps that
+// only use the IV, and X is the keystream. The right sum only wins about
+// 1.36/256 of the votes, so rather than trusting the winners, keys are
+// tried best first by how many votes they give up against them, up to
+// `max_keys` of them, until one decrypts captured frames with a valid ICV.
+pub fn recover_key(
+  frames: &[WepFrame],
+  key_len: usize,
+  max_keys: usize,
+) -> Option<Vec<u8>> {
+  let ranked: Vec<Vec<(u8, u32)>> = sigma_votes(frames, key_len)
+    .iter()
+    .map(|tally| {
+      let mut sums: Vec<u8> = (0..=255).collect();
+      sums.sort_by(|a, b| tally[*b as usize].cmp(&tally[*a as usize]));
+      let best = tally[sums[0] as usize];
+      sums
+        .into_iter()
+        .map(|sum| (sum, best - tally[sum as usize]))
+        .collect()
+    })
+    .collect();
+  let checks: Vec<&WepFrame> = frames.iter().take(2).collect();
+  if checks.is_empty() {
+    return None;
+  }
+
+  // Every rank combination is reached exactly once by only ever demoting
+  // the byte last demoted or one after it, and demoting never lowers the
+  // deficit, so popping the smallest deficit enumerates keys best first.
+  let mut queue = BinaryHeap::new();
+  queue.push(Reverse((0u32, vec![0usize; key_len], 0usize)));
+  let mut tried = 0;
+  while let Some(Reverse((deficit, ranks, last))) = queue.pop() {
+    if tried == max_keys {
+      return None;
+    }
+    tried += 1;
+    let key = key_from_sums(
+      &ranks
+        .iter()
+        .zip(ranked.iter())
+        .map(|(rank, sums)| sums[*rank].0)
+        .collect::<Vec<u8>>(),
+    );
+    if checks
+      .iter()
+      .all(|frame| decrypt_frame(&key, frame).is_ok())
+    {
+      return Some(key);
+    }
+    for idx in last..key_len {
+      if ranks[idx] + 1 < 256 {
+        let mut demoted = ranks.clone();
+        demoted[idx] += 1;
+        let extra = ranked[idx][ranks[idx] + 1].1 - ranked[idx][ranks[idx]].1;
+        queue.push(Reverse((deficit + extra, demoted, idx)));
+      }
+    }
+  }
+  None
+}
+
+// The PTW tallies: for each root key byte i, how many frames voted for each
+// value of sigma_i.
+pub fn sigma_votes(frames: &[WepFrame], key_len: usize) -> Vec<Vec<u32>> {
+  let mut votes = vec![vec![0u32; 256]; key_len];
+  for frame in frames.iter() {
+    let keystream: Vec<u8> = frame
+      .body
+      .iter()
+      .zip(ARP_HEADER.iter())
+      .map(|(c, p)| c ^ p)
+      .collect();
+    if keystream.len() < key_len + 2 {
+      continue;
+    }
+    let mut s = [0u8; 256];
+    for (i, byte) in s.iter_mut().enumerate() {
+      *byte = i as u8;
+    }
+    let mut j = 0u8;
+    for (i, iv_byte) in frame.iv.iter().enumerate() {
+      j = j.wrapping_add(s[i]).wrapping_add(*iv_byte);
+      s.swap(i, j as usize);
+    }
+    let mut inverse = [0u8; 256];
+    for (i, byte) in s.iter().enumerate() {
+      inverse[*byte as usize] = i as u8;
+    }
+    let mut sum = j;
+    for (i, tally) in votes.iter_mut().enumerate() {
+      sum = sum.wrapping_add(s[3 + i]);
+      let target = ((3 + i) as u8).wrapping_sub(keystream[2 + i]);
+      let sigma = inverse[target as usize].wrapping_sub(sum);
+      tally[sigma as usize] += 1;
+    }
+  }
+  votes
+}
+
+// Root key bytes from the running sums sigma_0, sigma_1, ...
+fn key_from_sums(sums: &[u8]) -> Vec<u8> {
+  sums
+    .iter()
+    .scan(0u8, |previous, sum| {
+      let byte = sum.wrapping_sub(*previous);
+      *previous = *sum;
+      Some(byte)
+    })
+    .collect()
+}
+
+// Xors `delta` into the start of a frame's payload without the key. The
+// change that makes to the CRC doesn't depend on the payload, so xoring it
+// into the encrypted ICV keeps the frame valid.
+pub fn flip_bits(
+  frame: &WepFrame,
+  delta: &[u8],
+) -> Result<WepFrame, CryptoError> {
+  if frame.body.len() < ICV_SIZE {
+    return Err(CryptoError::LengthMismatch {
+      left: ICV_SIZE,
+      right: frame.body.len(),
+    });
+  }
+  let payload_len = frame.body.len() - ICV_SIZE;
+  if delta.len() > payload_len {
+    return Err(CryptoError::LengthMismatch {
+      left: payload_len,
+      right: delta.len(),
+    });
+  }
+  let mut padded = delta.to_vec();
+  padded.resize(payload_len, 0);
+  let mut icv_delta = [0u8; ICV_SIZE];
+  LittleEndian::write_u32(&mut icv_delta, IEEE_CRC.flip_delta(&padded));
+  padded.extend(icv_delta.iter());
+  Ok(WepFrame {
+    iv: frame.iv,
+    body: frame
+      .body
+      .iter()
+      .zip(padded.iter())
+      .map(|(c, d)| c ^ d)
+      .collect(),
+  })
+}
+
+fn per_packet_cipher(iv: &[u8], root_key: &[u8]) -> Rc4 {
+  Rc4::new(&[iv, root_key].concat()).expect("WEP keys are 8 or 16 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn the_access_point_checks_the_icv() {
+    let ap = AccessPoint::new(&aes::generate_rand_bytes(13)).unwrap();
+    let payload = "GET /index.html HTTP/1.1".as_bytes();
+    let mut frame = ap.encrypt([1, 2, 3], payload);
+
+    assert_eq!(ap.decrypt(&frame).unwrap(), payload);
+    frame.body[0] ^= 1;
+    assert_eq!(ap.decrypt(&frame), Err(CryptoError::AuthenticationFailed));
+    assert_eq!(
+      AccessPoint::new(&[0u8; 16]).err(),
+      Some(CryptoError::UnsupportedKeyLength {
+        supported: &[5, 13],
+        actual: 16
+      })
+    );
+  }
+
+  #[test]
+  fn it_flips_plaintext_bits_through_the_icv() {
+    let ap = AccessPoint::new(&aes::generate_rand_bytes(5)).unwrap();
+    let payload = "transfer amount=0100 to=alice".as_bytes();
+    let wanted = "transfer amount=9999 to=mallo".as_bytes();
+    let frame = ap.encrypt([9, 9, 9], payload);
+    let delta: Vec<u8> = payload
+      .iter()
+      .zip(wanted.iter())
+      .map(|(p, w)| p ^ w)
+      .collect();
+
+    let flipped = flip_bits(&frame, &delta).unwrap();
+    assert_eq!(ap.decrypt(&flipped).unwrap(), wanted);
+    let flipped = flip_bits(&frame, &[0, 0, 1]).unwrap();
+    assert_eq!(ap.decrypt(&flipped).unwrap()[2], payload[2] ^ 1);
+  }
+
+  #[test]
+  fn it_rejects_deltas_that_dont_fit_the_frame() {
+    let ap = AccessPoint::new(&aes::generate_rand_bytes(5)).unwrap();
+    let frame = ap.encrypt([9, 9, 9], b"short");
+    let truncated = WepFrame {
+      iv: frame.iv,
+      body: frame.body[..3].to_vec(),
+    };
+
+    assert_eq!(
+      flip_bits(&frame, &[1; 6]),
+      Err(CryptoError::LengthMismatch { left: 5, right: 6 })
+    );
+    assert_eq!(
+      flip_bits(&truncated, &[]),
+      Err(CryptoError::LengthMismatch { left: 4, right: 3 })
+    );
+  }
+
+  #[test]
+  fn it_recovers_a_wep_40_key_with_ptw() {
+    let key = aes::generate_rand_bytes(5);
+    let frames = AccessPoint::new(&key).unwrap().capture_arp_traffic(60_000);
+
+    assert_eq!(recover_key(&frames, 5, 1 << 14), Some(key));
+  }
+
+  #[test]
+  fn it_recovers_a_wep_104_key_with_ptw() {
+    let key = aes::generate_rand_bytes(13);
+    let frames = AccessPoint::new(&key).unwrap().capture_arp_traffic(120_000);
+
+    assert_eq!(recover_key(&frames, 13, 1 << 16), Some(key));
+  }
+
+  #[test]
+  fn too_little_traffic_finds_nothing() {
+    let key = aes::generate_rand_bytes(13);
+    let frames = AccessPoint::new(&key).unwrap().capture_arp_traffic(100);
+
+    assert_eq!(recover_key(&frames, 13, 1000), None);
+    assert_eq!(recover_key(&[], 13, 1000), None);
+  }
+}